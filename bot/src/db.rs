@@ -2,6 +2,10 @@ use {
     crate::{
         data_center_info::{DataCenterId, DataCenterInfo},
         generic_stake_pool::ValidatorStakeState,
+        notes::{
+            BlockProductionStats, EpochNote, StakeAction, StakeStateReason, ValidatorNote,
+            ValidatorStakeAction,
+        },
     },
     log::*,
     serde::{Deserialize, Serialize},
@@ -15,7 +19,7 @@ use {
 };
 
 #[derive(Default, Clone, Deserialize, Serialize)]
-pub struct ValidatorClassification {
+pub struct ValidatorClassificationV1 {
     pub identity: Pubkey, // Validator identity
     pub vote_address: Pubkey,
 
@@ -42,31 +46,47 @@ pub struct ValidatorClassification {
     pub participant: Option<Pubkey>,
 }
 
-impl ValidatorClassification {
-    pub fn stake_state_streak(&self) -> usize {
-        let mut streak = 1;
+#[derive(Default, Clone, Deserialize, Serialize)]
+pub struct ValidatorClassification {
+    pub identity: Pubkey, // Validator identity
+    pub vote_address: Pubkey,
 
-        if let Some(ref stake_states) = self.stake_states {
-            while streak < stake_states.len() && stake_states[0].0 == stake_states[streak].0 {
-                streak += 1;
-            }
-        }
-        streak
-    }
+    pub stake_state: ValidatorStakeState,
+    pub stake_state_reason: StakeStateReason,
 
-    // Was the validator staked for at last `n` of the last `m` epochs?
-    pub fn staked_for(&self, n: usize, m: usize) -> bool {
-        self.stake_states
-            .as_ref()
-            .map(|stake_states| {
-                stake_states
-                    .iter()
-                    .take(m)
-                    .filter(|(stake_state, _)| *stake_state != ValidatorStakeState::None)
-                    .count()
-                    >= n
-            })
-            .unwrap_or_default()
+    // Summary of the action was taken this epoch to advance the validator's stake
+    pub stake_action: Option<ValidatorStakeAction>,
+
+    // Informational notes regarding this validator
+    pub notes: Vec<ValidatorNote>,
+
+    // Map of data center to number of times the validator has been observed there.
+    pub data_center_residency: HashMap<DataCenterId, usize>,
+
+    // The data center that the validator was observed at for this classification
+    pub current_data_center: DataCenterId,
+
+    // The identity of the staking program participant, used to establish a link between
+    // testnet and mainnet validator classifications
+    pub participant: Option<Pubkey>,
+}
+
+impl From<ValidatorClassificationV1> for ValidatorClassification {
+    // `stake_states` is dropped, `migrate_store` moves it into the `StakeStateHistory` first
+    fn from(v1: ValidatorClassificationV1) -> Self {
+        Self {
+            identity: v1.identity,
+            vote_address: v1.vote_address,
+            stake_state: v1.stake_state,
+            stake_state_reason: StakeStateReason::Legacy(v1.stake_state_reason),
+            stake_action: v1
+                .stake_action
+                .map(|stake_action| StakeAction::Legacy(stake_action).into()),
+            notes: v1.notes.into_iter().map(ValidatorNote::Legacy).collect(),
+            data_center_residency: v1.data_center_residency.unwrap_or_default(),
+            current_data_center: v1.current_data_center.unwrap_or_default(),
+            participant: v1.participant,
+        }
     }
 }
 
@@ -79,111 +99,338 @@ pub struct EpochClassificationV1 {
     pub data_center_info: Vec<DataCenterInfo>,
 
     // `None` indicates a pause due to unusual observations during classification
-    pub validator_classifications: Option<ValidatorClassificationByIdentity>,
+    pub validator_classifications: Option<HashMap<Pubkey, ValidatorClassificationV1>>,
 
     // Informational notes regarding this epoch
     pub notes: Vec<String>,
 }
 
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+pub enum RunOrigin {
+    Bot,            // Produced by a run of the bot
+    MigratedFromV1, // Upgraded from an `EpochClassificationV1`, run metadata and inputs are unknown
+}
+
+impl Default for RunOrigin {
+    fn default() -> Self {
+        Self::Bot
+    }
+}
+
+// Progress of the run that produced a classification, persisted as each step completes so that
+// an interrupted run can be resumed
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
+pub enum RunStatus {
+    Classified, // Classification is complete, stake has not been adjusted yet
+    Applying,   // Stake adjustments were started but did not complete
+    Applied,    // Stake adjustments are complete
+}
+
+impl Default for RunStatus {
+    fn default() -> Self {
+        Self::Classified
+    }
+}
+
+#[derive(Default, Deserialize, Serialize, Clone)]
+pub struct RunMetadata {
+    pub origin: RunOrigin,
+    pub status: RunStatus,
+
+    // Version of the bot that produced the classification, as reported by `--version`
+    pub bot_version: String,
+
+    pub json_rpc_url: String,
+
+    // Unix timestamps of when classification started and when stake adjustments completed
+    pub started_at: u64,
+    pub completed_at: u64,
+}
+
+// The classification settings in effect for a run
+#[derive(Default, Deserialize, Serialize, Clone)]
+pub struct ConfigSnapshot {
+    pub cluster: String,
+    pub dry_run: bool,
+    pub quality_block_producer_percentage: usize,
+    pub max_poor_block_producer_percentage: usize,
+    pub max_commission: u8,
+    pub min_release_version: Option<String>,
+    pub max_old_release_version_percentage: usize,
+    pub max_poor_voter_percentage: usize,
+    pub max_infrastructure_concentration: Option<f64>,
+    pub infrastructure_concentration_affects: String,
+    pub bad_cluster_average_skip_rate: usize,
+    pub min_epoch_credit_percentage_of_average: usize,
+    pub min_self_stake_lamports: u64,
+    pub enforce_min_self_stake: bool,
+    pub enforce_testnet_participation: bool,
+    pub min_testnet_participation: Option<(usize, usize)>,
+}
+
+#[derive(Default, Deserialize, Serialize, Clone)]
+pub struct ValidatorInputs {
+    pub commission: u8,
+    pub epoch_credits: u64,
+    pub self_stake: u64,
+
+    // `None` if the validator was not observed in gossip
+    pub release_version: Option<String>,
+
+    // `None` if the validator had no leader slots
+    pub block_production: Option<BlockProductionStats>,
+}
+
+// Raw cluster observations that the classification was derived from
+#[derive(Default, Deserialize, Serialize, Clone)]
+pub struct ClassificationInputs {
+    // The epoch that the observations were made over
+    pub epoch: Epoch,
+
+    pub total_active_stake: u64,
+    pub cluster_average_skip_rate: usize,
+    pub avg_epoch_credits: u64,
+    pub validators: HashMap<Pubkey, ValidatorInputs>,
+}
+
+#[derive(Default, Deserialize, Serialize, Clone)]
+pub struct EpochClassificationV2 {
+    pub run: RunMetadata,
+    pub config: ConfigSnapshot,
+    pub inputs: ClassificationInputs,
+
+    // Data Center observations for this epoch
+    pub data_center_info: Vec<DataCenterInfo>,
+
+    // `None` indicates a pause due to unusual observations during classification
+    pub validator_classifications: Option<ValidatorClassificationByIdentity>,
+
+    // Informational notes regarding this epoch
+    pub notes: Vec<EpochNote>,
+}
+
+impl From<EpochClassificationV1> for EpochClassificationV2 {
+    fn from(v1: EpochClassificationV1) -> Self {
+        Self {
+            run: RunMetadata {
+                origin: RunOrigin::MigratedFromV1,
+                status: RunStatus::Applied,
+                ..RunMetadata::default()
+            },
+            config: ConfigSnapshot::default(),
+            inputs: ClassificationInputs::default(),
+            data_center_info: v1.data_center_info,
+            validator_classifications: v1.validator_classifications.map(
+                |validator_classifications| {
+                    validator_classifications
+                        .into_iter()
+                        .map(|(identity, classification)| (identity, classification.into()))
+                        .collect()
+                },
+            ),
+            notes: v1.notes.into_iter().map(EpochNote::Legacy).collect(),
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub enum EpochClassification {
     V1(EpochClassificationV1),
+    V2(EpochClassificationV2),
 }
 
 impl Default for EpochClassification {
     fn default() -> Self {
-        Self::V1(EpochClassificationV1::default())
+        Self::V2(EpochClassificationV2::default())
     }
 }
 
 impl EpochClassification {
-    pub fn new(v1: EpochClassificationV1) -> Self {
-        EpochClassification::V1(v1)
+    pub fn new(v2: EpochClassificationV2) -> Self {
+        EpochClassification::V2(v2)
     }
 
-    pub fn into_current(self) -> EpochClassificationV1 {
+    // Older versions are migrated to the current version on load, free-form strings are
+    // preserved as `Legacy` variants
+    pub fn into_current(self) -> EpochClassificationV2 {
         match self {
-            EpochClassification::V1(v1) => v1,
+            EpochClassification::V1(v1) => v1.into(),
+            EpochClassification::V2(v2) => v2,
         }
     }
+}
 
-    fn file_name<P>(epoch: Epoch, path: P) -> PathBuf
-    where
-        P: AsRef<Path>,
-    {
-        path.as_ref().join(format!("epoch-{}.yml", epoch))
-    }
+// Storage backend for epoch classifications and the stake state history
+pub trait ClassificationStore {
+    // Returns all epochs with a classification, oldest first
+    fn epochs(&self) -> Result<Vec<Epoch>, io::Error>;
 
-    pub fn exists<P>(epoch: Epoch, path: P) -> bool
-    where
-        P: AsRef<Path>,
-    {
-        Self::file_name(epoch, path).exists()
-    }
+    fn exists(&self, epoch: Epoch) -> Result<bool, io::Error>;
 
-    pub fn load<P>(epoch: Epoch, path: P) -> Result<Self, io::Error>
-    where
-        P: AsRef<Path>,
-    {
-        let file = File::open(Self::file_name(epoch, path))?;
-        serde_yaml::from_reader(file)
-            .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))
-    }
+    fn load(&self, epoch: Epoch) -> Result<EpochClassification, io::Error>;
+
+    fn save(
+        &self,
+        epoch: Epoch,
+        epoch_classification: &EpochClassification,
+    ) -> Result<(), io::Error>;
+
+    // Loads the stake state history, including all epochs currently in the store
+    fn load_stake_state_history(&self) -> Result<StakeStateHistory, io::Error>;
+
+    fn save_stake_state_history(&self, history: &StakeStateHistory) -> Result<(), io::Error>;
 
     // Loads the first epoch older than `epoch` that contains `Some(validator_classifications)`.
     // Returns `Ok(None)` if no previous epochs are available
-    pub fn load_previous<P>(epoch: Epoch, path: P) -> Result<Option<(Epoch, Self)>, io::Error>
-    where
-        P: AsRef<Path>,
-    {
-        let mut previous_epoch = epoch;
-        loop {
-            if previous_epoch == 0 {
+    fn load_previous(
+        &self,
+        epoch: Epoch,
+    ) -> Result<Option<(Epoch, EpochClassification)>, io::Error> {
+        for previous_epoch in self.epochs()?.into_iter().rev() {
+            if previous_epoch >= epoch {
+                continue;
+            }
+
+            let previous_epoch_classification = self.load(previous_epoch)?.into_current();
+            if previous_epoch_classification
+                .validator_classifications
+                .is_some()
+            {
+                info!(
+                    "Previous EpochClassification found for epoch {}",
+                    previous_epoch
+                );
+                return Ok(Some((
+                    previous_epoch,
+                    EpochClassification::V2(previous_epoch_classification),
+                )));
+            } else {
                 info!(
-                    "No previous EpochClassification found at {}",
-                    path.as_ref().display()
+                    "Skipping previous EpochClassification for epoch {}",
+                    previous_epoch
                 );
-                return Ok(None);
             }
-            previous_epoch -= 1;
+        }
+        info!("No previous EpochClassification found");
+        Ok(None)
+    }
 
-            if Self::exists(previous_epoch, &path) {
-                let previous_epoch_classification =
-                    Self::load(previous_epoch, &path)?.into_current();
+    // Loads the latest epoch that contains `Some(validator_classifications)`
+    // Returns `Ok(None)` if no epoch is available
+    fn load_latest(&self) -> Result<Option<(Epoch, EpochClassification)>, io::Error> {
+        match self.epochs()?.last() {
+            Some(latest_epoch) => self.load_previous(latest_epoch + 1),
+            None => Ok(None),
+        }
+    }
 
-                if previous_epoch_classification
-                    .validator_classifications
-                    .is_some()
+    // Returns all epochs where the validator was classified with `ValidatorStakeState::None`,
+    // oldest first
+    fn destaked_epochs(&self, identity: &Pubkey) -> Result<Vec<Epoch>, io::Error> {
+        let mut destaked_epochs = vec![];
+        for epoch in self.epochs()? {
+            if let Some(validator_classifications) =
+                self.load(epoch)?.into_current().validator_classifications
+            {
+                if validator_classifications
+                    .get(identity)
+                    .map(|vc| vc.stake_state == ValidatorStakeState::None)
+                    .unwrap_or(false)
                 {
-                    info!(
-                        "Previous EpochClassification found for epoch {} at {}",
-                        previous_epoch,
-                        path.as_ref().display()
-                    );
-                    return Ok(Some((
-                        previous_epoch,
-                        Self::V1(previous_epoch_classification),
-                    )));
-                } else {
-                    info!(
-                        "Skipping previous EpochClassification for epoch {}",
-                        previous_epoch
-                    );
+                    destaked_epochs.push(epoch);
                 }
             }
         }
+        Ok(destaked_epochs)
     }
+}
 
-    // Loads the latest epoch that contains `Some(validator_classifications)`
-    // Returns `Ok(None)` if no epoch is available
-    pub fn load_latest<P>(path: P) -> Result<Option<(Epoch, Self)>, io::Error>
+// Copies every epoch classification from `from` into `to`, returning the number of epochs copied
+pub fn copy_store(
+    from: &dyn ClassificationStore,
+    to: &dyn ClassificationStore,
+) -> Result<usize, io::Error> {
+    let epochs = from.epochs()?;
+    for epoch in &epochs {
+        to.save(*epoch, &from.load(*epoch)?)?;
+    }
+    to.save_stake_state_history(&to.load_stake_state_history()?)?;
+    Ok(epochs.len())
+}
+
+// Rewrites every V1 epoch classification in `store` with the current schema, returning the number
+// of epochs migrated. The stake state history kept by the newest V1 epoch is seeded into the
+// `StakeStateHistory` first, as it also covers epochs that are no longer in the store
+pub fn migrate_store(store: &dyn ClassificationStore) -> Result<usize, io::Error> {
+    let mut newest_v1 = None;
+    let mut migrated = 0;
+    for epoch in store.epochs()? {
+        let epoch_classification = store.load(epoch)?;
+        if let EpochClassification::V1(ref v1) = epoch_classification {
+            info!("Migrating epoch {}", epoch);
+            if let Some(validator_classifications) = &v1.validator_classifications {
+                newest_v1 = Some((epoch, validator_classifications.clone()));
+            }
+            store.save(
+                epoch,
+                &EpochClassification::new(epoch_classification.into_current()),
+            )?;
+            migrated += 1;
+        }
+    }
+
+    if migrated > 0 {
+        let mut history = store.load_stake_state_history()?;
+        if let Some((epoch, validator_classifications)) = newest_v1 {
+            history.seed_from_v1(epoch, &validator_classifications);
+        }
+        store.save_stake_state_history(&history)?;
+    }
+    Ok(migrated)
+}
+
+// Writes `contents` to a temporary file next to `path` and then renames it into place, so that
+// `path` never holds a partially written file
+fn write_atomically(path: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(tmp_path, path)
+}
+
+// Stores each epoch classification in an `epoch-{N}.yml` file
+pub struct YamlStore {
+    path: PathBuf,
+}
+
+impl YamlStore {
+    pub fn new<P>(path: P) -> Self
     where
         P: AsRef<Path>,
     {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    fn file_name(&self, epoch: Epoch) -> PathBuf {
+        self.path.join(format!("epoch-{}.yml", epoch))
+    }
+
+    fn stake_state_history_file_name(&self) -> PathBuf {
+        self.path.join("stake-state-history.yml")
+    }
+}
+
+impl ClassificationStore for YamlStore {
+    fn epochs(&self) -> Result<Vec<Epoch>, io::Error> {
         let epoch_filename_regex = regex::Regex::new(r"^epoch-(\d+).yml$").unwrap();
 
         let mut epochs = vec![];
-        if let Ok(entries) = fs::read_dir(&path) {
+        if let Ok(entries) = fs::read_dir(&self.path) {
             for entry in entries.filter_map(|entry| entry.ok()) {
                 if entry.path().is_file() {
                     let filename = entry
@@ -198,47 +445,527 @@ impl EpochClassification {
             }
         }
         epochs.sort_unstable();
+        Ok(epochs)
+    }
 
-        if let Some(latest_epoch) = epochs.last() {
-            Self::load_previous(*latest_epoch + 1, path)
-        } else {
-            Ok(None)
+    fn exists(&self, epoch: Epoch) -> Result<bool, io::Error> {
+        Ok(self.file_name(epoch).exists())
+    }
+
+    fn load(&self, epoch: Epoch) -> Result<EpochClassification, io::Error> {
+        let file = File::open(self.file_name(epoch))?;
+        serde_yaml::from_reader(file)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))
+    }
+
+    fn save(
+        &self,
+        epoch: Epoch,
+        epoch_classification: &EpochClassification,
+    ) -> Result<(), io::Error> {
+        let serialized = serde_yaml::to_string(epoch_classification)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?;
+
+        fs::create_dir_all(&self.path)?;
+        write_atomically(&self.file_name(epoch), serialized.as_bytes())?;
+
+        // `catch_up` only records epochs newer than the index, so an epoch that is saved again
+        // is re-recorded here. Without an index there is nothing to update, it is rebuilt on load
+        if self.stake_state_history_file_name().exists() {
+            let mut history = self.load_stake_state_history()?;
+            history.forget(epoch);
+            if let Some(validator_classifications) = &epoch_classification
+                .clone()
+                .into_current()
+                .validator_classifications
+            {
+                history.record(epoch, validator_classifications);
+            }
+            self.save_stake_state_history(&history)?;
         }
+        Ok(())
     }
 
-    pub fn save<P>(&self, epoch: Epoch, path: P) -> Result<(), io::Error>
-    where
-        P: AsRef<Path>,
-    {
-        let serialized = serde_yaml::to_string(self)
+    // The history index is rebuilt from the epoch classifications if it does not exist
+    fn load_stake_state_history(&self) -> Result<StakeStateHistory, io::Error> {
+        let file_name = self.stake_state_history_file_name();
+        let mut history: StakeStateHistory = if file_name.exists() {
+            let file = File::open(file_name)?;
+            serde_yaml::from_reader(file)
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?
+        } else {
+            info!(
+                "Rebuilding stake state history from {}",
+                self.path.display()
+            );
+            StakeStateHistory::default()
+        };
+        history.catch_up(self)?;
+        Ok(history)
+    }
+
+    fn save_stake_state_history(&self, history: &StakeStateHistory) -> Result<(), io::Error> {
+        let serialized = serde_yaml::to_string(history)
             .map_err(|err| io::Error::new(io::ErrorKind::Other, format!("{:?}", err)))?;
 
-        fs::create_dir_all(&path)?;
-        let mut file = File::create(Self::file_name(epoch, path))?;
-        file.write_all(&serialized.into_bytes())?;
+        fs::create_dir_all(&self.path)?;
+        write_atomically(&self.stake_state_history_file_name(), serialized.as_bytes())
+    }
+}
+
+// Per-validator index of the stake state in each classified epoch, so that the stake history of
+// a validator can be obtained without loading every epoch classification
+#[derive(Default, Deserialize, Serialize)]
+pub struct StakeStateHistory {
+    // The most recent epoch recorded
+    latest_epoch: Option<Epoch>,
+
+    // Stake states by validator identity, newest first. Epochs without validator
+    // classifications are not recorded
+    validators: HashMap<Pubkey, Vec<(Epoch, ValidatorStakeState)>>,
+}
 
+impl StakeStateHistory {
+    // Records any epoch classifications in `store` that are newer than the history
+    pub fn catch_up(&mut self, store: &dyn ClassificationStore) -> Result<(), io::Error> {
+        for epoch in store.epochs()? {
+            if self
+                .latest_epoch
+                .map(|latest_epoch| epoch > latest_epoch)
+                .unwrap_or(true)
+            {
+                if let Some(ref validator_classifications) =
+                    store.load(epoch)?.into_current().validator_classifications
+                {
+                    self.record(epoch, validator_classifications);
+                }
+            }
+        }
         Ok(())
     }
+
+    // Records the stake state of each validator in `epoch`, replacing any previous record for
+    // that epoch
+    pub fn record(
+        &mut self,
+        epoch: Epoch,
+        validator_classifications: &ValidatorClassificationByIdentity,
+    ) {
+        for (identity, validator_classification) in validator_classifications {
+            self.record_stake_state(*identity, epoch, validator_classification.stake_state);
+        }
+    }
+
+    // Records the V1 `stake_states` of each validator classified in `epoch`, taking each entry to
+    // be one epoch older than the previous one. Epochs that are already recorded are kept
+    pub fn seed_from_v1(
+        &mut self,
+        epoch: Epoch,
+        validator_classifications: &HashMap<Pubkey, ValidatorClassificationV1>,
+    ) {
+        for (identity, validator_classification) in validator_classifications {
+            for (stake_state_epoch, (stake_state, _)) in (0..=epoch)
+                .rev()
+                .zip(validator_classification.stake_states.iter().flatten())
+            {
+                let recorded = self
+                    .validators
+                    .get(identity)
+                    .into_iter()
+                    .flatten()
+                    .any(|(recorded_epoch, _)| *recorded_epoch == stake_state_epoch);
+                if !recorded {
+                    self.record_stake_state(*identity, stake_state_epoch, *stake_state);
+                }
+            }
+        }
+    }
+
+    // Removes the stake state of every validator in `epoch`
+    pub fn forget(&mut self, epoch: Epoch) {
+        for stake_states in self.validators.values_mut() {
+            stake_states.retain(|(recorded_epoch, _)| *recorded_epoch != epoch);
+        }
+    }
+
+    pub fn record_stake_state(
+        &mut self,
+        identity: Pubkey,
+        epoch: Epoch,
+        stake_state: ValidatorStakeState,
+    ) {
+        let stake_states = self.validators.entry(identity).or_default();
+        stake_states.retain(|(recorded_epoch, _)| *recorded_epoch != epoch);
+        stake_states.push((epoch, stake_state));
+        stake_states.sort_by(|a, b| b.0.cmp(&a.0));
+        self.latest_epoch = Some(self.latest_epoch.unwrap_or_default().max(epoch));
+    }
+
+    // Stake states of the validator up to and including `epoch`, newest first
+    fn stake_states_at(
+        &self,
+        identity: &Pubkey,
+        epoch: Epoch,
+    ) -> impl Iterator<Item = &ValidatorStakeState> {
+        self.validators
+            .get(identity)
+            .into_iter()
+            .flatten()
+            .skip_while(move |(recorded_epoch, _)| *recorded_epoch > epoch)
+            .map(|(_, stake_state)| stake_state)
+    }
+
+    // Number of consecutive epochs, ending at `epoch`, that the validator has held its stake state
+    pub fn stake_state_streak(&self, identity: &Pubkey, epoch: Epoch) -> usize {
+        let mut stake_states = self.stake_states_at(identity, epoch);
+        match stake_states.next() {
+            Some(stake_state) => 1 + stake_states.take_while(|s| *s == stake_state).count(),
+            None => 0,
+        }
+    }
+
+    // Was the validator staked for at last `n` of its last `m` recorded epochs?
+    pub fn staked_for(&self, identity: &Pubkey, n: usize, m: usize) -> bool {
+        match self.validators.get(identity) {
+            Some(stake_states) if !stake_states.is_empty() => {
+                stake_states
+                    .iter()
+                    .take(m)
+                    .filter(|(_, stake_state)| *stake_state != ValidatorStakeState::None)
+                    .count()
+                    >= n
+            }
+            _ => false,
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn validator_classifications(
+        identity: Pubkey,
+        stake_state: ValidatorStakeState,
+    ) -> ValidatorClassificationByIdentity {
+        let mut validator_classifications = HashMap::new();
+        validator_classifications.insert(
+            identity,
+            ValidatorClassification {
+                identity,
+                stake_state,
+                ..ValidatorClassification::default()
+            },
+        );
+        validator_classifications
+    }
+
     #[test]
     fn test_staked_for() {
-        let mut vc = ValidatorClassification::default();
-
-        assert_eq!(vc.staked_for(0, 0), false);
-        assert_eq!(vc.staked_for(1, 0), false);
-        assert_eq!(vc.staked_for(0, 1), false);
-
-        vc.stake_states = Some(vec![
-            (ValidatorStakeState::None, String::new()),
-            (ValidatorStakeState::Baseline, String::new()),
-            (ValidatorStakeState::Bonus, String::new()),
-        ]);
-        assert_eq!(vc.staked_for(3, 3), false);
-        assert_eq!(vc.staked_for(2, 3), true);
+        let identity = Pubkey::new_unique();
+        let mut history = StakeStateHistory::default();
+
+        assert_eq!(history.staked_for(&identity, 0, 0), false);
+        assert_eq!(history.staked_for(&identity, 1, 0), false);
+        assert_eq!(history.staked_for(&identity, 0, 1), false);
+
+        history.record(
+            3,
+            &validator_classifications(identity, ValidatorStakeState::Bonus),
+        );
+        history.record(
+            1,
+            &validator_classifications(identity, ValidatorStakeState::None),
+        );
+        history.record(
+            2,
+            &validator_classifications(identity, ValidatorStakeState::Baseline),
+        );
+        // Re-recording an epoch replaces the previous record
+        history.record(
+            3,
+            &validator_classifications(identity, ValidatorStakeState::None),
+        );
+        history.record(
+            4,
+            &validator_classifications(identity, ValidatorStakeState::Bonus),
+        );
+
+        assert_eq!(history.staked_for(&identity, 3, 3), false);
+        assert_eq!(history.staked_for(&identity, 2, 3), true);
+        assert_eq!(history.staked_for(&Pubkey::new_unique(), 0, 3), false);
+    }
+
+    #[test]
+    fn test_stake_state_streak() {
+        let identity = Pubkey::new_unique();
+        let mut history = StakeStateHistory::default();
+        assert_eq!(history.stake_state_streak(&identity, 0), 0);
+
+        for (epoch, stake_state) in [
+            ValidatorStakeState::None,
+            ValidatorStakeState::Baseline,
+            ValidatorStakeState::Baseline,
+            ValidatorStakeState::Bonus,
+        ]
+        .iter()
+        .enumerate()
+        {
+            history.record(
+                epoch as Epoch,
+                &validator_classifications(identity, *stake_state),
+            );
+        }
+
+        assert_eq!(history.stake_state_streak(&identity, 0), 1);
+        assert_eq!(history.stake_state_streak(&identity, 2), 2);
+        assert_eq!(history.stake_state_streak(&identity, 3), 1);
+        assert_eq!(history.stake_state_streak(&identity, 10), 1);
+    }
+
+    #[test]
+    fn test_stake_state_history_load() {
+        let path = std::env::temp_dir().join(format!("som-test-{}", Pubkey::new_unique()));
+        let store = YamlStore::new(&path);
+        let identity = Pubkey::new_unique();
+
+        for epoch in 1..4 {
+            store
+                .save(
+                    epoch,
+                    &EpochClassification::new(EpochClassificationV2 {
+                        validator_classifications: Some(validator_classifications(
+                            identity,
+                            ValidatorStakeState::Bonus,
+                        )),
+                        ..EpochClassificationV2::default()
+                    }),
+                )
+                .unwrap();
+        }
+
+        // Rebuilt from the epoch classifications
+        let history = store.load_stake_state_history().unwrap();
+        assert_eq!(history.stake_state_streak(&identity, 3), 3);
+        store.save_stake_state_history(&history).unwrap();
+
+        // Catches up with epochs classified after the index was saved
+        store
+            .save(
+                4,
+                &EpochClassification::new(EpochClassificationV2 {
+                    validator_classifications: Some(validator_classifications(
+                        identity,
+                        ValidatorStakeState::None,
+                    )),
+                    ..EpochClassificationV2::default()
+                }),
+            )
+            .unwrap();
+        let history = store.load_stake_state_history().unwrap();
+        assert_eq!(history.stake_state_streak(&identity, 3), 3);
+        assert_eq!(history.stake_state_streak(&identity, 4), 1);
+
+        store.save_stake_state_history(&history).unwrap();
+
+        // An epoch that is already indexed is re-recorded when it is saved again
+        store
+            .save(
+                3,
+                &EpochClassification::new(EpochClassificationV2 {
+                    validator_classifications: Some(validator_classifications(
+                        identity,
+                        ValidatorStakeState::None,
+                    )),
+                    ..EpochClassificationV2::default()
+                }),
+            )
+            .unwrap();
+        let history = store.load_stake_state_history().unwrap();
+        assert_eq!(history.stake_state_streak(&identity, 2), 2);
+        assert_eq!(history.stake_state_streak(&identity, 4), 2);
+        assert!(!history.staked_for(&identity, 3, 3));
+
+        assert_eq!(store.epochs().unwrap(), vec![1, 2, 3, 4]);
+        assert!(!path.join("epoch-4.tmp").exists());
+        assert_eq!(store.destaked_epochs(&identity).unwrap(), vec![3, 4]);
+        assert_eq!(store.load_latest().unwrap().unwrap().0, 4);
+        assert_eq!(store.load_previous(4).unwrap().unwrap().0, 3);
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_record_stake_state_of_indexed_epoch() {
+        let path = std::env::temp_dir().join(format!("som-test-{}", Pubkey::new_unique()));
+        let store = YamlStore::new(&path);
+        let identity = Pubkey::new_unique();
+
+        for epoch in 1..3 {
+            store
+                .save(
+                    epoch,
+                    &EpochClassification::new(EpochClassificationV2 {
+                        validator_classifications: Some(validator_classifications(
+                            identity,
+                            ValidatorStakeState::Bonus,
+                        )),
+                        ..EpochClassificationV2::default()
+                    }),
+                )
+                .unwrap();
+        }
+        store
+            .save_stake_state_history(&store.load_stake_state_history().unwrap())
+            .unwrap();
+
+        // An emergency destake changes the stake state of the latest epoch after it was indexed
+        let mut history = store.load_stake_state_history().unwrap();
+        history.record_stake_state(identity, 2, ValidatorStakeState::None);
+        store.save_stake_state_history(&history).unwrap();
+
+        let history = store.load_stake_state_history().unwrap();
+        assert_eq!(history.stake_state_streak(&identity, 1), 1);
+        assert_eq!(history.stake_state_streak(&identity, 2), 1);
+        assert!(!history.staked_for(&identity, 2, 2));
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_registry_cli_reads_stake_state_reason() {
+        let path = std::env::temp_dir().join(format!("som-test-{}", Pubkey::new_unique()));
+        let store = YamlStore::new(&path);
+        let identity = Pubkey::new_unique();
+        let reason = StakeStateReason::InsufficientVoteCredits {
+            earned: 100,
+            required: 200,
+            epoch: 42,
+        };
+
+        let mut validator_classifications =
+            validator_classifications(identity, ValidatorStakeState::None);
+        validator_classifications
+            .get_mut(&identity)
+            .unwrap()
+            .stake_state_reason = reason.clone();
+        store
+            .save(
+                43,
+                &EpochClassification::new(EpochClassificationV2 {
+                    validator_classifications: Some(validator_classifications),
+                    ..EpochClassificationV2::default()
+                }),
+            )
+            .unwrap();
+
+        let bot_classifications = registry_cli::get_latest_bot_classifications(&path).unwrap();
+        assert_eq!(
+            bot_classifications[&identity].stake_state_reason,
+            reason.to_string()
+        );
+
+        fs::remove_dir_all(&path).unwrap();
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        let identity = Pubkey::new_unique();
+        let mut validator_classifications = HashMap::new();
+        validator_classifications.insert(
+            identity,
+            ValidatorClassificationV1 {
+                identity,
+                stake_state: ValidatorStakeState::Baseline,
+                stake_state_reason: "good block production".to_string(),
+                stake_action: Some("no change".to_string()),
+                stake_states: Some(vec![
+                    (
+                        ValidatorStakeState::Baseline,
+                        "good block production".to_string(),
+                    ),
+                    (ValidatorStakeState::None, "poor voter".to_string()),
+                ]),
+                notes: vec!["a note".to_string()],
+                ..ValidatorClassificationV1::default()
+            },
+        );
+        let v1 = EpochClassification::V1(EpochClassificationV1 {
+            data_center_info: vec![],
+            validator_classifications: Some(validator_classifications),
+            notes: vec!["an epoch note".to_string()],
+        });
+
+        let serialized = serde_yaml::to_string(&v1).unwrap();
+        let v2 = serde_yaml::from_str::<EpochClassification>(&serialized)
+            .unwrap()
+            .into_current();
+
+        assert_eq!(v2.run.origin, RunOrigin::MigratedFromV1);
+        assert_eq!(v2.run.status, RunStatus::Applied);
+        assert_eq!(v2.notes, vec![EpochNote::Legacy("an epoch note".into())]);
+        let vc = &v2.validator_classifications.unwrap()[&identity];
+        assert_eq!(vc.stake_state, ValidatorStakeState::Baseline);
+        assert_eq!(vc.stake_state_reason.to_string(), "good block production");
+        assert_eq!(
+            vc.stake_action.as_ref().map(|action| action.to_string()),
+            Some("no change".to_string())
+        );
+        assert_eq!(vc.notes, vec![ValidatorNote::Legacy("a note".into())]);
+        assert_eq!(vc.data_center_residency, HashMap::default());
+    }
+
+    #[test]
+    fn test_migrate_store() {
+        let path = std::env::temp_dir().join(format!("som-test-{}", Pubkey::new_unique()));
+        let store = YamlStore::new(&path);
+        let identity = Pubkey::new_unique();
+
+        // Only the latest epoch remains, its `stake_states` hold the history of the older ones
+        let mut validator_classifications = HashMap::new();
+        validator_classifications.insert(
+            identity,
+            ValidatorClassificationV1 {
+                identity,
+                stake_state: ValidatorStakeState::Baseline,
+                stake_states: Some(vec![
+                    (ValidatorStakeState::Baseline, "good".to_string()),
+                    (ValidatorStakeState::Baseline, "good".to_string()),
+                    (ValidatorStakeState::None, "poor voter".to_string()),
+                    (ValidatorStakeState::Bonus, "good".to_string()),
+                ]),
+                ..ValidatorClassificationV1::default()
+            },
+        );
+        store
+            .save(
+                10,
+                &EpochClassification::V1(EpochClassificationV1 {
+                    data_center_info: vec![],
+                    validator_classifications: Some(validator_classifications),
+                    notes: vec![],
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(migrate_store(&store).unwrap(), 1);
+        assert!(matches!(
+            store.load(10).unwrap(),
+            EpochClassification::V2(_)
+        ));
+
+        let history = store.load_stake_state_history().unwrap();
+        assert_eq!(history.stake_state_streak(&identity, 10), 2);
+        assert_eq!(history.stake_state_streak(&identity, 8), 1);
+        assert_eq!(history.stake_state_streak(&identity, 7), 1);
+        assert!(history.staked_for(&identity, 3, 4));
+        assert!(!history.staked_for(&identity, 4, 4));
+
+        // Nothing left to migrate
+        assert_eq!(migrate_store(&store).unwrap(), 0);
+        let history = store.load_stake_state_history().unwrap();
+        assert_eq!(history.stake_state_streak(&identity, 7), 1);
+
+        fs::remove_dir_all(&path).unwrap();
     }
 }
@@ -1,8 +1,20 @@
 use {
-    serde::{Deserialize, Serialize},
+    crate::{
+        notes::{EpochNote, StakeActionStatus, ValidatorStakeAction},
+        rpc_client_utils::{
+            get_durable_nonce, pack_transactions, send_and_confirm_signed_transactions,
+            send_and_confirm_transactions, simulate_transactions, use_durable_nonce,
+            DescribedTransaction, TransactionReport, TransactionStatus,
+        },
+    },
+    log::*,
+    serde::{Deserialize, Serialize, Serializer},
     solana_client::rpc_client::RpcClient,
-    solana_sdk::pubkey::Pubkey,
-    std::{collections::HashMap, error},
+    solana_sdk::{pubkey::Pubkey, signature::Signer, transaction::Transaction},
+    std::{
+        collections::{HashMap, HashSet},
+        error, mem,
+    },
 };
 
 #[derive(Debug, PartialEq, Clone, Copy, Deserialize, Serialize)]
@@ -25,8 +37,341 @@ pub struct ValidatorStake {
     pub stake_state: ValidatorStakeState,
 }
 
-pub type ValidatorStakeActions = HashMap<Pubkey, String>;
-pub type EpochStakeNotes = Vec<String>;
+pub type ValidatorStakeActions = HashMap<Pubkey, ValidatorStakeAction>;
+pub type EpochStakeNotes = Vec<EpochNote>;
+
+// A transaction of one phase of `GenericStakePool::apply`, and the outcome of either its
+// simulation, for a dry run, or its submission.
+//
+// `transaction` holds the bs58-encoded bincode serialization of a planned transaction. It is
+// unsigned when planned, and built by `GenericStakePool::plan` on the durable nonce of
+// `nonce_account` so that it may be signed offline with `sign` and submitted with `execute_plan`
+// at any later time.
+//
+// A dry run simulates every phase against the current state, so a transaction that is `dependent`
+// on an earlier phase of the plan may fail simulation only because that phase has not run yet
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct PlannedTransaction {
+    pub phase: String,
+    pub description: String,
+    #[serde(default)]
+    pub identities: Vec<Pubkey>,
+    pub transaction: String,
+    #[serde(default)]
+    pub nonce_account: Option<Pubkey>,
+    #[serde(default)]
+    pub dependent: bool,
+    pub simulation_error: Option<String>,
+    pub simulation_logs: Vec<String>,
+    #[serde(default)]
+    pub signature: Option<String>,
+    #[serde(default)]
+    pub attempts: usize,
+    #[serde(default)]
+    pub status: Option<TransactionStatus>,
+}
+
+impl PlannedTransaction {
+    pub fn transaction(&self) -> Result<Transaction, Box<dyn error::Error>> {
+        let data = bs58::decode(&self.transaction)
+            .into_vec()
+            .map_err(|err| format!("Invalid transaction encoding: {}", err))?;
+        bincode::deserialize(&data)
+            .map_err(|err| format!("Invalid transaction: {}: {}", self.description, err).into())
+    }
+
+    pub fn set_transaction(
+        &mut self,
+        transaction: &Transaction,
+    ) -> Result<(), Box<dyn error::Error>> {
+        self.transaction = bs58::encode(bincode::serialize(transaction)?).into_string();
+        Ok(())
+    }
+
+    // Adds the signature of `signer` to the transaction, keeping its durable nonce as the blockhash.
+    // A transaction on a recent blockhash is not signed, as it would likely expire before being
+    // submitted
+    pub fn sign(&mut self, signer: &dyn Signer) -> Result<(), Box<dyn error::Error>> {
+        if self.nonce_account.is_none() {
+            return Err(format!(
+                "Transaction not planned on a durable nonce: {}",
+                self.description
+            )
+            .into());
+        }
+        let mut transaction = self.transaction()?;
+        let recent_blockhash = transaction.message.recent_blockhash;
+        transaction.try_partial_sign(&vec![signer], recent_blockhash)?;
+        self.set_transaction(&transaction)
+    }
+
+    fn set_report(&mut self, report: TransactionReport) {
+        self.signature = Some(report.signature.to_string());
+        self.attempts = report.attempts;
+        self.status = Some(report.status);
+    }
+}
+
+pub type TransactionPlan = Vec<PlannedTransaction>;
+
+// Current stake of a validator in the pool, as reported by `GenericStakePool::status`
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ValidatorStakeStatus {
+    #[serde(serialize_with = "serialize_optional_pubkey")]
+    pub identity: Option<Pubkey>,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub vote_address: Pubkey,
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub stake_address: Pubkey,
+    // Stake excluding rent: the active stake of the validator stake account, and all stake of the
+    // transient stake account whatever its activation state
+    pub active_stake: u64,
+    pub transient_stake: u64,
+    // The validator is busy while its transient stake account exists
+    #[serde(serialize_with = "serialize_optional_pubkey")]
+    pub transient_stake_address: Option<Pubkey>,
+    // Activation state of the transient stake account, if it exists
+    pub transient_stake_state: Option<String>,
+    // `StakeStatus` of the validator list entry, if the pool keeps one
+    pub stake_status: Option<String>,
+
+    // Target and stake recorded by the latest epoch classification, and the change in stake since
+    pub target_stake: Option<u64>,
+    pub classified_stake: Option<u64>,
+    pub stake_difference: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct StakePoolStatus {
+    #[serde(serialize_with = "serialize_pubkey")]
+    pub reserve_stake_address: Pubkey,
+    pub reserve_balance: u64,
+    pub validators: Vec<ValidatorStakeStatus>,
+    // Stake accounts of the authorized staker that are not part of the pool
+    #[serde(serialize_with = "serialize_pubkeys")]
+    pub orphaned_stake_addresses: Vec<Pubkey>,
+}
+
+// Status reports are read by operators, so addresses are written in base58
+fn serialize_pubkey<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(pubkey)
+}
+
+fn serialize_optional_pubkey<S: Serializer>(
+    pubkey: &Option<Pubkey>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match pubkey {
+        Some(pubkey) => serializer.collect_str(pubkey),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn serialize_pubkeys<S: Serializer>(pubkeys: &[Pubkey], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(pubkeys.iter().map(|pubkey| pubkey.to_string()))
+}
+
+// Send and confirm the transactions of one phase of `GenericStakePool::apply`, adding them to
+// `plan` with their outcome and returning whether they all succeeded. The transactions of a phase
+// are independent of each other, and are packed together to save fees and round trips.
+//
+// When `dry_run` is set nothing is sent; each transaction is simulated instead and added to `plan`
+// unsigned, with its simulation error if it would fail, and a dry run always proceeds to the next
+// phase. As the earlier phases of the plan have not run, the transactions of the later phases are
+// marked `dependent`
+pub fn process_transactions(
+    rpc_client: &RpcClient,
+    dry_run: bool,
+    phase: &str,
+    transactions: Vec<DescribedTransaction>,
+    authorized_staker: &dyn Signer,
+    plan: &mut TransactionPlan,
+) -> Result<bool, Box<dyn error::Error>> {
+    let transactions = pack_transactions(rpc_client, transactions)?;
+    if dry_run {
+        let dependent = plan
+            .iter()
+            .any(|planned_transaction| planned_transaction.phase != phase);
+        for simulated_transaction in simulate_transactions(rpc_client, transactions)? {
+            if let Some(err) = &simulated_transaction.err {
+                if dependent {
+                    info!(
+                        "Planned transaction fails simulation before the earlier phases run: {}: {}",
+                        simulated_transaction.transaction.memo, err
+                    );
+                } else {
+                    warn!(
+                        "Planned transaction fails simulation: {}: {}",
+                        simulated_transaction.transaction.memo, err
+                    );
+                }
+            }
+            let mut planned_transaction = PlannedTransaction {
+                phase: phase.to_string(),
+                description: simulated_transaction.transaction.memo,
+                identities: simulated_transaction.transaction.identities,
+                dependent,
+                simulation_error: simulated_transaction.err.map(|err| err.to_string()),
+                simulation_logs: simulated_transaction.logs,
+                ..PlannedTransaction::default()
+            };
+            planned_transaction.set_transaction(&simulated_transaction.transaction.transaction)?;
+            plan.push(planned_transaction);
+        }
+        Ok(true)
+    } else {
+        let mut ok = true;
+        for report in send_and_confirm_transactions(rpc_client, transactions, authorized_staker)? {
+            ok &= report.succeeded();
+            let mut planned_transaction = PlannedTransaction {
+                phase: phase.to_string(),
+                description: report.memo.clone(),
+                identities: report.identities.clone(),
+                ..PlannedTransaction::default()
+            };
+            planned_transaction.set_report(report);
+            plan.push(planned_transaction);
+        }
+        Ok(ok)
+    }
+}
+
+// Link the stake actions of the validators concerned by `planned_transactions` to the outcome of
+// their transaction
+pub fn link_stake_actions(
+    planned_transactions: &[PlannedTransaction],
+    validator_stake_actions: &mut ValidatorStakeActions,
+) {
+    for planned_transaction in planned_transactions {
+        for validator_stake_action in planned_transaction
+            .identities
+            .iter()
+            .filter_map(|identity| validator_stake_actions.get_mut(identity))
+        {
+            validator_stake_action.signature = planned_transaction.signature.clone();
+            validator_stake_action.status = match &planned_transaction.status {
+                Some(TransactionStatus::Succeeded) => StakeActionStatus::Confirmed,
+                Some(TransactionStatus::Failed(err))
+                | Some(TransactionStatus::PreflightFailed(err)) => {
+                    StakeActionStatus::Failed(err.clone())
+                }
+                Some(TransactionStatus::Rejected(_)) | Some(TransactionStatus::Expired) | None => {
+                    StakeActionStatus::NotSent
+                }
+            };
+        }
+    }
+}
+
+// Build the transactions of `plan` on the durable nonces of `nonce_accounts`, one per transaction,
+// each of which must be advanceable by the payer of its transaction
+pub fn use_durable_nonces(
+    rpc_client: &RpcClient,
+    plan: &mut [PlannedTransaction],
+    nonce_accounts: &[Pubkey],
+) -> Result<(), Box<dyn error::Error>> {
+    if nonce_accounts.iter().collect::<HashSet<_>>().len() < nonce_accounts.len() {
+        return Err("A nonce account can only be used by one planned transaction".into());
+    }
+    if nonce_accounts.len() < plan.len() {
+        return Err(format!(
+            "{} transactions planned but only {} nonce accounts provided",
+            plan.len(),
+            nonce_accounts.len()
+        )
+        .into());
+    }
+
+    for (planned_transaction, nonce_account) in plan.iter_mut().zip(nonce_accounts) {
+        let transaction = planned_transaction.transaction()?;
+        let payer = transaction.message.account_keys[0];
+        let (nonce, nonce_authority) = get_durable_nonce(rpc_client, nonce_account)?;
+        if nonce_authority != payer {
+            return Err(format!(
+                "Nonce account {} authority is {}, not {}",
+                nonce_account, nonce_authority, payer
+            )
+            .into());
+        }
+        let transaction =
+            use_durable_nonce(&transaction, nonce_account, nonce).ok_or_else(|| {
+                format!(
+                    "Transaction too large for a durable nonce: {}",
+                    planned_transaction.description
+                )
+            })?;
+        planned_transaction.set_transaction(&transaction)?;
+        planned_transaction.nonce_account = Some(*nonce_account);
+    }
+    Ok(())
+}
+
+// Submit the pre-signed transactions of `plan`, recording the outcome of each one. As in
+// `GenericStakePool::apply`, the transactions of each phase must all succeed before the next
+// phase is sent. A plan with a transaction that failed simulation is not submitted
+pub fn execute_plan(
+    rpc_client: &RpcClient,
+    plan: &mut [PlannedTransaction],
+) -> Result<(), Box<dyn error::Error>> {
+    if let Some((description, err)) = plan.iter().find_map(|planned_transaction| {
+        planned_transaction
+            .simulation_error
+            .as_ref()
+            .map(|err| (&planned_transaction.description, err))
+    }) {
+        return Err(format!(
+            "Planned transaction failed simulation: {}: {}",
+            description, err
+        )
+        .into());
+    }
+
+    let mut remaining = plan;
+    while !remaining.is_empty() {
+        let phase_name = remaining[0].phase.clone();
+        let phase_len = remaining
+            .iter()
+            .take_while(|planned_transaction| planned_transaction.phase == phase_name)
+            .count();
+        let (phase, rest) = mem::take(&mut remaining).split_at_mut(phase_len);
+        remaining = rest;
+
+        let mut transactions = vec![];
+        for planned_transaction in phase.iter() {
+            let transaction = planned_transaction.transaction()?;
+            if transaction.verify().is_err() {
+                return Err(format!(
+                    "Transaction not fully signed: {}",
+                    planned_transaction.description
+                )
+                .into());
+            }
+            transactions.push(DescribedTransaction {
+                transaction,
+                memo: planned_transaction.description.clone(),
+                identities: planned_transaction.identities.clone(),
+            });
+        }
+
+        info!(
+            "Executing {}: {} transactions",
+            phase_name,
+            transactions.len()
+        );
+        let reports = send_and_confirm_signed_transactions(rpc_client, transactions)?;
+        let mut failed = 0;
+        for (planned_transaction, report) in phase.iter_mut().zip(reports) {
+            if !report.succeeded() {
+                failed += 1;
+            }
+            planned_transaction.set_report(report);
+        }
+        if failed > 0 {
+            return Err(format!("{} transactions failed to {}", failed, phase_name).into());
+        }
+    }
+    Ok(())
+}
 
 pub trait GenericStakePool {
     fn apply(
@@ -34,5 +379,81 @@ pub trait GenericStakePool {
         rpc_client: &RpcClient,
         dry_run: bool,
         desired_validator_stake: &[ValidatorStake],
-    ) -> Result<(EpochStakeNotes, ValidatorStakeActions), Box<dyn error::Error>>;
+    ) -> Result<(EpochStakeNotes, ValidatorStakeActions, TransactionPlan), Box<dyn error::Error>>;
+
+    // Plan the stake adjustments without side effects, for the returned unsigned transactions to
+    // be signed offline and submitted with `execute_plan`. Each phase is built from the state left
+    // by the previous ones, so only the first phase with transactions is planned, on the durable
+    // nonces of `nonce_accounts`, and the next phase is planned once it has been executed
+    fn plan(
+        &mut self,
+        rpc_client: &RpcClient,
+        desired_validator_stake: &[ValidatorStake],
+        nonce_accounts: &[Pubkey],
+    ) -> Result<(EpochStakeNotes, ValidatorStakeActions, TransactionPlan), Box<dyn error::Error>>
+    {
+        let (notes, validator_stake_actions, mut plan) =
+            self.apply(rpc_client, true, desired_validator_stake)?;
+        plan.retain(|planned_transaction| !planned_transaction.dependent);
+        use_durable_nonces(rpc_client, &mut plan, nonce_accounts)?;
+        Ok((notes, validator_stake_actions, plan))
+    }
+
+    // Remove the stake of a single validator right away, outside of the epoch run
+    fn emergency_destake(
+        &mut self,
+        rpc_client: &RpcClient,
+        dry_run: bool,
+        identity: Pubkey,
+        vote_address: Pubkey,
+    ) -> Result<(ValidatorStakeAction, TransactionPlan), Box<dyn error::Error>>;
+
+    // Read-only view of the stake of the pool and of the validators in `desired_validator_stake`
+    fn status(
+        &self,
+        rpc_client: &RpcClient,
+        desired_validator_stake: &[ValidatorStake],
+    ) -> Result<StakePoolStatus, Box<dyn error::Error>>;
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        solana_sdk::{hash::Hash, signature::Keypair, system_instruction},
+    };
+
+    #[test]
+    fn test_sign_planned_transaction() {
+        let authorized_staker = Keypair::new();
+        let nonce_account = Pubkey::new_unique();
+        let nonce = Hash::new(&[1; 32]);
+        let transaction = Transaction::new_with_payer(
+            &[system_instruction::transfer(
+                &authorized_staker.pubkey(),
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&authorized_staker.pubkey()),
+        );
+
+        // A transaction on a recent blockhash is not signed
+        let mut planned_transaction = PlannedTransaction::default();
+        planned_transaction.set_transaction(&transaction).unwrap();
+        assert!(planned_transaction.sign(&authorized_staker).is_err());
+
+        let transaction = use_durable_nonce(&transaction, &nonce_account, nonce).unwrap();
+        assert_eq!(transaction.message.instructions.len(), 2);
+        planned_transaction.set_transaction(&transaction).unwrap();
+        planned_transaction.nonce_account = Some(nonce_account);
+        assert!(planned_transaction.transaction().unwrap().verify().is_err());
+
+        planned_transaction.sign(&authorized_staker).unwrap();
+        let signed_transaction = planned_transaction.transaction().unwrap();
+        assert!(signed_transaction.verify().is_ok());
+        assert_eq!(signed_transaction.message.recent_blockhash, nonce);
+
+        // Signing with a key the transaction does not require fails
+        assert!(planned_transaction.sign(&Keypair::new()).is_err());
+    }
 }
@@ -1,17 +1,28 @@
 use {
-    crate::{db::*, generic_stake_pool::*, rpc_client_utils::*},
+    crate::{
+        db::*,
+        generic_stake_pool::*,
+        notes::*,
+        overrides::{
+            active_overrides, forced_stake_state, read_overrides, OverrideRule, ValidatorOverride,
+        },
+        rpc_client_utils::*,
+        stake_pool::ReserveTarget,
+    },
     clap::{
         crate_description, crate_name, value_t, value_t_or_exit, values_t, App, AppSettings, Arg,
         ArgMatches, SubCommand,
     },
     log::*,
     registry_cli::get_participants_with_state,
-    registry_program::state::{Participant, ParticipantState},
+    registry_program::state::{ParticipantState, ParticipantV2},
     solana_clap_utils::{
-        input_parsers::{keypair_of, lamports_of_sol, pubkey_of},
+        input_parsers::{lamports_of_sol, pubkey_of, pubkeys_of},
         input_validators::{
-            is_amount, is_keypair, is_parsable, is_pubkey_or_keypair, is_url, is_valid_percentage,
+            is_amount, is_parsable, is_pubkey, is_pubkey_or_keypair, is_url, is_valid_percentage,
+            is_valid_signer,
         },
+        keypair::signer_from_path,
     },
     solana_client::rpc_client::RpcClient,
     solana_notifier::Notifier,
@@ -22,6 +33,7 @@ use {
         commitment_config::CommitmentConfig,
         native_token::*,
         pubkey::Pubkey,
+        signature::{NullSigner, Signer},
         slot_history::{self, SlotHistory},
         stake_history::StakeHistory,
         sysvar,
@@ -33,10 +45,10 @@ use {
         error,
         fs::{self, File},
         io::Write,
-        path::PathBuf,
+        path::{Path, PathBuf},
         process,
         str::FromStr,
-        time::Duration,
+        time::{Duration, SystemTime, UNIX_EPOCH},
     },
     thiserror::Error,
 };
@@ -44,7 +56,12 @@ use {
 mod data_center_info;
 mod db;
 mod generic_stake_pool;
+mod notes;
+mod overrides;
+mod rebalance;
 mod rpc_client_utils;
+#[cfg(feature = "sqlite")]
+mod sqlite_store;
 mod stake_pool;
 mod stake_pool_v0;
 mod validator_list;
@@ -53,10 +70,11 @@ mod validators_app;
 type BoxResult<T> = Result<T, Box<dyn error::Error>>;
 type ValidatorList = HashSet<Pubkey>;
 type IdentityToParticipant = HashMap<Pubkey, Pubkey>;
+type SuspendedParticipants = HashMap<Pubkey, Epoch>;
 
 enum InfrastructureConcentrationAffectKind {
-    Destake(String),
-    Warn(String),
+    Destake(StakeStateReason),
+    Warn(ValidatorNote),
 }
 
 #[derive(Debug)]
@@ -68,19 +86,11 @@ enum InfrastructureConcentrationAffects {
 }
 
 impl InfrastructureConcentrationAffects {
-    fn destake_memo(concentration: f64) -> String {
-        format!(
-            "infrastructure concentration {:.1}% is too high; \
-            find a new data center",
-            concentration
-        )
+    fn destake_memo(concentration: f64) -> StakeStateReason {
+        StakeStateReason::InfrastructureConcentrationTooHigh { concentration }
     }
-    fn warning_memo(concentration: f64) -> String {
-        format!(
-            "infrastructure concentration {:.1}% is too high; \
-            consider finding a new data center",
-            concentration
-        )
+    fn warning_memo(concentration: f64) -> ValidatorNote {
+        ValidatorNote::InfrastructureConcentrationTooHigh { concentration }
     }
     pub fn memo(
         &self,
@@ -186,15 +196,29 @@ impl std::fmt::Display for Cluster {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DbBackend {
+    Yaml,
+    Sqlite,
+}
+
 #[derive(Debug)]
 struct Config {
     json_rpc_url: String,
     cluster: Cluster,
     db_path: PathBuf,
+    db_backend: DbBackend,
     markdown_path: Option<PathBuf>,
 
     dry_run: bool,
 
+    /// Where to write the transactions planned by a dry run. Defaults to the cluster's db path
+    dry_run_report_path: Option<PathBuf>,
+
+    /// Durable nonce accounts of the authorized staker, one for each transaction planned by a
+    /// dry run
+    nonce_accounts: Vec<Pubkey>,
+
     /// Quality validators produce within this percentage of the cluster average skip rate over
     /// the previous epoch
     quality_block_producer_percentage: usize,
@@ -257,6 +281,9 @@ struct Config {
     ///
     /// This setting is ignored if `cluster` is not `"mainnet-beta"`
     min_testnet_participation: Option<(/*n:*/ usize, /*m:*/ usize)>,
+
+    /// Manual overrides of validator classifications, applied after the automatic rules
+    overrides: Vec<ValidatorOverride>,
 }
 
 impl Config {
@@ -266,8 +293,11 @@ impl Config {
             json_rpc_url: "https://api.mainnet-beta.solana.com".to_string(),
             cluster: Cluster::MainnetBeta,
             db_path: PathBuf::default(),
+            db_backend: DbBackend::Yaml,
             markdown_path: None,
             dry_run: true,
+            dry_run_report_path: None,
+            nonce_accounts: vec![],
             quality_block_producer_percentage: 15,
             max_poor_block_producer_percentage: 20,
             max_commission: 100,
@@ -283,6 +313,7 @@ impl Config {
             enforce_min_self_stake: false,
             enforce_testnet_participation: false,
             min_testnet_participation: None,
+            overrides: vec![],
         }
     }
 
@@ -293,6 +324,88 @@ impl Config {
     fn cluster_db_path(&self) -> PathBuf {
         self.cluster_db_path_for(self.cluster)
     }
+
+    fn classification_store_for(
+        &self,
+        cluster: Cluster,
+    ) -> BoxResult<Box<dyn ClassificationStore>> {
+        let path = self.cluster_db_path_for(cluster);
+        match self.db_backend {
+            DbBackend::Yaml => Ok(Box::new(YamlStore::new(path))),
+            #[cfg(feature = "sqlite")]
+            DbBackend::Sqlite => Ok(Box::new(sqlite_store::SqliteStore::open(
+                path.join("classifications.sqlite3"),
+            )?)),
+            #[cfg(not(feature = "sqlite"))]
+            DbBackend::Sqlite => {
+                Err("The sqlite db backend requires building with `--features sqlite`".into())
+            }
+        }
+    }
+
+    fn classification_store(&self) -> BoxResult<Box<dyn ClassificationStore>> {
+        self.classification_store_for(self.cluster)
+    }
+
+    fn snapshot(&self) -> ConfigSnapshot {
+        ConfigSnapshot {
+            cluster: self.cluster.to_string(),
+            dry_run: self.dry_run,
+            quality_block_producer_percentage: self.quality_block_producer_percentage,
+            max_poor_block_producer_percentage: self.max_poor_block_producer_percentage,
+            max_commission: self.max_commission,
+            min_release_version: self
+                .min_release_version
+                .as_ref()
+                .map(|version| version.to_string()),
+            max_old_release_version_percentage: self.max_old_release_version_percentage,
+            max_poor_voter_percentage: self.max_poor_voter_percentage,
+            max_infrastructure_concentration: self.max_infrastructure_concentration,
+            infrastructure_concentration_affects: match self.infrastructure_concentration_affects {
+                InfrastructureConcentrationAffects::WarnAll => "warn".to_string(),
+                InfrastructureConcentrationAffects::DestakeAll => "destake-all".to_string(),
+                InfrastructureConcentrationAffects::DestakeNew => "destake-new".to_string(),
+                InfrastructureConcentrationAffects::DestakeListed(ref list) => {
+                    format!("destake-listed ({} validators)", list.len())
+                }
+            },
+            bad_cluster_average_skip_rate: self.bad_cluster_average_skip_rate,
+            min_epoch_credit_percentage_of_average: self.min_epoch_credit_percentage_of_average,
+            min_self_stake_lamports: self.min_self_stake_lamports,
+            enforce_min_self_stake: self.enforce_min_self_stake,
+            enforce_testnet_participation: self.enforce_testnet_participation,
+            min_testnet_participation: self.min_testnet_participation,
+        }
+    }
+}
+
+enum Command {
+    StakePool(Box<dyn GenericStakePool>),
+    DbMigrate,
+    DbImport(PathBuf),
+    DbExport(PathBuf),
+    DbDestaked(Pubkey),
+    SignPlan {
+        path: PathBuf,
+        output_path: PathBuf,
+        signer: Box<dyn Signer>,
+    },
+    ExecutePlan(PathBuf),
+    StakePoolStatus {
+        stake_pool: Box<dyn GenericStakePool>,
+        output_format: OutputFormat,
+    },
+    EmergencyDestake {
+        stake_pool: Box<dyn GenericStakePool>,
+        identity: Pubkey,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Table,
+    Json,
 }
 
 fn default_confirmed_block_cache_path() -> PathBuf {
@@ -300,6 +413,13 @@ fn default_confirmed_block_cache_path() -> PathBuf {
     PathBuf::from(home_dir).join(".cache/solana/som/confirmed-block-cache/")
 }
 
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
 fn app_version() -> String {
     // Determine version based on the environment variables set by Github Actions
     let tag = option_env!("GITHUB_REF")
@@ -311,7 +431,7 @@ fn app_version() -> String {
     })
 }
 
-fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
+fn get_config() -> BoxResult<(Config, RpcClient, Command)> {
     let default_confirmed_block_cache_path = default_confirmed_block_cache_path()
         .to_str()
         .unwrap()
@@ -347,6 +467,30 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
                 .takes_value(false)
                 .help("Confirm that the stake adjustments should actually be made")
         )
+        .arg(
+            Arg::with_name("dry_run_report")
+                .long("dry-run-report")
+                .value_name("PATH")
+                .takes_value(true)
+                .conflicts_with("confirm")
+                .help("Write the transactions planned by a dry run to this file, for them to be \
+                       signed with `sign-plan` and submitted with `execute-plan` \
+                       [default: <db-path>/data-<cluster>/dry-run-plan-<epoch>.yml]")
+        )
+        .arg(
+            Arg::with_name("nonce_account")
+                .long("nonce-account")
+                .value_name("ADDRESS")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .validator(is_pubkey)
+                .conflicts_with("confirm")
+                .help("Durable nonce account whose authority is the authorized staker, to plan a \
+                       dry run transaction on for offline signing. Only the first phase with \
+                       transactions is planned, and one is required for each of its \
+                       transactions")
+        )
         .arg(
             Arg::with_name("markdown")
                 .long("markdown")
@@ -361,6 +505,16 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
                 .default_value("db")
                 .help("Location for storing staking history")
         )
+        .arg(
+            Arg::with_name("db_backend")
+                .long("db-backend")
+                .value_name("BACKEND")
+                .takes_value(true)
+                .possible_values(&["yaml", "sqlite"])
+                .default_value("yaml")
+                .help("Storage format of the staking history. \
+                       The sqlite backend requires building with `--features sqlite`")
+        )
         .arg(
             Arg::with_name("quality_block_producer_percentage")
                 .long("quality-block-producer-percentage")
@@ -513,6 +667,19 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
                 .help("Enforce the minimum testnet participation requirement.\n
                        This setting is ignored if the --cluster is not `mainnet-beta`")
         )
+        .arg(
+            Arg::with_name("overrides")
+                .long("overrides")
+                .value_name("PATH")
+                .takes_value(true)
+                .help("YAML file of manual classification overrides. Each entry has an \
+                       `identity`, an `action` of `force_none`, `force_baseline` or \
+                       `exempt_from: [RULE, ...]`, an optional `expires_epoch`, and a `reason`. \
+                       Rules are commission, self_stake, infrastructure_concentration, \
+                       testnet_participation, vote_credits and release_version. All active \
+                       overrides of a validator apply, and `force_none` takes precedence over \
+                       `force_baseline`")
+        )
         .subcommand(
             SubCommand::with_name("stake-pool-v0").about("Use the stake-pool v0 solution")
             .arg(
@@ -527,11 +694,11 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
             .arg(
                 Arg::with_name("authorized_staker")
                     .index(2)
-                    .value_name("KEYPAIR")
-                    .validator(is_keypair)
+                    .value_name("SIGNER")
+                    .validator(is_valid_signer)
                     .required(true)
                     .takes_value(true)
-                    .help("Keypair of the authorized staker")
+                    .help("Signer of the authorized staker. A dry run only requires its pubkey")
             )
             .arg(
                 Arg::with_name("min_reserve_stake_balance")
@@ -550,6 +717,51 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
                     .default_value("5000")
                     .validator(is_amount)
             )
+            .arg(
+                Arg::with_name("max_churn_percentage")
+                    .long("max-churn-percentage")
+                    .value_name("PERCENTAGE")
+                    .takes_value(true)
+                    .default_value("100")
+                    .validator(is_valid_percentage)
+                    .help("Maximum stake to add or remove in an epoch, as a percentage of the \
+                           pool size. Adjustments beyond this limit are deferred to later epochs")
+            )
+            .subcommand(
+                SubCommand::with_name("status")
+                    .about("Display the stake of the pool and its validators, without making changes")
+                    .arg(
+                        Arg::with_name("output_format")
+                            .long("output")
+                            .value_name("FORMAT")
+                            .takes_value(true)
+                            .possible_values(&["table", "json"])
+                            .default_value("table")
+                            .help("Output format")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("emergency-destake")
+                    .about("Remove the stake of a validator right away, without waiting for the \
+                            next epoch")
+                    .arg(
+                        Arg::with_name("identity")
+                            .index(1)
+                            .value_name("IDENTITY")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(is_pubkey_or_keypair)
+                            .help("Identity of the validator to destake")
+                    )
+                    .arg(
+                        Arg::with_name("reason")
+                            .long("reason")
+                            .value_name("TEXT")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Why the validator is destaked, recorded in its classification")
+                    )
+            )
         )
         .subcommand(
             SubCommand::with_name("stake-pool").about("Use a stake pool")
@@ -565,11 +777,11 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
             .arg(
                 Arg::with_name("authorized_staker")
                     .index(2)
-                    .value_name("KEYPAIR")
-                    .validator(is_keypair)
+                    .value_name("SIGNER")
+                    .validator(is_valid_signer)
                     .required(true)
                     .takes_value(true)
-                    .help("Keypair of the authorized staker")
+                    .help("Signer of the authorized staker. A dry run only requires its pubkey")
             )
             .arg(
                 Arg::with_name("baseline_stake_amount")
@@ -579,10 +791,155 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
                     .default_value("5000")
                     .validator(is_amount)
             )
+            .arg(
+                Arg::with_name("max_churn_percentage")
+                    .long("max-churn-percentage")
+                    .value_name("PERCENTAGE")
+                    .takes_value(true)
+                    .default_value("100")
+                    .validator(is_valid_percentage)
+                    .help("Maximum stake to add or remove in an epoch, as a percentage of the \
+                           pool size. Adjustments beyond this limit are deferred to later epochs")
+            )
+            .arg(
+                Arg::with_name("reserve_target")
+                    .long("reserve-target")
+                    .value_name("SOL or PERCENTAGE%")
+                    .takes_value(true)
+                    .default_value("0")
+                    .validator(|value| value.parse::<ReserveTarget>().map(|_| ()))
+                    .help("Liquid stake to keep in the reserve for withdrawals, either an amount \
+                           or a percentage of the pool size. Bonus stake is decreased to refill \
+                           the reserve when it falls below this target")
+            )
+            .subcommand(
+                SubCommand::with_name("status")
+                    .about("Display the stake of the pool and its validators, without making changes")
+                    .arg(
+                        Arg::with_name("output_format")
+                            .long("output")
+                            .value_name("FORMAT")
+                            .takes_value(true)
+                            .possible_values(&["table", "json"])
+                            .default_value("table")
+                            .help("Output format")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("emergency-destake")
+                    .about("Remove the stake of a validator right away, without waiting for the \
+                            next epoch")
+                    .arg(
+                        Arg::with_name("identity")
+                            .index(1)
+                            .value_name("IDENTITY")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(is_pubkey_or_keypair)
+                            .help("Identity of the validator to destake")
+                    )
+                    .arg(
+                        Arg::with_name("reason")
+                            .long("reason")
+                            .value_name("TEXT")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Why the validator is destaked, recorded in its classification")
+                    )
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("db").about("Manage the classification database")
+            .setting(AppSettings::SubcommandRequiredElseHelp)
+            .subcommand(
+                SubCommand::with_name("migrate")
+                    .about("Rewrite all epoch classifications in the database with the current schema")
+            )
+            .subcommand(
+                SubCommand::with_name("import")
+                    .about("Import YAML epoch classifications into the database")
+                    .arg(
+                        Arg::with_name("path")
+                            .index(1)
+                            .value_name("PATH")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Directory containing the epoch-{N}.yml files to import")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("export")
+                    .about("Export the database as YAML epoch classifications")
+                    .arg(
+                        Arg::with_name("path")
+                            .index(1)
+                            .value_name("PATH")
+                            .takes_value(true)
+                            .required(true)
+                            .help("Directory to write the epoch-{N}.yml files into")
+                    )
+            )
+            .subcommand(
+                SubCommand::with_name("destaked")
+                    .about("List the epochs where a validator was classified for no stake")
+                    .arg(
+                        Arg::with_name("identity")
+                            .index(1)
+                            .value_name("IDENTITY")
+                            .takes_value(true)
+                            .required(true)
+                            .validator(is_pubkey_or_keypair)
+                            .help("Validator identity")
+                    )
+            )
+        )
+        .subcommand(
+            SubCommand::with_name("sign-plan")
+                .about("Sign the transactions of a dry run plan. Does not require network access")
+                .arg(
+                    Arg::with_name("path")
+                        .index(1)
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Plan written by a dry run")
+                )
+                .arg(
+                    Arg::with_name("signer")
+                        .long("signer")
+                        .value_name("SIGNER")
+                        .takes_value(true)
+                        .required(true)
+                        .validator(is_valid_signer)
+                        .help("Signer of the authorized staker, such as a keypair file or a \
+                               usb:// hardware wallet URI")
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .help("Write the signed plan to this file [default: PATH]")
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("execute-plan")
+                .about("Submit the transactions of a signed plan, then run a dry run again to \
+                        plan the next phase")
+                .arg(
+                    Arg::with_name("path")
+                        .index(1)
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Plan signed with `sign-plan`")
+                )
         )
         .get_matches();
 
     let dry_run = !matches.is_present("confirm");
+    let dry_run_report_path = value_t!(matches, "dry_run_report", PathBuf).ok();
+    let nonce_accounts = pubkeys_of(&matches, "nonce_account").unwrap_or_default();
     let cluster = match value_t_or_exit!(matches, "cluster", String).as_str() {
         "mainnet-beta" => Cluster::MainnetBeta,
         "testnet" => Cluster::Testnet,
@@ -612,6 +969,11 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
         process::exit(1);
     }
 
+    let overrides = match matches.value_of("overrides") {
+        Some(path) => read_overrides(Path::new(path))?,
+        None => vec![],
+    };
+
     let json_rpc_url = match cluster {
         Cluster::MainnetBeta => value_t!(matches, "json_rpc_url", String)
             .unwrap_or_else(|_| "http://api.mainnet-beta.solana.com".into()),
@@ -619,6 +981,11 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
             .unwrap_or_else(|_| "http://testnet.solana.com".into()),
     };
     let db_path = value_t_or_exit!(matches, "db_path", PathBuf);
+    let db_backend = match value_t_or_exit!(matches, "db_backend", String).as_str() {
+        "yaml" => DbBackend::Yaml,
+        "sqlite" => DbBackend::Sqlite,
+        _ => unreachable!(),
+    };
     let markdown_path = if matches.is_present("markdown") {
         Some(db_path.join("md"))
     } else {
@@ -645,8 +1012,11 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
         json_rpc_url,
         cluster,
         db_path,
+        db_backend,
         markdown_path,
         dry_run,
+        dry_run_report_path,
+        nonce_accounts,
         quality_block_producer_percentage,
         max_poor_block_producer_percentage,
         max_commission,
@@ -662,49 +1032,138 @@ fn get_config() -> BoxResult<(Config, RpcClient, Box<dyn GenericStakePool>)> {
         enforce_min_self_stake,
         enforce_testnet_participation,
         min_testnet_participation,
+        overrides,
     };
 
     info!("RPC URL: {}", config.json_rpc_url);
     let rpc_client =
         RpcClient::new_with_timeout(config.json_rpc_url.clone(), Duration::from_secs(180));
 
+    if let ("db", Some(matches)) = matches.subcommand() {
+        return match matches.subcommand() {
+            ("migrate", _) => Ok((config, rpc_client, Command::DbMigrate)),
+            ("import", Some(matches)) => {
+                let path = value_t_or_exit!(matches, "path", PathBuf);
+                Ok((config, rpc_client, Command::DbImport(path)))
+            }
+            ("export", Some(matches)) => {
+                let path = value_t_or_exit!(matches, "path", PathBuf);
+                Ok((config, rpc_client, Command::DbExport(path)))
+            }
+            ("destaked", Some(matches)) => {
+                let identity = pubkey_of(matches, "identity").unwrap();
+                Ok((config, rpc_client, Command::DbDestaked(identity)))
+            }
+            _ => unreachable!(),
+        };
+    }
+
+    match matches.subcommand() {
+        ("sign-plan", Some(matches)) => {
+            let path = value_t_or_exit!(matches, "path", PathBuf);
+            let output_path = value_t!(matches, "output", PathBuf).unwrap_or_else(|_| path.clone());
+            let signer = signer_from_path(
+                matches,
+                matches.value_of("signer").unwrap(),
+                "signer",
+                &mut None,
+            )?;
+            return Ok((
+                config,
+                rpc_client,
+                Command::SignPlan {
+                    path,
+                    output_path,
+                    signer,
+                },
+            ));
+        }
+        ("execute-plan", Some(matches)) => {
+            let path = value_t_or_exit!(matches, "path", PathBuf);
+            return Ok((config, rpc_client, Command::ExecutePlan(path)));
+        }
+        _ => {}
+    }
+
     // Sanity check that the RPC endpoint is healthy before performing too much work
     rpc_client
         .get_health()
         .map_err(|err| format!("RPC endpoint is unhealthy: {:?}", err))?;
 
-    let stake_pool: Box<dyn GenericStakePool> = match matches.subcommand() {
-        ("stake-pool-v0", Some(matches)) => {
-            let authorized_staker = keypair_of(&matches, "authorized_staker").unwrap();
-            let reserve_stake_address = pubkey_of(&matches, "reserve_stake_address").unwrap();
-            let min_reserve_stake_balance =
-                sol_to_lamports(value_t_or_exit!(matches, "min_reserve_stake_balance", f64));
-            let baseline_stake_amount =
-                sol_to_lamports(value_t_or_exit!(matches, "baseline_stake_amount", f64));
-            Box::new(stake_pool_v0::new(
-                &rpc_client,
-                authorized_staker,
-                baseline_stake_amount,
-                reserve_stake_address,
-                min_reserve_stake_balance,
-            )?)
-        }
-        ("stake-pool", Some(matches)) => {
-            let authorized_staker = keypair_of(&matches, "authorized_staker").unwrap();
-            let pool_address = pubkey_of(&matches, "pool_address").unwrap();
-            let baseline_stake_amount =
-                sol_to_lamports(value_t_or_exit!(matches, "baseline_stake_amount", f64));
-            Box::new(stake_pool::new(
-                &rpc_client,
-                authorized_staker,
-                pool_address,
-                baseline_stake_amount,
-            )?)
+    let (stake_pool, stake_pool_subcommand): (Box<dyn GenericStakePool>, _) =
+        match matches.subcommand() {
+            ("stake-pool-v0", Some(matches)) => {
+                let offline = config.dry_run || matches.subcommand_name() == Some("status");
+                let authorized_staker = authorized_staker_of(matches, offline)?;
+                let reserve_stake_address = pubkey_of(&matches, "reserve_stake_address").unwrap();
+                let min_reserve_stake_balance =
+                    sol_to_lamports(value_t_or_exit!(matches, "min_reserve_stake_balance", f64));
+                let baseline_stake_amount =
+                    sol_to_lamports(value_t_or_exit!(matches, "baseline_stake_amount", f64));
+                let max_churn_percentage = value_t_or_exit!(matches, "max_churn_percentage", usize);
+                let stake_pool = stake_pool_v0::new(
+                    &rpc_client,
+                    authorized_staker,
+                    baseline_stake_amount,
+                    max_churn_percentage,
+                    reserve_stake_address,
+                    min_reserve_stake_balance,
+                )?;
+                (Box::new(stake_pool), matches.subcommand())
+            }
+            ("stake-pool", Some(matches)) => {
+                let offline = config.dry_run || matches.subcommand_name() == Some("status");
+                let authorized_staker = authorized_staker_of(matches, offline)?;
+                let pool_address = pubkey_of(&matches, "pool_address").unwrap();
+                let baseline_stake_amount =
+                    sol_to_lamports(value_t_or_exit!(matches, "baseline_stake_amount", f64));
+                let max_churn_percentage = value_t_or_exit!(matches, "max_churn_percentage", usize);
+                let reserve_target = value_t_or_exit!(matches, "reserve_target", ReserveTarget);
+                let stake_pool = stake_pool::new(
+                    &rpc_client,
+                    authorized_staker,
+                    pool_address,
+                    baseline_stake_amount,
+                    max_churn_percentage,
+                    reserve_target,
+                )?;
+                (Box::new(stake_pool), matches.subcommand())
+            }
+            _ => unreachable!(),
+        };
+
+    let command = match stake_pool_subcommand {
+        ("status", Some(matches)) => {
+            let output_format = match matches.value_of("output_format") {
+                Some("json") => OutputFormat::Json,
+                _ => OutputFormat::Table,
+            };
+            Command::StakePoolStatus {
+                stake_pool,
+                output_format,
+            }
         }
-        _ => unreachable!(),
+        ("emergency-destake", Some(matches)) => Command::EmergencyDestake {
+            stake_pool,
+            identity: pubkey_of(matches, "identity").unwrap(),
+            reason: value_t_or_exit!(matches, "reason", String),
+        },
+        _ => Command::StakePool(stake_pool),
     };
 
-    Ok((config, rpc_client, stake_pool))
+    Ok((config, rpc_client, command))
+}
+
+// Neither a dry run nor a status query signs, so they accept the pubkey of an authorized staker
+// whose key is kept offline
+fn authorized_staker_of(matches: &ArgMatches, offline: bool) -> BoxResult<Box<dyn Signer>> {
+    let path = matches.value_of("authorized_staker").unwrap();
+    if offline {
+        if let Ok(pubkey) = Pubkey::from_str(path) {
+            return Ok(Box::new(NullSigner::new(&pubkey)));
+        }
+    }
+    signer_from_path(matches, path, "authorized_staker", &mut None)
 }
 
 type ClassifyResult = (
@@ -712,8 +1171,8 @@ type ClassifyResult = (
     ValidatorList,
     // poor
     ValidatorList,
-    // classification reason
-    HashMap<Pubkey, String>,
+    // block production
+    HashMap<Pubkey, BlockProductionStats>,
     // cluster_skip_rate
     usize,
     // too_many_poor_block_producers
@@ -729,7 +1188,7 @@ fn classify_producers(
     let mut poor_block_producers = HashSet::new();
     let mut quality_block_producers = HashSet::new();
     let mut blocks_and_slots = HashMap::new();
-    let mut reason_msg = HashMap::new();
+    let mut block_production = HashMap::new();
 
     let mut total_blocks = 0;
     let mut total_slots = 0;
@@ -756,12 +1215,13 @@ fn classify_producers(
     for (validator_identity, (blocks, slots)) in blocks_and_slots {
         let skip_rate: usize = 100 - (blocks * 100 / slots);
 
-        let msg = format!(
-            "{} blocks in {} slots, {:.2}% skip rate",
-            blocks, slots, skip_rate
-        );
-        trace!("Validator {} produced {}", validator_identity, msg);
-        reason_msg.insert(validator_identity, msg);
+        let stats = BlockProductionStats {
+            blocks,
+            slots,
+            skip_rate,
+        };
+        trace!("Validator {} produced {}", validator_identity, stats);
+        block_production.insert(validator_identity, stats);
 
         if skip_rate.saturating_sub(config.quality_block_producer_percentage)
             > cluster_average_skip_rate
@@ -790,7 +1250,7 @@ fn classify_producers(
     Ok((
         quality_block_producers,
         poor_block_producers,
-        reason_msg,
+        block_production,
         cluster_average_skip_rate,
         too_many_poor_block_producers,
     ))
@@ -955,20 +1415,25 @@ fn get_self_stake_by_vote_account(
 fn get_testnet_participation(config: &Config) -> BoxResult<Option<HashMap<Pubkey, bool>>> {
     if let Some((n, m)) = &config.min_testnet_participation {
         assert_eq!(config.cluster, Cluster::MainnetBeta);
-        let latest_testnet_epoch_classification =
-            EpochClassification::load_latest(&config.cluster_db_path_for(Cluster::Testnet))?
-                .ok_or("Unable to load testnet epoch classification")?
-                .1
-                .into_current();
+        let testnet_store = config.classification_store_for(Cluster::Testnet)?;
+        let latest_testnet_epoch_classification = testnet_store
+            .load_latest()?
+            .ok_or("Unable to load testnet epoch classification")?
+            .1
+            .into_current();
+        let testnet_stake_state_history = testnet_store.load_stake_state_history()?;
 
         let testnet_participation = latest_testnet_epoch_classification
             .validator_classifications
             .unwrap()
             .drain()
-            .filter_map(|(_, validator_classification)| {
-                validator_classification
-                    .participant
-                    .map(|participant| (participant, validator_classification.staked_for(*n, *m)))
+            .filter_map(|(identity, validator_classification)| {
+                validator_classification.participant.map(|participant| {
+                    (
+                        participant,
+                        testnet_stake_state_history.staked_for(&identity, *n, *m),
+                    )
+                })
             })
             .collect::<HashMap<_, _>>();
 
@@ -1002,8 +1467,10 @@ fn classify(
     epoch: Epoch,
     validator_list: &ValidatorList,
     identity_to_participant: &IdentityToParticipant,
+    suspended_participants: &SuspendedParticipants,
     previous_epoch_validator_classifications: Option<&ValidatorClassificationByIdentity>,
-) -> BoxResult<EpochClassificationV1> {
+) -> BoxResult<EpochClassificationV2> {
+    let started_at = unix_timestamp();
     let last_epoch = epoch - 1;
 
     let testnet_participation = get_testnet_participation(config)?;
@@ -1057,26 +1524,30 @@ fn classify(
     let self_stake_by_vote_account =
         get_self_stake_by_vote_account(rpc_client, epoch, &vote_account_info)?;
 
+    let cluster_node_versions: HashMap<Pubkey, semver::Version> = rpc_client
+        .get_cluster_nodes()?
+        .into_iter()
+        .filter_map(|rpc_contact_info| {
+            if let Ok(identity) = Pubkey::from_str(&rpc_contact_info.pubkey) {
+                if validator_list.contains(&identity) {
+                    if let Some(ref version) = rpc_contact_info.version {
+                        if let Ok(semver) = semver::Version::parse(version) {
+                            return Some((identity, semver));
+                        }
+                    }
+                }
+            }
+            None
+        })
+        .collect();
+
     let (cluster_nodes_with_old_version, min_release_version): (HashMap<String, _>, _) =
         match config.min_release_version {
             Some(ref min_release_version) => (
-                rpc_client
-                    .get_cluster_nodes()?
-                    .into_iter()
-                    .filter_map(|rpc_contact_info| {
-                        if let Ok(identity) = Pubkey::from_str(&rpc_contact_info.pubkey) {
-                            if validator_list.contains(&identity) {
-                                if let Some(ref version) = rpc_contact_info.version {
-                                    if let Ok(semver) = semver::Version::parse(version) {
-                                        if semver < *min_release_version {
-                                            return Some((rpc_contact_info.pubkey, semver));
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        None
-                    })
+                cluster_node_versions
+                    .iter()
+                    .filter(|(_, semver)| *semver < min_release_version)
+                    .map(|(identity, semver)| (identity.to_string(), semver.clone()))
                     .collect(),
                 min_release_version.to_string(),
             ),
@@ -1093,7 +1564,7 @@ fn classify(
     let (
         quality_block_producers,
         poor_block_producers,
-        block_producer_classification_reason,
+        block_production,
         cluster_average_skip_rate,
         too_many_poor_block_producers,
     ) = classify_block_producers(&rpc_client, &config, last_epoch)?;
@@ -1121,68 +1592,93 @@ fn classify(
         too_many_poor_voters,
     ) = classify_poor_voters(&config, &vote_account_info);
 
+    let inputs = ClassificationInputs {
+        epoch: last_epoch,
+        total_active_stake,
+        cluster_average_skip_rate,
+        avg_epoch_credits,
+        validators: vote_account_info
+            .iter()
+            .filter(|vai| validator_list.contains(&vai.identity))
+            .map(|vai| {
+                (
+                    vai.identity,
+                    ValidatorInputs {
+                        commission: vai.commission,
+                        epoch_credits: vai.epoch_credits,
+                        self_stake: self_stake_by_vote_account
+                            .get(&vai.vote_address)
+                            .cloned()
+                            .unwrap_or_default(),
+                        release_version: cluster_node_versions
+                            .get(&vai.identity)
+                            .map(|semver| semver.to_string()),
+                        block_production: block_production.get(&vai.identity).cloned(),
+                    },
+                )
+            })
+            .collect(),
+    };
+
     let mut notes = vec![
-        format!(
-            "Minimum vote credits required for epoch {}: {} (cluster average: {}, grace: {}%)",
-            last_epoch,
+        EpochNote::MinimumVoteCredits {
+            epoch: last_epoch,
             min_epoch_credits,
             avg_epoch_credits,
-            config.min_epoch_credit_percentage_of_average,
-        ),
-        format!(
-            "Maximum allowed skip rate for epoch {}: {:.2}% (cluster average: {:.2}%, grace: {}%)",
-            last_epoch,
-            cluster_average_skip_rate + config.quality_block_producer_percentage,
+            grace_percentage: config.min_epoch_credit_percentage_of_average,
+        },
+        EpochNote::MaximumSkipRate {
+            epoch: last_epoch,
+            max_skip_rate: cluster_average_skip_rate + config.quality_block_producer_percentage,
             cluster_average_skip_rate,
-            config.quality_block_producer_percentage,
-        ),
-        format!("Solana release {} or greater required", min_release_version),
-        format!("Maximum commission: {}%", config.max_commission),
-        format!(
-            "Minimum required self stake: {}",
-            Sol(config.min_self_stake_lamports)
-        ),
+            grace_percentage: config.quality_block_producer_percentage,
+        },
+        EpochNote::MinimumReleaseVersion {
+            version: min_release_version.clone(),
+        },
+        EpochNote::MaximumCommission {
+            commission: config.max_commission,
+        },
+        EpochNote::MinimumSelfStake {
+            lamports: config.min_self_stake_lamports,
+        },
     ];
     if let Some(max_infrastructure_concentration) = config.max_infrastructure_concentration {
-        notes.push(format!(
-            "Maximum infrastructure concentration: {:0}%",
-            max_infrastructure_concentration
-        ));
+        notes.push(EpochNote::MaximumInfrastructureConcentration {
+            percentage: max_infrastructure_concentration,
+        });
     }
-    if let Some((n, m)) = &config.min_testnet_participation {
-        notes.push(format!(
-            "Participants must maintain Baseline or Bonus stake level for {} of the last {} Testnet epochs",
-            n, m
-        ));
+    if let Some((n, m)) = config.min_testnet_participation {
+        notes.push(EpochNote::MinimumTestnetParticipation { n, m });
     }
 
     if cluster_average_skip_rate > config.bad_cluster_average_skip_rate {
-        notes.push("Cluster average skip rate is poor".to_string());
+        notes.push(EpochNote::PoorClusterAverageSkipRate);
     }
     if too_many_poor_voters {
-        notes.push(format!(
-            "Too many validators classified as poor voters for epoch {}: {}% (limit: {}%)",
-            last_epoch, poor_voter_percentage, config.max_poor_voter_percentage
-        ));
+        notes.push(EpochNote::TooManyPoorVoters {
+            epoch: last_epoch,
+            percentage: poor_voter_percentage,
+            limit: config.max_poor_voter_percentage,
+        });
     }
     if too_many_old_validators {
-        notes.push(format!(
-            "Over {}% of validators classified as running an older release",
-            config.max_old_release_version_percentage
-        ));
+        notes.push(EpochNote::TooManyOldReleases {
+            limit: config.max_old_release_version_percentage,
+        });
     }
     if too_many_poor_block_producers {
-        notes.push(format!(
-            "Over {}% of validators classified as poor block producers in epoch {}",
-            config.max_poor_block_producer_percentage, last_epoch,
-        ));
+        notes.push(EpochNote::TooManyPoorBlockProducers {
+            epoch: last_epoch,
+            limit: config.max_poor_block_producer_percentage,
+        });
     }
 
     let validator_classifications = if too_many_poor_voters
         || too_many_old_validators
         || too_many_poor_block_producers
     {
-        notes.push("Stake adjustments skipped this epoch".to_string());
+        notes.push(EpochNote::StakeAdjustmentsSkipped);
         None
     } else {
         let mut validator_classifications = HashMap::new();
@@ -1212,7 +1708,6 @@ fn classify(
 
             let mut previous_data_center_residency = previous_classification
                 .map(|vc| vc.data_center_residency.clone())
-                .flatten()
                 .unwrap_or_default();
 
             let previous_stake_state = previous_classification
@@ -1224,15 +1719,20 @@ fn classify(
                 .cloned()
                 .unwrap_or_default();
 
-            let block_producer_classification_reason_msg = block_producer_classification_reason
-                .get(&identity)
-                .cloned()
-                .unwrap_or_default();
-            let vote_credits_msg =
-                format!("{} credits earned in epoch {}", epoch_credits, last_epoch);
+            let validator_block_production =
+                block_production.get(&identity).cloned().unwrap_or_default();
 
             let mut validator_notes = vec![];
 
+            // Every active override of the validator applies: their exemptions are merged, and a
+            // forced stake state replaces the classification
+            let validator_overrides = active_overrides(&config.overrides, &identity, epoch);
+            let exempt = |rule| {
+                validator_overrides
+                    .iter()
+                    .any(|validator_override| validator_override.exempts(rule))
+            };
+
             let infrastructure_concentration_destake_reason = infrastructure_concentration_too_high
                 .get(&identity)
                 .map(|concentration| {
@@ -1242,6 +1742,7 @@ fn classify(
                         *concentration,
                     )
                 })
+                .filter(|_| !exempt(OverrideRule::InfrastructureConcentration))
                 .and_then(|affect| match affect {
                     InfrastructureConcentrationAffectKind::Destake(reason) => Some(reason),
                     InfrastructureConcentrationAffectKind::Warn(reason) => {
@@ -1250,22 +1751,24 @@ fn classify(
                     }
                 });
 
-            let insufficent_self_stake_msg =
-                format!("insufficient self stake: {}", Sol(self_stake));
             if !config.enforce_min_self_stake && self_stake < config.min_self_stake_lamports {
-                validator_notes.push(insufficent_self_stake_msg.clone());
+                validator_notes.push(ValidatorNote::InsufficientSelfStake {
+                    self_stake,
+                    required: config.min_self_stake_lamports,
+                });
             }
 
             let insufficent_testnet_participation = testnet_participation
                 .as_ref()
+                .filter(|_| !exempt(OverrideRule::TestnetParticipation))
                 .map(|testnet_participation| {
                     if let Some(participant) = participant {
                         if !testnet_participation.get(&participant).unwrap_or(&true) {
-                            let note = "insufficient testnet participation".to_string();
                             if config.enforce_testnet_participation {
-                                return Some(note);
+                                return Some(StakeStateReason::InsufficientTestnetParticipation);
                             } else {
-                                validator_notes.push(note);
+                                validator_notes
+                                    .push(ValidatorNote::InsufficientTestnetParticipation);
                             }
                         }
                     }
@@ -1273,54 +1776,80 @@ fn classify(
                 })
                 .flatten();
 
-            let (stake_state, reason) = if let Some(reason) =
-                infrastructure_concentration_destake_reason
-            {
+            let suspended_until_epoch = participant
+                .and_then(|participant| suspended_participants.get(&participant))
+                .cloned();
+
+            let (stake_state, reason) = if let Some(until_epoch) = suspended_until_epoch {
+                (
+                    ValidatorStakeState::None,
+                    StakeStateReason::ParticipantSuspended { until_epoch },
+                )
+            } else if let Some(reason) = infrastructure_concentration_destake_reason {
                 (ValidatorStakeState::None, reason)
-            } else if config.enforce_min_self_stake && self_stake < config.min_self_stake_lamports {
-                (ValidatorStakeState::None, insufficent_self_stake_msg)
-            } else if commission > config.max_commission {
+            } else if config.enforce_min_self_stake
+                && self_stake < config.min_self_stake_lamports
+                && !exempt(OverrideRule::SelfStake)
+            {
                 (
                     ValidatorStakeState::None,
-                    format!("commission is too high: {}% commission", commission),
+                    StakeStateReason::InsufficientSelfStake {
+                        self_stake,
+                        required: config.min_self_stake_lamports,
+                    },
+                )
+            } else if commission > config.max_commission && !exempt(OverrideRule::Commission) {
+                (
+                    ValidatorStakeState::None,
+                    StakeStateReason::CommissionTooHigh {
+                        commission,
+                        max_commission: config.max_commission,
+                    },
                 )
             } else if let Some(insufficent_testnet_participation) =
                 insufficent_testnet_participation
             {
                 (ValidatorStakeState::None, insufficent_testnet_participation)
-            } else if poor_voters.contains(&identity) {
+            } else if poor_voters.contains(&identity) && !exempt(OverrideRule::VoteCredits) {
                 (
                     ValidatorStakeState::None,
-                    format!("insufficient vote credits: {}", vote_credits_msg),
+                    StakeStateReason::InsufficientVoteCredits {
+                        earned: epoch_credits,
+                        required: min_epoch_credits,
+                        epoch: last_epoch,
+                    },
                 )
-            } else if cluster_nodes_with_old_version.contains_key(&identity.to_string()) {
+            } else if cluster_nodes_with_old_version.contains_key(&identity.to_string())
+                && !exempt(OverrideRule::ReleaseVersion)
+            {
                 (
                     ValidatorStakeState::None,
-                    format!(
-                        "Outdated solana release: {}",
-                        cluster_nodes_with_old_version
+                    StakeStateReason::OutdatedRelease {
+                        version: cluster_nodes_with_old_version
                             .get(&identity.to_string())
                             .unwrap()
-                    ),
+                            .to_string(),
+                        required: min_release_version.clone(),
+                    },
                 )
             } else if quality_block_producers.contains(&identity) {
                 (
                     ValidatorStakeState::Bonus,
-                    format!(
-                        "good block production during epoch {}: {}",
-                        last_epoch, block_producer_classification_reason_msg
-                    ),
+                    StakeStateReason::GoodBlockProduction {
+                        epoch: last_epoch,
+                        block_production: validator_block_production,
+                    },
                 )
             } else if poor_block_producers.contains(&identity) {
                 (
                     ValidatorStakeState::Baseline,
-                    format!(
-                        "poor block production during epoch {}: {}",
-                        last_epoch, block_producer_classification_reason_msg
-                    ),
+                    StakeStateReason::PoorBlockProduction {
+                        epoch: last_epoch,
+                        block_production: validator_block_production,
+                    },
                 )
             } else {
-                assert!(!poor_voters.contains(&identity));
+                assert!(!poor_voters.contains(&identity) || exempt(OverrideRule::VoteCredits));
                 assert!(not_in_leader_schedule.contains(&identity));
                 (
                     // If the validator is not in the leader schedule but was Bonus previously,
@@ -1335,10 +1864,40 @@ fn classify(
                     } else {
                         ValidatorStakeState::Baseline
                     },
-                    format!("no leader slots; {}", vote_credits_msg),
+                    StakeStateReason::NoLeaderSlots {
+                        credits: epoch_credits,
+                        epoch: last_epoch,
+                    },
                 )
             };
 
+            for validator_override in &validator_overrides {
+                info!(
+                    "Classification of {} overridden ({}): {}",
+                    identity, validator_override.action, validator_override.reason
+                );
+                validator_notes.push(ValidatorNote::Override {
+                    action: validator_override.action.clone(),
+                    reason: validator_override.reason.clone(),
+                    expires_epoch: validator_override.expires_epoch,
+                });
+                notes.push(EpochNote::ValidatorOverride {
+                    identity,
+                    action: validator_override.action.clone(),
+                    reason: validator_override.reason.clone(),
+                });
+            }
+
+            let (stake_state, reason) = match forced_stake_state(&validator_overrides) {
+                Some((forced_stake_state, validator_override)) => (
+                    forced_stake_state,
+                    StakeStateReason::Override {
+                        reason: validator_override.reason.clone(),
+                    },
+                ),
+                None => (stake_state, reason),
+            };
+
             // Data center seniority increases with Bonus stake and decreases
             // otherwise
             previous_data_center_residency
@@ -1376,38 +1935,42 @@ fn classify(
                 reason
             );
 
-            let mut stake_states = previous_classification
-                .map(|vc| vc.stake_states.clone())
-                .flatten()
-                .unwrap_or_default();
-            stake_states.insert(0, (stake_state, reason.clone()));
-
             validator_classifications.insert(
                 identity,
                 ValidatorClassification {
                     identity,
                     vote_address,
                     stake_state,
-                    stake_states: Some(stake_states),
                     stake_action: None,
                     stake_state_reason: reason,
                     notes: validator_notes,
-                    data_center_residency: Some(data_center_residency),
-                    current_data_center: Some(current_data_center.clone()),
+                    data_center_residency,
+                    current_data_center: current_data_center.clone(),
                     participant,
                 },
             );
         }
-        notes.push(format!(
-            "{} validators processed",
-            validator_classifications.len()
-        ));
+        notes.push(EpochNote::ValidatorsProcessed {
+            count: validator_classifications.len(),
+        });
 
         Some(validator_classifications)
     };
-    notes.push(format!("Active stake: {}", Sol(total_active_stake)));
-
-    Ok(EpochClassificationV1 {
+    notes.push(EpochNote::ActiveStake {
+        lamports: total_active_stake,
+    });
+
+    Ok(EpochClassificationV2 {
+        run: RunMetadata {
+            origin: RunOrigin::Bot,
+            status: RunStatus::Classified,
+            bot_version: app_version(),
+            json_rpc_url: config.json_rpc_url.clone(),
+            started_at,
+            completed_at: started_at,
+        },
+        config: config.snapshot(),
+        inputs,
         data_center_info: data_centers.info,
         validator_classifications,
         notes,
@@ -1417,13 +1980,36 @@ fn classify(
 fn main() -> BoxResult<()> {
     solana_logger::setup_with_default("solana=info");
 
-    let (config, rpc_client, mut stake_pool) = get_config()?;
+    let (config, rpc_client, command) = get_config()?;
+    let mut stake_pool = match command {
+        Command::StakePool(stake_pool) => stake_pool,
+        command => return process_command(&config, &rpc_client, command),
+    };
 
     info!("Loading participants...");
     let participants = get_participants_with_state(
         &RpcClient::new("https://api.mainnet-beta.solana.com".to_string()),
-        Some(ParticipantState::Approved),
-    )?;
+        None,
+    )?
+    .into_iter()
+    .filter(|(_, participant)| {
+        matches!(
+            participant.state,
+            ParticipantState::Approved | ParticipantState::Suspended { .. }
+        )
+    })
+    .collect::<HashMap<_, _>>();
+
+    let suspended_participants = participants
+        .iter()
+        .filter_map(|(address, participant)| match participant.state {
+            ParticipantState::Suspended { until_epoch } => Some((*address, until_epoch)),
+            _ => None,
+        })
+        .collect::<SuspendedParticipants>();
+    if !suspended_participants.is_empty() {
+        info!("{} participants suspended", suspended_participants.len());
+    }
 
     let (mainnet_identity_to_participant, testnet_identity_to_participant): (
         IdentityToParticipant,
@@ -1433,7 +2019,7 @@ fn main() -> BoxResult<()> {
         .map(
             |(
                 participant,
-                Participant {
+                ParticipantV2 {
                     mainnet_identity,
                     testnet_identity,
                     ..
@@ -1474,37 +2060,65 @@ fn main() -> BoxResult<()> {
     }
 
     info!("Data directory: {}", config.cluster_db_path().display());
+    let store = config.classification_store()?;
+
+    let previous_epoch_classification = store
+        .load_previous(epoch)?
+        .map(|p| p.1)
+        .unwrap_or_default()
+        .into_current();
+
+    // `first_time` is true until a run has completed all stake adjustments for this epoch. A run
+    // that was interrupted is resumed from its saved classification. A dry run makes no stake
+    // adjustments, so it leaves the run `Classified` for the confirmed run to resume
+    let (mut epoch_classification, first_time) = if store.exists(epoch)? {
+        let epoch_classification = store.load(epoch)?.into_current();
+        match epoch_classification.run.status {
+            RunStatus::Applied => {
+                info!("Classification for {} already exists", epoch);
+                (epoch_classification, false)
+            }
+            status => {
+                info!(
+                    "Resuming interrupted run for {} (status: {:?})",
+                    epoch, status
+                );
+                (epoch_classification, true)
+            }
+        }
+    } else {
+        let epoch_classification = classify(
+            &rpc_client,
+            &config,
+            epoch,
+            &validator_list,
+            &identity_to_participant,
+            &suspended_participants,
+            previous_epoch_classification
+                .validator_classifications
+                .as_ref(),
+        )?;
+        store.save(
+            epoch,
+            &EpochClassification::new(epoch_classification.clone()),
+        )?;
+        (epoch_classification, true)
+    };
 
-    let previous_epoch_classification =
-        EpochClassification::load_previous(epoch, &config.cluster_db_path())?
-            .map(|p| p.1)
-            .unwrap_or_default()
-            .into_current();
-
-    let (mut epoch_classification, first_time) =
-        if EpochClassification::exists(epoch, &config.cluster_db_path()) {
-            info!("Classification for {} already exists", epoch);
-            (
-                EpochClassification::load(epoch, &config.cluster_db_path())?.into_current(),
-                false,
-            )
-        } else {
-            (
-                classify(
-                    &rpc_client,
-                    &config,
-                    epoch,
-                    &validator_list,
-                    &identity_to_participant,
-                    previous_epoch_classification
-                        .validator_classifications
-                        .as_ref(),
-                )?,
-                true,
-            )
-        };
+    if first_time && !config.dry_run && epoch_classification.run.status == RunStatus::Classified {
+        epoch_classification.run.status = RunStatus::Applying;
+        epoch_classification.config.dry_run = false;
+        store.save(
+            epoch,
+            &EpochClassification::new(epoch_classification.clone()),
+        )?;
+    }
 
-    let mut notifications = epoch_classification.notes.clone();
+    let mut notifications = epoch_classification
+        .notes
+        .iter()
+        .map(|note| note.to_string())
+        .collect::<Vec<_>>();
 
     if let Some(ref mut validator_classifications) = epoch_classification.validator_classifications
     {
@@ -1546,12 +2160,26 @@ fn main() -> BoxResult<()> {
             })
             .collect();
 
-        let (stake_pool_notes, validator_stake_actions) =
-            stake_pool.apply(&rpc_client, config.dry_run, &desired_validator_stake)?;
-        notifications.extend(stake_pool_notes.clone());
+        // A dry run simulates every phase, unless nonce accounts are provided to plan the first
+        // phase for offline signing
+        let (stake_pool_notes, validator_stake_actions, plan) = if config.nonce_accounts.is_empty()
+        {
+            stake_pool.apply(&rpc_client, config.dry_run, &desired_validator_stake)?
+        } else {
+            stake_pool.plan(
+                &rpc_client,
+                &desired_validator_stake,
+                &config.nonce_accounts,
+            )?
+        };
+        write_transaction_report(epoch, &config, &plan)?;
+        notifications.extend(stake_pool_notes.iter().map(|note| note.to_string()));
         epoch_classification.notes.extend(stake_pool_notes);
 
         for (identity, stake_action) in validator_stake_actions {
+            if stake_action.unsuccessful() {
+                notifications.push(format!("Stake action for {}: {}", identity, stake_action));
+            }
             validator_classifications
                 .entry(identity)
                 .and_modify(|e| e.stake_action = Some(stake_action));
@@ -1565,10 +2193,17 @@ fn main() -> BoxResult<()> {
     }
 
     if first_time {
-        EpochClassification::new(epoch_classification).save(epoch, &config.cluster_db_path())?;
-        generate_markdown(epoch, &config)?;
+        if !config.dry_run {
+            epoch_classification.run.status = RunStatus::Applied;
+            epoch_classification.run.completed_at = unix_timestamp();
+            store.save(epoch, &EpochClassification::new(epoch_classification))?;
+            store.save_stake_state_history(&store.load_stake_state_history()?)?;
+        }
+
+        generate_markdown(epoch, &config, &*store)?;
 
-        // Only notify the user if this is the first run for this epoch
+        // Only notify the user if this is the first run for this epoch. Every dry run notifies, as
+        // it leaves the epoch for the confirmed run
         for notification in notifications {
             info!("notification: {}", notification);
             notifier.send(&notification);
@@ -1578,26 +2213,336 @@ fn main() -> BoxResult<()> {
     Ok(())
 }
 
-fn generate_markdown(epoch: Epoch, config: &Config) -> BoxResult<()> {
+// Writes the transactions of a dry run plan, or the outcome of each transaction sent by a
+// confirmed run
+fn write_transaction_report(
+    epoch: Epoch,
+    config: &Config,
+    plan: &[PlannedTransaction],
+) -> BoxResult<()> {
+    if config.dry_run {
+        let path = config.dry_run_report_path.clone().unwrap_or_else(|| {
+            config
+                .cluster_db_path()
+                .join(format!("dry-run-plan-{}.yml", epoch))
+        });
+        write_plan(&path, plan)?;
+
+        let failed = plan
+            .iter()
+            .filter(|planned_transaction| planned_transaction.simulation_error.is_some())
+            .count();
+        let dependent = plan
+            .iter()
+            .filter(|planned_transaction| planned_transaction.dependent)
+            .count();
+        info!(
+            "Dry run planned {} transactions, {} failed simulation and {} depend on earlier \
+             phases. Plan written to {}",
+            plan.len(),
+            failed,
+            dependent,
+            path.display()
+        );
+    } else {
+        let path = config
+            .cluster_db_path()
+            .join(format!("transactions-{}.yml", epoch));
+        write_plan(&path, plan)?;
+
+        let failed = plan
+            .iter()
+            .filter(|planned_transaction| {
+                planned_transaction.status != Some(TransactionStatus::Succeeded)
+            })
+            .count();
+        info!(
+            "{} transactions sent, {} failed. Report written to {}",
+            plan.len(),
+            failed,
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+fn write_plan(path: &Path, plan: &[PlannedTransaction]) -> BoxResult<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_yaml::to_string(plan)?)?;
+    Ok(())
+}
+
+fn read_plan(path: &Path) -> BoxResult<TransactionPlan> {
+    let file = File::open(path)
+        .map_err(|err| format!("Unable to open plan {}: {}", path.display(), err))?;
+    serde_yaml::from_reader(file)
+        .map_err(|err| format!("Invalid plan {}: {}", path.display(), err).into())
+}
+
+fn process_command(config: &Config, rpc_client: &RpcClient, command: Command) -> BoxResult<()> {
+    match command {
+        Command::DbMigrate => migrate_db(config),
+        Command::DbImport(path) => {
+            let imported = copy_store(&YamlStore::new(&path), &*config.classification_store()?)?;
+            info!("{} epochs imported from {}", imported, path.display());
+            Ok(())
+        }
+        Command::DbExport(path) => {
+            let exported = copy_store(&*config.classification_store()?, &YamlStore::new(&path))?;
+            info!("{} epochs exported to {}", exported, path.display());
+            Ok(())
+        }
+        Command::DbDestaked(identity) => {
+            for epoch in config.classification_store()?.destaked_epochs(&identity)? {
+                println!("{}", epoch);
+            }
+            Ok(())
+        }
+        Command::SignPlan {
+            path,
+            output_path,
+            signer,
+        } => {
+            let mut plan = read_plan(&path)?;
+            for planned_transaction in &mut plan {
+                planned_transaction.sign(&*signer)?;
+            }
+            write_plan(&output_path, &plan)?;
+            info!(
+                "{} transactions signed by {} written to {}",
+                plan.len(),
+                signer.pubkey(),
+                output_path.display()
+            );
+            Ok(())
+        }
+        Command::ExecutePlan(path) => {
+            let mut plan = read_plan(&path)?;
+            let result = execute_plan(rpc_client, &mut plan);
+            // Record the outcome of every submitted transaction, even if a phase failed
+            write_plan(&path, &plan)?;
+            result
+        }
+        Command::StakePoolStatus {
+            stake_pool,
+            output_format,
+        } => {
+            let store = config.classification_store()?;
+            let (epoch, validator_classifications) = match store.load_previous(Epoch::MAX)? {
+                Some((epoch, epoch_classification)) => (
+                    Some(epoch),
+                    epoch_classification
+                        .validator_classifications
+                        .unwrap_or_default(),
+                ),
+                None => (None, HashMap::default()),
+            };
+
+            let desired_validator_stake = validator_classifications
+                .values()
+                .map(|vc| ValidatorStake {
+                    identity: vc.identity,
+                    vote_address: vc.vote_address,
+                    stake_state: vc.stake_state,
+                })
+                .collect::<Vec<_>>();
+            let mut status = stake_pool.status(rpc_client, &desired_validator_stake)?;
+
+            for validator in &mut status.validators {
+                let stake_action = validator
+                    .identity
+                    .and_then(|identity| validator_classifications.get(&identity))
+                    .and_then(|vc| vc.stake_action.as_ref());
+                if let Some(ValidatorStakeAction {
+                    action:
+                        StakeAction::Adjust {
+                            target, current, ..
+                        },
+                    ..
+                }) = stake_action
+                {
+                    validator.target_stake = Some(*target);
+                    validator.classified_stake = Some(*current);
+                    validator.stake_difference = Some(
+                        (validator.active_stake + validator.transient_stake) as i64
+                            - *current as i64,
+                    );
+                }
+            }
+
+            match output_format {
+                OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+                OutputFormat::Table => print_stake_pool_status(&status, epoch),
+            }
+            Ok(())
+        }
+        Command::EmergencyDestake {
+            mut stake_pool,
+            identity,
+            reason,
+        } => {
+            let store = config.classification_store()?;
+            let epoch = rpc_client.get_epoch_info()?.epoch;
+            if !store.exists(epoch)? {
+                return Err(format!(
+                    "Epoch {} has not been classified yet, run the bot for this epoch first",
+                    epoch
+                )
+                .into());
+            }
+
+            let mut epoch_classification = store.load(epoch)?.into_current();
+            let validator_classification = epoch_classification
+                .validator_classifications
+                .as_mut()
+                .and_then(|validator_classifications| validator_classifications.get_mut(&identity))
+                .ok_or_else(|| {
+                    format!("Validator {} not classified in epoch {}", identity, epoch)
+                })?;
+
+            let (stake_action, plan) = stake_pool.emergency_destake(
+                rpc_client,
+                config.dry_run,
+                identity,
+                validator_classification.vote_address,
+            )?;
+            write_plan(
+                &config
+                    .cluster_db_path()
+                    .join(format!("emergency-destake-{}-{}.yml", epoch, identity)),
+                &plan,
+            )?;
+
+            let notifier = if config.dry_run {
+                Notifier::new("DRYRUN")
+            } else {
+                Notifier::default()
+            };
+            let notification = format!(
+                "Emergency destake of {}: {}. Stake action: {}",
+                identity, reason, stake_action
+            );
+            info!("notification: {}", notification);
+            notifier.send(&notification);
+
+            if !config.dry_run {
+                validator_classification.stake_state = ValidatorStakeState::None;
+                validator_classification.stake_state_reason =
+                    StakeStateReason::EmergencyDestake { reason };
+                validator_classification.stake_action = Some(stake_action);
+
+                store.save(epoch, &EpochClassification::new(epoch_classification))?;
+                let mut stake_state_history = store.load_stake_state_history()?;
+                stake_state_history.record_stake_state(identity, epoch, ValidatorStakeState::None);
+                store.save_stake_state_history(&stake_state_history)?;
+                generate_markdown(epoch, config, &*store)?;
+            }
+            Ok(())
+        }
+        Command::StakePool(_) => unreachable!(),
+    }
+}
+
+fn print_stake_pool_status(status: &StakePoolStatus, epoch: Option<Epoch>) {
+    println!(
+        "Reserve stake: {} ({})",
+        Sol(status.reserve_balance),
+        status.reserve_stake_address
+    );
+    match epoch {
+        Some(epoch) => println!(
+            "Stake differences since the classification of epoch {}",
+            epoch
+        ),
+        None => println!("No epoch classification available"),
+    }
+    println!();
+
+    println!(
+        "{:<44}  {:<44}  {:<22}  {:>16}  {:>16}  {:<15}  {:>16}  {:>16}  Busy transient stake account",
+        "Identity",
+        "Vote address",
+        "Status",
+        "Active",
+        "Transient",
+        "Transient state",
+        "Target",
+        "Difference"
+    );
+    let sol = |lamports: Option<u64>| {
+        lamports
+            .map(|lamports| format!("{:.3}", lamports_to_sol(lamports)))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    for validator in &status.validators {
+        println!(
+            "{:<44}  {:<44}  {:<22}  {:>16}  {:>16}  {:<15}  {:>16}  {:>16}  {}",
+            validator
+                .identity
+                .map(|identity| identity.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            validator.vote_address.to_string(),
+            validator.stake_status.as_deref().unwrap_or("-"),
+            sol(Some(validator.active_stake)),
+            sol(Some(validator.transient_stake)),
+            validator.transient_stake_state.as_deref().unwrap_or("-"),
+            sol(validator.target_stake),
+            validator
+                .stake_difference
+                .map(|difference| format!("{:+.3}", difference as f64 / LAMPORTS_PER_SOL as f64))
+                .unwrap_or_else(|| "-".to_string()),
+            validator
+                .transient_stake_address
+                .map(|address| address.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+        );
+    }
+
+    if !status.orphaned_stake_addresses.is_empty() {
+        println!();
+        println!("Orphaned staker stake accounts:");
+        for address in &status.orphaned_stake_addresses {
+            println!("  {}", address);
+        }
+    }
+}
+
+// Rewrite every epoch classification of every cluster under `config.db_path` with the current
+// schema
+fn migrate_db(config: &Config) -> BoxResult<()> {
+    for cluster in &[Cluster::MainnetBeta, Cluster::Testnet] {
+        let store = config.classification_store_for(*cluster)?;
+
+        let migrated = migrate_store(&*store)
+            .map_err(|err| format!("Failed to migrate {}: {}", cluster, err))?;
+        info!("{}: {} epochs migrated", cluster, migrated);
+    }
+    Ok(())
+}
+
+fn generate_markdown(
+    epoch: Epoch,
+    config: &Config,
+    store: &dyn ClassificationStore,
+) -> BoxResult<()> {
     let markdown_path = match config.markdown_path.as_ref() {
         Some(d) => d,
         None => return Ok(()),
     };
     fs::create_dir_all(&markdown_path)?;
 
-    let mut list = vec![(
-        epoch,
-        EpochClassification::load(epoch, &config.cluster_db_path())?.into_current(),
-    )];
+    let mut list = vec![(epoch, store.load(epoch)?.into_current())];
+
+    let stake_state_history = store.load_stake_state_history()?;
 
     let cluster_md = match config.cluster {
         Cluster::MainnetBeta => "Mainnet",
         Cluster::Testnet => "Testnet",
     };
 
-    while let Some((epoch, epoch_classification)) =
-        EpochClassification::load_previous(list.last().unwrap().0, &config.cluster_db_path())?
-    {
+    while let Some((epoch, epoch_classification)) = store.load_previous(list.last().unwrap().0)? {
         list.push((epoch, epoch_classification.into_current()));
     }
 
@@ -1622,7 +2567,7 @@ fn generate_markdown(epoch: Epoch, config: &Config) -> BoxResult<()> {
                     "### [[{1} Epoch {0}|{1}#Epoch-{0}]]",
                     epoch, cluster_md
                 ));
-                let stake_state_streak = classification.stake_state_streak();
+                let stake_state_streak = stake_state_history.stake_state_streak(&identity, epoch);
                 validator_markdown.push(format!(
                     "* Stake level: **{:?}**{}",
                     classification.stake_state,
@@ -1644,26 +2589,23 @@ fn generate_markdown(epoch: Epoch, config: &Config) -> BoxResult<()> {
                     "* Vote account address: {}",
                     classification.vote_address
                 ));
-                if let (Some(current_data_center), Some(data_center_residency)) = (
-                    classification.current_data_center,
-                    classification.data_center_residency,
-                ) {
-                    validator_markdown.push(format!("* Data Center: {}", current_data_center));
-
-                    if data_center_residency.len() > 1
-                        || (data_center_residency.len() == 1
-                            && !data_center_residency.contains_key(&current_data_center))
-                    {
-                        let data_center_residency = data_center_residency
-                            .keys()
-                            .cloned()
-                            .map(|data_center| data_center.to_string())
-                            .collect::<Vec<_>>();
-                        validator_markdown.push(format!(
-                            "* Resident Data Center(s): {}",
-                            data_center_residency.join(", ")
-                        ));
-                    }
+                let current_data_center = classification.current_data_center;
+                let data_center_residency = classification.data_center_residency;
+                validator_markdown.push(format!("* Data Center: {}", current_data_center));
+
+                if data_center_residency.len() > 1
+                    || (data_center_residency.len() == 1
+                        && !data_center_residency.contains_key(&current_data_center))
+                {
+                    let data_center_residency = data_center_residency
+                        .keys()
+                        .cloned()
+                        .map(|data_center| data_center.to_string())
+                        .collect::<Vec<_>>();
+                    validator_markdown.push(format!(
+                        "* Resident Data Center(s): {}",
+                        data_center_residency.join(", ")
+                    ));
                 }
 
                 for note in classification.notes {
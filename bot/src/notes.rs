@@ -0,0 +1,644 @@
+pub use registry_cli::{BlockProductionStats, StakeStateReason};
+use {
+    crate::overrides::OverrideAction,
+    serde::{Deserialize, Serialize},
+    solana_sdk::{clock::Epoch, native_token::Sol, pubkey::Pubkey},
+    std::fmt,
+};
+
+/// Informational note regarding a validator that does not affect its stake
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum ValidatorNote {
+    InfrastructureConcentrationTooHigh {
+        concentration: f64,
+    },
+    InsufficientSelfStake {
+        self_stake: u64,
+        required: u64,
+    },
+    InsufficientTestnetParticipation,
+    Override {
+        action: OverrideAction,
+        reason: String,
+        expires_epoch: Option<Epoch>,
+    },
+
+    /// Free-form note carried over from an `EpochClassificationV1`
+    Legacy(String),
+}
+
+impl fmt::Display for ValidatorNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InfrastructureConcentrationTooHigh { concentration } => write!(
+                f,
+                "infrastructure concentration {:.1}% is too high; consider finding a new data center",
+                concentration
+            ),
+            Self::InsufficientSelfStake { self_stake, .. } => {
+                write!(f, "insufficient self stake: {}", Sol(*self_stake))
+            }
+            Self::InsufficientTestnetParticipation => {
+                write!(f, "insufficient testnet participation")
+            }
+            Self::Override {
+                action,
+                reason,
+                expires_epoch,
+            } => {
+                write!(f, "classification overridden ({}): {}", action, reason)?;
+                if let Some(expires_epoch) = expires_epoch {
+                    write!(f, " (until epoch {})", expires_epoch)?;
+                }
+                Ok(())
+            }
+            Self::Legacy(note) => write!(f, "{}", note),
+        }
+    }
+}
+
+/// Informational note regarding an epoch
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum EpochNote {
+    MinimumVoteCredits {
+        epoch: Epoch,
+        min_epoch_credits: u64,
+        avg_epoch_credits: u64,
+        grace_percentage: usize,
+    },
+    MaximumSkipRate {
+        epoch: Epoch,
+        max_skip_rate: usize,
+        cluster_average_skip_rate: usize,
+        grace_percentage: usize,
+    },
+    MinimumReleaseVersion {
+        version: String,
+    },
+    MaximumCommission {
+        commission: u8,
+    },
+    MinimumSelfStake {
+        lamports: u64,
+    },
+    MaximumInfrastructureConcentration {
+        percentage: f64,
+    },
+    MinimumTestnetParticipation {
+        n: usize,
+        m: usize,
+    },
+    PoorClusterAverageSkipRate,
+    TooManyPoorVoters {
+        epoch: Epoch,
+        percentage: usize,
+        limit: usize,
+    },
+    TooManyOldReleases {
+        limit: usize,
+    },
+    TooManyPoorBlockProducers {
+        epoch: Epoch,
+        limit: usize,
+    },
+    StakeAdjustmentsSkipped,
+    ValidatorsProcessed {
+        count: usize,
+    },
+    ActiveStake {
+        lamports: u64,
+    },
+    StakePoolSize {
+        total: u64,
+        inactive: u64,
+    },
+    StakePoolV0Size {
+        total: u64,
+        available_for_delegation: u64,
+    },
+    BaselineStakeAmount {
+        lamports: u64,
+    },
+    BonusStakeAmount {
+        lamports: u64,
+    },
+    ReserveTarget {
+        lamports: u64,
+    },
+    MaximumStakeChurn {
+        percentage: usize,
+        lamports: u64,
+    },
+    StakeDeferred {
+        lamports: u64,
+        validators: usize,
+    },
+    ValidatorOverride {
+        identity: Pubkey,
+        action: OverrideAction,
+        reason: String,
+    },
+
+    /// Free-form note carried over from an `EpochClassificationV1`
+    Legacy(String),
+}
+
+impl fmt::Display for EpochNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MinimumVoteCredits {
+                epoch,
+                min_epoch_credits,
+                avg_epoch_credits,
+                grace_percentage,
+            } => write!(
+                f,
+                "Minimum vote credits required for epoch {}: {} (cluster average: {}, grace: {}%)",
+                epoch, min_epoch_credits, avg_epoch_credits, grace_percentage,
+            ),
+            Self::MaximumSkipRate {
+                epoch,
+                max_skip_rate,
+                cluster_average_skip_rate,
+                grace_percentage,
+            } => write!(
+                f,
+                "Maximum allowed skip rate for epoch {}: {:.2}% (cluster average: {:.2}%, grace: {}%)",
+                epoch, max_skip_rate, cluster_average_skip_rate, grace_percentage,
+            ),
+            Self::MinimumReleaseVersion { version } => {
+                write!(f, "Solana release {} or greater required", version)
+            }
+            Self::MaximumCommission { commission } => {
+                write!(f, "Maximum commission: {}%", commission)
+            }
+            Self::MinimumSelfStake { lamports } => {
+                write!(f, "Minimum required self stake: {}", Sol(*lamports))
+            }
+            Self::MaximumInfrastructureConcentration { percentage } => {
+                write!(f, "Maximum infrastructure concentration: {:0}%", percentage)
+            }
+            Self::MinimumTestnetParticipation { n, m } => write!(
+                f,
+                "Participants must maintain Baseline or Bonus stake level for {} of the last {} Testnet epochs",
+                n, m
+            ),
+            Self::PoorClusterAverageSkipRate => write!(f, "Cluster average skip rate is poor"),
+            Self::TooManyPoorVoters {
+                epoch,
+                percentage,
+                limit,
+            } => write!(
+                f,
+                "Too many validators classified as poor voters for epoch {}: {}% (limit: {}%)",
+                epoch, percentage, limit
+            ),
+            Self::TooManyOldReleases { limit } => write!(
+                f,
+                "Over {}% of validators classified as running an older release",
+                limit
+            ),
+            Self::TooManyPoorBlockProducers { epoch, limit } => write!(
+                f,
+                "Over {}% of validators classified as poor block producers in epoch {}",
+                limit, epoch
+            ),
+            Self::StakeAdjustmentsSkipped => write!(f, "Stake adjustments skipped this epoch"),
+            Self::ValidatorsProcessed { count } => write!(f, "{} validators processed", count),
+            Self::ActiveStake { lamports } => write!(f, "Active stake: {}", Sol(*lamports)),
+            Self::StakePoolSize { total, inactive } => write!(
+                f,
+                "Stake pool size: {} (inactive: {})",
+                Sol(*total),
+                Sol(*inactive)
+            ),
+            Self::StakePoolV0Size {
+                total,
+                available_for_delegation,
+            } => write!(
+                f,
+                "Stake pool size: {} (available for delegation: {})",
+                Sol(*total),
+                Sol(*available_for_delegation)
+            ),
+            Self::BaselineStakeAmount { lamports } => {
+                write!(f, "Baseline stake amount: {}", Sol(*lamports))
+            }
+            Self::BonusStakeAmount { lamports } => {
+                write!(f, "Bonus stake amount: {}", Sol(*lamports))
+            }
+            Self::ReserveTarget { lamports } => {
+                write!(f, "Stake pool reserve target: {}", Sol(*lamports))
+            }
+            Self::MaximumStakeChurn {
+                percentage,
+                lamports,
+            } => write!(
+                f,
+                "Maximum stake moved this epoch: {} ({}% of the pool)",
+                Sol(*lamports),
+                percentage
+            ),
+            Self::StakeDeferred {
+                lamports,
+                validators,
+            } => write!(
+                f,
+                "{} of stake adjustments for {} validators deferred to a later epoch",
+                Sol(*lamports),
+                validators
+            ),
+            Self::ValidatorOverride {
+                identity,
+                action,
+                reason,
+            } => write!(
+                f,
+                "Classification of {} overridden ({}): {}",
+                identity, action, reason
+            ),
+            Self::Legacy(note) => write!(f, "{}", note),
+        }
+    }
+}
+
+/// Change made to a validator's stake account during an epoch
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum StakeOperation {
+    Add {
+        amount: u64,
+    },
+    AddTooSmall {
+        amount: u64,
+    },
+    Remove {
+        amount: u64,
+    },
+    RemoveTooSmall {
+        amount: u64,
+    },
+    /// Only `amount` of the adjustment fits in this epoch, the rest is deferred
+    AddPartial {
+        amount: u64,
+        deferred: u64,
+    },
+    RemovePartial {
+        amount: u64,
+        deferred: u64,
+    },
+    /// None of the adjustment fits in this epoch
+    AddDeferred {
+        amount: u64,
+    },
+    RemoveDeferred {
+        amount: u64,
+    },
+    /// No longer produced, `AddDeferred` is recorded instead
+    ReserveDepleted,
+    NoChange,
+}
+
+impl StakeOperation {
+    /// Amount of the adjustment deferred to a later epoch
+    pub fn deferred(&self) -> u64 {
+        match self {
+            Self::AddPartial { deferred, .. } | Self::RemovePartial { deferred, .. } => *deferred,
+            Self::AddDeferred { amount } | Self::RemoveDeferred { amount } => *amount,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for StakeOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Add { amount } => write!(f, "adding {}", Sol(*amount)),
+            Self::AddTooSmall { amount } => {
+                write!(f, "not adding {} (amount too small)", Sol(*amount))
+            }
+            Self::Remove { amount } => write!(f, "removing {}", Sol(*amount)),
+            Self::RemoveTooSmall { amount } => {
+                write!(f, "not removing {} (amount too small)", Sol(*amount))
+            }
+            Self::AddPartial { amount, deferred } => write!(
+                f,
+                "adding {}, deferring {} to a later epoch",
+                Sol(*amount),
+                Sol(*deferred)
+            ),
+            Self::RemovePartial { amount, deferred } => write!(
+                f,
+                "removing {}, deferring {} to a later epoch",
+                Sol(*amount),
+                Sol(*deferred)
+            ),
+            Self::AddDeferred { amount } => {
+                write!(f, "deferring adding {} to a later epoch", Sol(*amount))
+            }
+            Self::RemoveDeferred { amount } => {
+                write!(f, "deferring removing {} to a later epoch", Sol(*amount))
+            }
+            Self::ReserveDepleted => write!(f, "reserve depleted"),
+            Self::NoChange => write!(f, "no change"),
+        }
+    }
+}
+
+/// Summary of the action taken this epoch to advance a validator's stake
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum StakeAction {
+    Adjust {
+        target: u64,
+        current: u64,
+        operation: StakeOperation,
+    },
+    BusyTransientStakeAccount {
+        address: Pubkey,
+    },
+    BusyTransientStakeActivation {
+        address: Pubkey,
+    },
+    BusyTransientStakeDeactivation {
+        address: Pubkey,
+    },
+    BusyCreditsObservedMismatch {
+        transient_address: Pubkey,
+    },
+    BusyStakeActivation {
+        address: Pubkey,
+    },
+    BusyStakeDeactivation {
+        address: Pubkey,
+    },
+    BusyInactiveStake {
+        address: Pubkey,
+    },
+    BusyNoStakeAccount {
+        address: Pubkey,
+    },
+    CreatingStakeAccount {
+        address: Pubkey,
+    },
+    InsufficientReserveForStakeAccount {
+        address: Pubkey,
+    },
+
+    /// Free-form action carried over from an `EpochClassificationV1`
+    Legacy(String),
+}
+
+impl fmt::Display for StakeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Adjust {
+                target,
+                current,
+                operation,
+            } => write!(
+                f,
+                "target stake amount: {}, current stake amount: {} - {}",
+                Sol(*target),
+                Sol(*current),
+                operation
+            ),
+            Self::BusyTransientStakeAccount { address } => {
+                write!(f, "busy due to transient stake account {}", address)
+            }
+            Self::BusyTransientStakeActivation { address } => write!(
+                f,
+                "stake account busy due to transient stake activation: {}",
+                address
+            ),
+            Self::BusyTransientStakeDeactivation { address } => write!(
+                f,
+                "stake account busy due to transient stake deactivation: {}",
+                address
+            ),
+            Self::BusyCreditsObservedMismatch { transient_address } => write!(
+                f,
+                "stake account busy due to credits observed mismatch with transient stake account {}",
+                transient_address
+            ),
+            Self::BusyStakeActivation { address } => write!(
+                f,
+                "stake account busy due to stake activation of {}",
+                address
+            ),
+            Self::BusyStakeDeactivation { address } => write!(
+                f,
+                "stake account busy due to stake deactivation of {}",
+                address
+            ),
+            Self::BusyInactiveStake { address } => {
+                write!(f, "stake account busy due to inactive stake {}", address)
+            }
+            Self::BusyNoStakeAccount { address } => write!(
+                f,
+                "stake account busy due to no stake account: {}",
+                address
+            ),
+            Self::CreatingStakeAccount { address } => {
+                write!(f, "creating new stake account {}", address)
+            }
+            Self::InsufficientReserveForStakeAccount { address } => write!(
+                f,
+                "insufficient funds in reserve account to create stake account {}",
+                address
+            ),
+            Self::Legacy(action) => write!(f, "{}", action),
+        }
+    }
+}
+
+/// Outcome of the transaction carrying out a `StakeAction`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum StakeActionStatus {
+    /// The action did not require a transaction
+    NoTransaction,
+    /// Planned by a dry run, rejected by the RPC node, or expired before being confirmed
+    NotSent,
+    Confirmed,
+    Failed(String),
+}
+
+impl Default for StakeActionStatus {
+    fn default() -> Self {
+        Self::NoTransaction
+    }
+}
+
+/// A `StakeAction`, linked to the transaction carrying it out
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(from = "ValidatorStakeActionRepr")]
+pub struct ValidatorStakeAction {
+    pub action: StakeAction,
+    pub signature: Option<String>,
+    pub status: StakeActionStatus,
+}
+
+impl ValidatorStakeAction {
+    /// True if the action required a transaction that did not succeed
+    pub fn unsuccessful(&self) -> bool {
+        matches!(
+            self.status,
+            StakeActionStatus::NotSent | StakeActionStatus::Failed(_)
+        )
+    }
+}
+
+impl From<StakeAction> for ValidatorStakeAction {
+    fn from(action: StakeAction) -> Self {
+        Self {
+            action,
+            signature: None,
+            status: StakeActionStatus::NoTransaction,
+        }
+    }
+}
+
+// Classifications saved before stake actions were linked to their transaction hold a bare
+// `StakeAction`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ValidatorStakeActionRepr {
+    Linked {
+        action: StakeAction,
+        #[serde(default)]
+        signature: Option<String>,
+        #[serde(default)]
+        status: StakeActionStatus,
+    },
+    Unlinked(StakeAction),
+}
+
+impl From<ValidatorStakeActionRepr> for ValidatorStakeAction {
+    fn from(repr: ValidatorStakeActionRepr) -> Self {
+        match repr {
+            ValidatorStakeActionRepr::Linked {
+                action,
+                signature,
+                status,
+            } => Self {
+                action,
+                signature,
+                status,
+            },
+            ValidatorStakeActionRepr::Unlinked(action) => action.into(),
+        }
+    }
+}
+
+impl fmt::Display for ValidatorStakeAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            StakeActionStatus::NoTransaction => write!(f, "{}", self.action),
+            StakeActionStatus::NotSent => write!(f, "{} (not sent)", self.action),
+            StakeActionStatus::Confirmed => write!(f, "{} (confirmed)", self.action),
+            StakeActionStatus::Failed(err) => write!(f, "{} (failed: {})", self.action, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, solana_sdk::native_token::sol_to_lamports};
+
+    #[test]
+    fn test_render_matches_legacy_text() {
+        assert_eq!(
+            StakeStateReason::InsufficientVoteCredits {
+                earned: 100,
+                required: 200,
+                epoch: 42,
+            }
+            .to_string(),
+            "insufficient vote credits: 100 credits earned in epoch 42"
+        );
+        assert_eq!(
+            StakeStateReason::GoodBlockProduction {
+                epoch: 42,
+                block_production: BlockProductionStats {
+                    blocks: 9,
+                    slots: 10,
+                    skip_rate: 10,
+                },
+            }
+            .to_string(),
+            "good block production during epoch 42: 9 blocks in 10 slots, 10% skip rate"
+        );
+        assert_eq!(
+            ValidatorNote::InfrastructureConcentrationTooHigh {
+                concentration: 12.34
+            }
+            .to_string(),
+            "infrastructure concentration 12.3% is too high; consider finding a new data center"
+        );
+        assert_eq!(
+            EpochNote::TooManyPoorBlockProducers {
+                epoch: 42,
+                limit: 20
+            }
+            .to_string(),
+            "Over 20% of validators classified as poor block producers in epoch 42"
+        );
+        assert_eq!(
+            StakeAction::Adjust {
+                target: sol_to_lamports(10.),
+                current: sol_to_lamports(5.),
+                operation: StakeOperation::Add {
+                    amount: sol_to_lamports(5.)
+                },
+            }
+            .to_string(),
+            format!(
+                "target stake amount: {}, current stake amount: {} - adding {}",
+                Sol(sol_to_lamports(10.)),
+                Sol(sol_to_lamports(5.)),
+                Sol(sol_to_lamports(5.))
+            )
+        );
+    }
+
+    #[test]
+    fn test_serialization_roundtrip() {
+        let reason = StakeStateReason::InsufficientVoteCredits {
+            earned: 100,
+            required: 200,
+            epoch: 42,
+        };
+        let serialized = serde_yaml::to_string(&reason).unwrap();
+        assert!(serialized.contains("InsufficientVoteCredits"));
+        assert_eq!(
+            serde_yaml::from_str::<StakeStateReason>(&serialized).unwrap(),
+            reason
+        );
+    }
+
+    #[test]
+    fn test_validator_stake_action_serialization() {
+        let action = StakeAction::Adjust {
+            target: 10,
+            current: 5,
+            operation: StakeOperation::Add { amount: 5 },
+        };
+        let validator_stake_action = ValidatorStakeAction {
+            action: action.clone(),
+            signature: Some("signature".to_string()),
+            status: StakeActionStatus::Failed("custom program error: 0x1".to_string()),
+        };
+        assert_eq!(
+            serde_yaml::from_str::<ValidatorStakeAction>(
+                &serde_yaml::to_string(&validator_stake_action).unwrap()
+            )
+            .unwrap(),
+            validator_stake_action
+        );
+
+        // A bare `StakeAction` saved by an earlier version is still readable
+        assert_eq!(
+            serde_yaml::from_str::<ValidatorStakeAction>(&serde_yaml::to_string(&action).unwrap())
+                .unwrap(),
+            ValidatorStakeAction::from(action)
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use {
+    crate::generic_stake_pool::ValidatorStakeState,
+    serde::{de, Deserialize, Deserializer, Serialize},
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    std::{error, fmt, fs::File, path::Path, str::FromStr},
+};
+
+/// Classification rule that a validator can be exempted from
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideRule {
+    Commission,
+    SelfStake,
+    InfrastructureConcentration,
+    TestnetParticipation,
+    VoteCredits,
+    ReleaseVersion,
+}
+
+impl fmt::Display for OverrideRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Commission => write!(f, "commission"),
+            Self::SelfStake => write!(f, "self stake"),
+            Self::InfrastructureConcentration => write!(f, "infrastructure concentration"),
+            Self::TestnetParticipation => write!(f, "testnet participation"),
+            Self::VoteCredits => write!(f, "vote credits"),
+            Self::ReleaseVersion => write!(f, "release version"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverrideAction {
+    ForceNone,
+    ForceBaseline,
+    ExemptFrom(Vec<OverrideRule>),
+}
+
+impl fmt::Display for OverrideAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ForceNone => write!(f, "no stake"),
+            Self::ForceBaseline => write!(f, "baseline stake"),
+            Self::ExemptFrom(rules) => write!(
+                f,
+                "exempt from {}",
+                rules
+                    .iter()
+                    .map(|rule| rule.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Manual override of the classification of a validator, applied after the automatic rules
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ValidatorOverride {
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub identity: Pubkey,
+    pub action: OverrideAction,
+    // The override no longer applies after this epoch
+    pub expires_epoch: Option<Epoch>,
+    pub reason: String,
+}
+
+impl ValidatorOverride {
+    pub fn is_active(&self, epoch: Epoch) -> bool {
+        self.expires_epoch
+            .map(|expires_epoch| epoch <= expires_epoch)
+            .unwrap_or(true)
+    }
+
+    pub fn exempts(&self, rule: OverrideRule) -> bool {
+        match &self.action {
+            OverrideAction::ExemptFrom(rules) => rules.contains(&rule),
+            _ => false,
+        }
+    }
+}
+
+/// Overrides of `identity` that are active in `epoch`, in file order
+pub fn active_overrides<'a>(
+    overrides: &'a [ValidatorOverride],
+    identity: &Pubkey,
+    epoch: Epoch,
+) -> Vec<&'a ValidatorOverride> {
+    overrides
+        .iter()
+        .filter(|validator_override| {
+            validator_override.identity == *identity && validator_override.is_active(epoch)
+        })
+        .collect()
+}
+
+/// Stake state forced by `overrides`, and the override that forces it. A `ForceNone` override
+/// takes precedence over a `ForceBaseline` one, and `ExemptFrom` overrides force nothing
+pub fn forced_stake_state<'a>(
+    overrides: &[&'a ValidatorOverride],
+) -> Option<(ValidatorStakeState, &'a ValidatorOverride)> {
+    let forcing = |action: OverrideAction| {
+        overrides
+            .iter()
+            .find(|validator_override| validator_override.action == action)
+            .copied()
+    };
+    forcing(OverrideAction::ForceNone)
+        .map(|validator_override| (ValidatorStakeState::None, validator_override))
+        .or_else(|| {
+            forcing(OverrideAction::ForceBaseline)
+                .map(|validator_override| (ValidatorStakeState::Baseline, validator_override))
+        })
+}
+
+fn deserialize_pubkey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let pubkey = String::deserialize(deserializer)?;
+    Pubkey::from_str(&pubkey).map_err(|err| de::Error::custom(format!("{}: {}", pubkey, err)))
+}
+
+pub fn read_overrides(path: &Path) -> Result<Vec<ValidatorOverride>, Box<dyn error::Error>> {
+    let file =
+        File::open(path).map_err(|err| format!("Unable to open {}: {}", path.display(), err))?;
+    serde_yaml::from_reader(file)
+        .map_err(|err| format!("Invalid overrides file {}: {}", path.display(), err).into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_overrides() {
+        let identity = Pubkey::new_unique();
+        let overrides: Vec<ValidatorOverride> = serde_yaml::from_str(&format!(
+            "
+- identity: {identity}
+  action: force_none
+  reason: equivocation
+- identity: {identity}
+  action:
+    exempt_from: [commission, self_stake]
+  expires_epoch: 200
+  reason: commission change announced in advance
+",
+            identity = identity
+        ))
+        .unwrap();
+
+        assert_eq!(
+            overrides,
+            vec![
+                ValidatorOverride {
+                    identity,
+                    action: OverrideAction::ForceNone,
+                    expires_epoch: None,
+                    reason: "equivocation".to_string(),
+                },
+                ValidatorOverride {
+                    identity,
+                    action: OverrideAction::ExemptFrom(vec![
+                        OverrideRule::Commission,
+                        OverrideRule::SelfStake
+                    ]),
+                    expires_epoch: Some(200),
+                    reason: "commission change announced in advance".to_string(),
+                },
+            ]
+        );
+
+        assert!(overrides[0].is_active(1_000));
+        assert!(!overrides[0].exempts(OverrideRule::Commission));
+        assert!(overrides[1].is_active(200));
+        assert!(!overrides[1].is_active(201));
+        assert!(overrides[1].exempts(OverrideRule::SelfStake));
+        assert!(!overrides[1].exempts(OverrideRule::VoteCredits));
+
+        // Both overrides apply until the exemption expires, and the forced stake state wins
+        let active = active_overrides(&overrides, &identity, 200);
+        assert_eq!(active.len(), 2);
+        assert!(active
+            .iter()
+            .any(|validator_override| validator_override.exempts(OverrideRule::Commission)));
+        assert_eq!(
+            forced_stake_state(&active),
+            Some((ValidatorStakeState::None, &overrides[0]))
+        );
+        assert_eq!(active_overrides(&overrides, &identity, 201).len(), 1);
+        assert_eq!(
+            forced_stake_state(&active_overrides(&overrides[1..], &identity, 200)),
+            None
+        );
+        assert!(active_overrides(&overrides, &Pubkey::new_unique(), 200).is_empty());
+
+        assert!(serde_yaml::from_str::<Vec<ValidatorOverride>>(
+            "- {identity: not-a-pubkey, action: force_none, reason: typo}"
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use {
+    crate::{
+        generic_stake_pool::ValidatorStakeActions,
+        notes::{EpochNote, StakeAction, StakeOperation},
+    },
+    std::cmp::Reverse,
+};
+
+/// Stake that may be moved in a single epoch
+#[derive(Debug, Clone, Copy)]
+pub struct RebalanceBudget {
+    /// Stake available for delegation in the reserve
+    pub reserve: u64,
+    /// Total stake that may be added or removed, across all validators
+    pub churn: u64,
+}
+
+/// Maximum stake moved in an epoch, as a percentage of the pool size
+pub fn max_stake_churn(total_stake_amount: u64, max_churn_percentage: usize) -> u64 {
+    (total_stake_amount as u128 * max_churn_percentage as u128 / 100) as u64
+}
+
+/// Plan the operation moving the stake of each validator, given as `(current, target)`, towards
+/// its target. Returns one operation per validator, in the same order.
+///
+/// Only the difference between the current and target stake is ever moved, and differences
+/// smaller than `min_stake_change` are left alone. Removals are planned first, then additions
+/// funded by the reserve, each prioritizing the validators furthest from their target. Whatever
+/// does not fit within `budget` is recorded as deferred to a later epoch
+pub fn plan_rebalance(
+    stakes: &[(u64, u64)],
+    min_stake_change: u64,
+    mut budget: RebalanceBudget,
+) -> Vec<StakeOperation> {
+    let mut operations = vec![StakeOperation::NoChange; stakes.len()];
+    let mut removals = vec![];
+    let mut additions = vec![];
+
+    for (i, (current, target)) in stakes.iter().enumerate() {
+        if current > target {
+            let amount = current - target;
+            if amount < min_stake_change {
+                operations[i] = StakeOperation::RemoveTooSmall { amount };
+            } else {
+                removals.push((i, amount));
+            }
+        } else if current < target {
+            let amount = target - current;
+            if amount < min_stake_change {
+                operations[i] = StakeOperation::AddTooSmall { amount };
+            } else {
+                additions.push((i, amount));
+            }
+        }
+    }
+
+    removals.sort_by_key(|(_, amount)| Reverse(*amount));
+    for (i, amount) in removals {
+        let allowed = amount.min(budget.churn);
+        operations[i] = if allowed == amount {
+            StakeOperation::Remove { amount }
+        } else if allowed < min_stake_change {
+            StakeOperation::RemoveDeferred { amount }
+        } else {
+            StakeOperation::RemovePartial {
+                amount: allowed,
+                deferred: amount - allowed,
+            }
+        };
+        budget.churn -= amount - operations[i].deferred();
+    }
+
+    additions.sort_by_key(|(_, amount)| Reverse(*amount));
+    for (i, amount) in additions {
+        let allowed = amount.min(budget.churn).min(budget.reserve);
+        operations[i] = if allowed == amount {
+            StakeOperation::Add { amount }
+        } else if allowed < min_stake_change {
+            StakeOperation::AddDeferred { amount }
+        } else {
+            StakeOperation::AddPartial {
+                amount: allowed,
+                deferred: amount - allowed,
+            }
+        };
+        let added = amount - operations[i].deferred();
+        budget.churn -= added;
+        budget.reserve -= added;
+    }
+
+    operations
+}
+
+/// Summarize the stake adjustments that were deferred to a later epoch
+pub fn deferred_stake_note(validator_stake_actions: &ValidatorStakeActions) -> Option<EpochNote> {
+    let deferred = validator_stake_actions
+        .values()
+        .filter_map(
+            |validator_stake_action| match &validator_stake_action.action {
+                StakeAction::Adjust { operation, .. } if operation.deferred() > 0 => {
+                    Some(operation.deferred())
+                }
+                _ => None,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    if deferred.is_empty() {
+        None
+    } else {
+        Some(EpochNote::StakeDeferred {
+            lamports: deferred.iter().sum(),
+            validators: deferred.len(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const UNLIMITED: u64 = u64::MAX;
+
+    #[test]
+    fn test_plan_rebalance_unlimited() {
+        let operations = plan_rebalance(
+            &[(10, 10), (10, 30), (30, 10), (10, 11), (11, 10)],
+            5,
+            RebalanceBudget {
+                reserve: UNLIMITED,
+                churn: UNLIMITED,
+            },
+        );
+        assert_eq!(
+            operations,
+            vec![
+                StakeOperation::NoChange,
+                StakeOperation::Add { amount: 20 },
+                StakeOperation::Remove { amount: 20 },
+                StakeOperation::AddTooSmall { amount: 1 },
+                StakeOperation::RemoveTooSmall { amount: 1 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_rebalance_reserve_limited() {
+        // The validator furthest below its target is funded first
+        let operations = plan_rebalance(
+            &[(20, 30), (0, 30), (25, 30)],
+            5,
+            RebalanceBudget {
+                reserve: 40,
+                churn: UNLIMITED,
+            },
+        );
+        assert_eq!(
+            operations,
+            vec![
+                StakeOperation::Add { amount: 10 },
+                StakeOperation::Add { amount: 30 },
+                StakeOperation::AddDeferred { amount: 5 },
+            ]
+        );
+
+        // Whatever the reserve cannot cover is deferred
+        let operations = plan_rebalance(
+            &[(20, 30), (0, 30)],
+            5,
+            RebalanceBudget {
+                reserve: 36,
+                churn: UNLIMITED,
+            },
+        );
+        assert_eq!(
+            operations,
+            vec![
+                StakeOperation::AddPartial {
+                    amount: 6,
+                    deferred: 4,
+                },
+                StakeOperation::Add { amount: 30 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_rebalance_churn_limited() {
+        // Removals are planned before additions, and share the same churn limit
+        let operations = plan_rebalance(
+            &[(0, 30), (50, 10), (30, 20)],
+            5,
+            RebalanceBudget {
+                reserve: UNLIMITED,
+                churn: 48,
+            },
+        );
+        assert_eq!(
+            operations,
+            vec![
+                StakeOperation::AddDeferred { amount: 30 },
+                StakeOperation::Remove { amount: 40 },
+                StakeOperation::RemovePartial {
+                    amount: 8,
+                    deferred: 2,
+                },
+            ]
+        );
+        assert_eq!(
+            operations
+                .iter()
+                .map(|operation| operation.deferred())
+                .sum::<u64>(),
+            32
+        );
+    }
+
+    #[test]
+    fn test_max_stake_churn() {
+        assert_eq!(max_stake_churn(1_000, 10), 100);
+        assert_eq!(max_stake_churn(u64::MAX, 100), u64::MAX);
+        assert_eq!(max_stake_churn(u64::MAX, 0), 0);
+    }
+}
@@ -1,28 +1,38 @@
 use {
     log::*,
     reqwest::StatusCode,
+    serde::{Deserialize, Serialize},
     solana_client::{
-        client_error,
+        client_error, nonce_utils,
         rpc_client::RpcClient,
         rpc_config::RpcSimulateTransactionConfig,
         rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
         rpc_filter,
-        rpc_request::MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS,
-        rpc_response::{RpcVoteAccountInfo, RpcVoteAccountStatus},
+        rpc_request::{RpcError, RpcResponseErrorData, MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS},
+        rpc_response::{
+            RpcSimulateTransactionResult, RpcStakeActivation, RpcVoteAccountInfo,
+            RpcVoteAccountStatus,
+        },
     },
     solana_sdk::{
         clock::Epoch,
+        hash::Hash,
+        instruction::{AccountMeta, Instruction},
+        message::Message,
         native_token::*,
+        packet::PACKET_DATA_SIZE,
         pubkey::Pubkey,
-        signature::{Keypair, Signature, Signer},
-        transaction::Transaction,
+        signature::{Signature, Signer},
+        transaction::{
+            get_nonce_pubkey_from_instruction, uses_durable_nonce, Transaction, TransactionError,
+        },
     },
     std::{
         collections::{HashMap, HashSet},
         error,
         str::FromStr,
         thread::sleep,
-        time::Duration,
+        time::{Duration, Instant},
     },
 };
 
@@ -53,20 +63,242 @@ where
     }
 }
 
-/// Simulate a list of transactions and filter out the ones that will fail
-#[allow(dead_code)]
+/// Maximum number of times a transaction is signed with a fresh blockhash and sent
+const MAX_SEND_ATTEMPTS: usize = 5;
+
+/// Rate limit for sending transactions to the RPC node
+const MAX_TRANSACTIONS_PER_SECOND: u64 = 50;
+
+/// Unconfirmed transactions are re-broadcast at this interval until their blockhash expires
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A transaction along with a description of what it does and the identities of the validators
+/// it concerns
+#[derive(Debug, Clone)]
+pub struct DescribedTransaction {
+    pub transaction: Transaction,
+    pub memo: String,
+    pub identities: Vec<Pubkey>,
+}
+
+// Signatures are allocated in an unsigned transaction, so its size is final
+fn fits_in_packet(transaction: &Transaction) -> bool {
+    bincode::serialized_size(transaction)
+        .map(|size| size <= PACKET_DATA_SIZE as u64)
+        .unwrap_or(false)
+}
+
+// Recover the instructions of a compiled message
+fn message_instructions(message: &Message) -> Vec<Instruction> {
+    message
+        .instructions
+        .iter()
+        .map(|instruction| Instruction {
+            program_id: message.account_keys[instruction.program_id_index as usize],
+            accounts: instruction
+                .accounts
+                .iter()
+                .map(|i| {
+                    let i = *i as usize;
+                    AccountMeta {
+                        pubkey: message.account_keys[i],
+                        is_signer: message.is_signer(i),
+                        is_writable: message.is_writable(i),
+                    }
+                })
+                .collect(),
+            data: instruction.data.clone(),
+        })
+        .collect()
+}
+
+// Combine the instructions of `transactions` into a single transaction paid for by the payer of
+// the first one. Returns `None` if the combined transaction, along with an instruction to advance
+// a durable nonce, does not fit in a packet
+fn merge_transactions(transactions: &[DescribedTransaction]) -> Option<DescribedTransaction> {
+    let payer = transactions.first()?.transaction.message.account_keys[0];
+    let instructions = transactions
+        .iter()
+        .flat_map(|transaction| message_instructions(&transaction.transaction.message))
+        .collect::<Vec<_>>();
+    let transaction = Transaction::new_with_payer(&instructions, Some(&payer));
+
+    // Planned transactions are rebuilt on a durable nonce, so room is left for one
+    let nonce_transaction = Transaction::new_unsigned(Message::new_with_nonce(
+        instructions,
+        Some(&payer),
+        &Pubkey::default(),
+        &payer,
+    ));
+    if !fits_in_packet(&nonce_transaction) {
+        return None;
+    }
+
+    Some(DescribedTransaction {
+        transaction,
+        memo: transactions
+            .iter()
+            .map(|transaction| transaction.memo.as_str())
+            .collect::<Vec<_>>()
+            .join("; "),
+        identities: transactions
+            .iter()
+            .flat_map(|transaction| transaction.identities.iter().cloned())
+            .collect(),
+    })
+}
+
+/// Rebuild an unsigned transaction to advance `nonce_account` and use its `nonce` as the
+/// blockhash, so that it can be signed offline and submitted at any later time. The payer must be
+/// the nonce authority. Returns `None` if the rebuilt transaction does not fit in a packet
+pub fn use_durable_nonce(
+    transaction: &Transaction,
+    nonce_account: &Pubkey,
+    nonce: Hash,
+) -> Option<Transaction> {
+    let payer = transaction.message.account_keys[0];
+    let mut transaction = Transaction::new_unsigned(Message::new_with_nonce(
+        message_instructions(&transaction.message),
+        Some(&payer),
+        nonce_account,
+        &payer,
+    ));
+    transaction.message.recent_blockhash = nonce;
+    if fits_in_packet(&transaction) {
+        Some(transaction)
+    } else {
+        None
+    }
+}
+
+/// Get the nonce held by a durable nonce account and the authority allowed to advance it
+pub fn get_durable_nonce(
+    rpc_client: &RpcClient,
+    nonce_account: &Pubkey,
+) -> Result<(Hash, Pubkey), Box<dyn error::Error>> {
+    let account = nonce_utils::get_account_with_commitment(
+        rpc_client,
+        nonce_account,
+        rpc_client.commitment(),
+    )
+    .map_err(|err| format!("Invalid nonce account {}: {}", nonce_account, err))?;
+    let data = nonce_utils::data_from_account(&account)
+        .map_err(|err| format!("Invalid nonce account {}: {}", nonce_account, err))?;
+    Ok((data.blockhash, data.authority))
+}
+
+// Whether `transaction` uses a durable nonce that has not been advanced yet, in which case it may
+// still be confirmed although its blockhash is not recent
+fn has_unused_durable_nonce(
+    rpc_client: &RpcClient,
+    transaction: &Transaction,
+) -> Result<bool, Box<dyn error::Error>> {
+    let nonce_account = match uses_durable_nonce(transaction)
+        .and_then(|instruction| get_nonce_pubkey_from_instruction(instruction, transaction))
+    {
+        Some(nonce_account) => *nonce_account,
+        None => return Ok(false),
+    };
+    let (nonce, _) = get_durable_nonce(rpc_client, &nonce_account)?;
+    Ok(nonce == transaction.message.recent_blockhash)
+}
+
+/// Pack independent transactions into as few transactions as fit within the packet size limit.
+///
+/// Each packed transaction is simulated, and one that fails is split in halves until the failing
+/// transactions are isolated, so that a failure is only attributed to the validators it concerns
+pub fn pack_transactions(
+    rpc_client: &RpcClient,
+    transactions: Vec<DescribedTransaction>,
+) -> client_error::Result<Vec<DescribedTransaction>> {
+    let num_transactions = transactions.len();
+
+    let mut batches: Vec<Vec<DescribedTransaction>> = vec![];
+    for mut transaction in transactions {
+        if let Some(batch) = batches.last_mut() {
+            let same_payer = batch[0].transaction.message.account_keys[0]
+                == transaction.transaction.message.account_keys[0];
+            if same_payer {
+                batch.push(transaction);
+                if merge_transactions(batch).is_some() {
+                    continue;
+                }
+                transaction = batch.pop().unwrap();
+            }
+        }
+        batches.push(vec![transaction]);
+    }
+
+    let mut packed_transactions = vec![];
+    for batch in batches {
+        pack_batch(rpc_client, batch, &mut packed_transactions)?;
+    }
+    info!(
+        "Packed {} transactions into {}",
+        num_transactions,
+        packed_transactions.len()
+    );
+    Ok(packed_transactions)
+}
+
+fn pack_batch(
+    rpc_client: &RpcClient,
+    mut batch: Vec<DescribedTransaction>,
+    packed_transactions: &mut Vec<DescribedTransaction>,
+) -> client_error::Result<()> {
+    if batch.len() == 1 {
+        packed_transactions.push(batch.pop().unwrap());
+        return Ok(());
+    }
+
+    let mut packed_transaction =
+        merge_transactions(&batch).expect("batch to fit in a single transaction");
+    packed_transaction.transaction.message.recent_blockhash =
+        retry_rpc_operation(10, || rpc_client.get_recent_blockhash())?.0;
+    let sim_result = rpc_client.simulate_transaction_with_config(
+        &packed_transaction.transaction,
+        RpcSimulateTransactionConfig {
+            sig_verify: false,
+            ..RpcSimulateTransactionConfig::default()
+        },
+    )?;
+
+    match sim_result.value.err {
+        None => packed_transactions.push(packed_transaction),
+        Some(err) => {
+            debug!(
+                "Splitting {} packed transactions that failed simulation: {}",
+                batch.len(),
+                err
+            );
+            let second_half = batch.split_off(batch.len() / 2);
+            pack_batch(rpc_client, batch, packed_transactions)?;
+            pack_batch(rpc_client, second_half, packed_transactions)?;
+        }
+    }
+    Ok(())
+}
+
+pub struct SimulatedTransaction {
+    pub transaction: DescribedTransaction,
+    pub err: Option<TransactionError>,
+    pub logs: Vec<String>,
+}
+
+/// Simulate a list of transactions, returning the outcome of each one
 pub fn simulate_transactions(
     rpc_client: &RpcClient,
-    candidate_transactions: Vec<(Transaction, String)>,
-) -> client_error::Result<Vec<(Transaction, String)>> {
+    candidate_transactions: Vec<DescribedTransaction>,
+) -> client_error::Result<Vec<SimulatedTransaction>> {
     info!("Simulating {} transactions", candidate_transactions.len());
     let mut simulated_transactions = vec![];
-    for (mut transaction, memo) in candidate_transactions {
+    for mut candidate_transaction in candidate_transactions {
+        let transaction = &mut candidate_transaction.transaction;
         transaction.message.recent_blockhash =
             retry_rpc_operation(10, || rpc_client.get_recent_blockhash())?.0;
 
         let sim_result = rpc_client.simulate_transaction_with_config(
-            &transaction,
+            transaction,
             RpcSimulateTransactionConfig {
                 sig_verify: false,
                 ..RpcSimulateTransactionConfig::default()
@@ -75,135 +307,334 @@ pub fn simulate_transactions(
 
         if sim_result.value.err.is_some() {
             warn!(
-                "filtering out transaction due to simulation failure: {:?}: {}",
-                sim_result, memo
+                "transaction simulation failure: {:?}: {}",
+                sim_result, candidate_transaction.memo
             );
-        } else {
-            simulated_transactions.push((transaction, memo))
         }
+        simulated_transactions.push(SimulatedTransaction {
+            transaction: candidate_transaction,
+            err: sim_result.value.err,
+            logs: sim_result.value.logs.unwrap_or_default(),
+        });
     }
     info!(
-        "Successfully simulating {} transactions",
+        "Successfully simulated {} of {} transactions",
+        simulated_transactions
+            .iter()
+            .filter(|simulated_transaction| simulated_transaction.err.is_none())
+            .count(),
         simulated_transactions.len()
     );
     Ok(simulated_transactions)
 }
 
-pub struct SendAndConfirmTransactionResult {
-    pub succeeded: HashSet<Signature>,
-    pub failed: HashSet<Signature>,
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum TransactionStatus {
+    /// Confirmed without error
+    Succeeded,
+    /// Confirmed, but the transaction returned an error
+    Failed(String),
+    /// Not accepted by the RPC node, so not forwarded to the leader
+    Rejected(String),
+    /// Failed the preflight simulation of the RPC node, and would fail the same way if sent again
+    PreflightFailed(String),
+    /// Not confirmed before the blockhash expired
+    Expired,
+}
+
+/// Outcome of the last attempt at sending a transaction
+#[derive(Debug, Clone)]
+pub struct TransactionReport {
+    pub memo: String,
+    pub identities: Vec<Pubkey>,
+    pub signature: Signature,
+    pub attempts: usize,
+    pub status: TransactionStatus,
+}
+
+impl TransactionReport {
+    pub fn succeeded(&self) -> bool {
+        self.status == TransactionStatus::Succeeded
+    }
+}
+
+fn log_transaction_reports(reports: &[TransactionReport]) {
+    for report in reports {
+        let identity = if report.identities.is_empty() {
+            String::default()
+        } else {
+            format!(
+                " ({})",
+                report
+                    .identities
+                    .iter()
+                    .map(|identity| identity.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        if report.succeeded() {
+            info!(
+                "{}{}: {} succeeded after {} attempts",
+                report.memo, identity, report.signature, report.attempts
+            );
+        } else {
+            error!(
+                "{}{}: {} {:?} after {} attempts",
+                report.memo, identity, report.signature, report.status, report.attempts
+            );
+        }
+    }
 }
 
+/// Sign and send transactions, re-signing them with a fresh blockhash and sending them again
+/// while they are rejected or expire, for up to `MAX_SEND_ATTEMPTS` attempts.
+///
+/// A transaction that was confirmed with an error is not retried
 pub fn send_and_confirm_transactions(
     rpc_client: &RpcClient,
-    dry_run: bool,
-    transactions: Vec<Transaction>,
-    authorized_staker: &Keypair,
-) -> Result<SendAndConfirmTransactionResult, Box<dyn error::Error>> {
+    transactions: Vec<DescribedTransaction>,
+    authorized_staker: &dyn Signer,
+) -> Result<Vec<TransactionReport>, Box<dyn error::Error>> {
     let authorized_staker_balance = rpc_client.get_balance(&authorized_staker.pubkey())?;
     info!(
         "Authorized staker balance: {} SOL",
         lamports_to_sol(authorized_staker_balance)
     );
 
-    let (blockhash, fee_calculator) = rpc_client.get_recent_blockhash()?;
+    let (_, fee_calculator) = rpc_client.get_recent_blockhash()?;
     info!("{} transactions to send", transactions.len());
 
     let required_fee = transactions.iter().fold(0, |fee, transaction| {
-        fee + fee_calculator.calculate_fee(&transaction.message)
+        fee + fee_calculator.calculate_fee(&transaction.transaction.message)
     });
     info!("Required fee: {} SOL", lamports_to_sol(required_fee));
     if required_fee > authorized_staker_balance {
         return Err("Authorized staker has insufficient funds".into());
     }
 
-    let mut pending_signatures = HashSet::new();
-    for mut transaction in transactions {
-        transaction.sign(&[authorized_staker], blockhash);
-
-        pending_signatures.insert(transaction.signatures[0]);
-        if !dry_run {
-            rpc_client.send_transaction(&transaction)?;
+    let mut reports: Vec<Option<TransactionReport>> = vec![None; transactions.len()];
+    let mut unconfirmed = (0..transactions.len()).collect::<Vec<_>>();
+    for attempt in 1..=MAX_SEND_ATTEMPTS {
+        if unconfirmed.is_empty() {
+            break;
+        }
+        if attempt > 1 {
+            warn!(
+                "Resending {} transactions, attempt {} of {}",
+                unconfirmed.len(),
+                attempt,
+                MAX_SEND_ATTEMPTS
+            );
         }
-    }
 
-    let mut succeeded_transactions = HashSet::new();
-    let mut failed_transactions = HashSet::new();
-    loop {
-        if pending_signatures.is_empty() {
-            break;
+        let (blockhash, _) = rpc_client.get_recent_blockhash()?;
+        let mut signed_transactions = vec![];
+        for i in &unconfirmed {
+            let mut transaction = transactions[*i].transaction.clone();
+            transaction.try_sign(&vec![authorized_staker], blockhash)?;
+            signed_transactions.push(transaction);
         }
 
-        let blockhash_expired = rpc_client
-            .get_fee_calculator_for_blockhash(&blockhash)?
-            .is_none();
-        if blockhash_expired {
-            error!(
-                "Blockhash {} expired with {} pending transactions",
-                blockhash,
-                pending_signatures.len()
-            );
+        let statuses = send_and_confirm_until_expired(rpc_client, &signed_transactions)?;
 
-            for signature in pending_signatures.into_iter() {
-                failed_transactions.insert(signature);
+        let mut still_unconfirmed = vec![];
+        for ((i, transaction), status) in unconfirmed
+            .into_iter()
+            .zip(signed_transactions)
+            .zip(statuses)
+        {
+            // Only transactions that can no longer be processed are signed again. A transaction that
+            // failed preflight would fail again
+            let retry = matches!(
+                status,
+                TransactionStatus::Rejected(_) | TransactionStatus::Expired
+            );
+            reports[i] = Some(TransactionReport {
+                memo: transactions[i].memo.clone(),
+                identities: transactions[i].identities.clone(),
+                signature: transaction.signatures[0],
+                attempts: attempt,
+                status,
+            });
+            if retry {
+                still_unconfirmed.push(i);
             }
-            break;
         }
+        unconfirmed = still_unconfirmed;
+    }
+
+    let reports = reports.into_iter().flatten().collect::<Vec<_>>();
+    log_transaction_reports(&reports);
+    Ok(reports)
+}
+
+/// Send transactions that have already been signed, possibly offline. As they cannot be
+/// re-signed, each is only re-broadcast until its blockhash expires or, for a transaction on a
+/// durable nonce, until the nonce is advanced
+pub fn send_and_confirm_signed_transactions(
+    rpc_client: &RpcClient,
+    transactions: Vec<DescribedTransaction>,
+) -> Result<Vec<TransactionReport>, Box<dyn error::Error>> {
+    let signed_transactions = transactions
+        .iter()
+        .map(|transaction| transaction.transaction.clone())
+        .collect::<Vec<_>>();
+    let statuses = send_and_confirm_until_expired(rpc_client, &signed_transactions)?;
+
+    let reports = transactions
+        .into_iter()
+        .zip(statuses)
+        .map(|(transaction, status)| TransactionReport {
+            signature: transaction.transaction.signatures[0],
+            memo: transaction.memo,
+            identities: transaction.identities,
+            attempts: 1,
+            status,
+        })
+        .collect::<Vec<_>>();
+    log_transaction_reports(&reports);
+    Ok(reports)
+}
+
+fn send_transaction(
+    rpc_client: &RpcClient,
+    transaction: &Transaction,
+) -> Result<(), client_error::ClientError> {
+    let result = rpc_client.send_transaction(transaction).map(|_| ());
+    sleep(Duration::from_millis(1000 / MAX_TRANSACTIONS_PER_SECOND));
+    result
+}
+
+// The status of a transaction that could not be sent, or `None` if the RPC node may still have
+// forwarded it, such as when the request timed out. Only a transaction that the RPC node answered
+// with an error is known not to have been forwarded, and may be signed again with a fresh
+// blockhash without risking that it is processed twice
+fn send_error_status(err: &client_error::ClientError) -> Option<TransactionStatus> {
+    match err.kind() {
+        client_error::ClientErrorKind::RpcError(RpcError::RpcResponseError { data, .. }) => {
+            Some(match data {
+                RpcResponseErrorData::SendTransactionPreflightFailure(
+                    RpcSimulateTransactionResult {
+                        err: Some(TransactionError::BlockhashNotFound),
+                        ..
+                    },
+                ) => TransactionStatus::Rejected(err.to_string()),
+                RpcResponseErrorData::SendTransactionPreflightFailure(_) => {
+                    TransactionStatus::PreflightFailed(err.to_string())
+                }
+                _ => TransactionStatus::Rejected(err.to_string()),
+            })
+        }
+        _ => None,
+    }
+}
+
+// Send signed transactions, re-broadcasting them until each one is either confirmed or its
+// blockhash expires, or its durable nonce is advanced. Returns the status of each transaction
+fn send_and_confirm_until_expired(
+    rpc_client: &RpcClient,
+    transactions: &[Transaction],
+) -> Result<Vec<TransactionStatus>, Box<dyn error::Error>> {
+    let mut statuses = vec![None; transactions.len()];
+    let mut pending = vec![];
+    for (i, transaction) in transactions.iter().enumerate() {
+        match send_transaction(rpc_client, transaction) {
+            Ok(()) => pending.push(i),
+            Err(err) => match send_error_status(&err) {
+                Some(status) => {
+                    warn!("{}: not sent: {}", transaction.signatures[0], err);
+                    statuses[i] = Some(status);
+                }
+                None => {
+                    warn!(
+                        "{}: send failed, waiting for it to be confirmed or expire: {}",
+                        transaction.signatures[0], err
+                    );
+                    pending.push(i);
+                }
+            },
+        }
+    }
 
-        let mut statuses = vec![];
-        for pending_signatures_chunk in pending_signatures
+    let mut last_broadcast = Instant::now();
+    while !pending.is_empty() {
+        // Check for expired blockhashes before fetching the statuses, so that a transaction is
+        // only considered expired once it can no longer be confirmed
+        let mut expired_blockhashes = HashSet::new();
+        for (blockhash, transaction) in pending
             .iter()
-            .cloned()
-            .collect::<Vec<_>>()
-            .chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS - 1)
+            .map(|i| (transactions[*i].message.recent_blockhash, &transactions[*i]))
+            .collect::<HashMap<_, _>>()
         {
-            trace!(
-                "checking {} pending_signatures",
-                pending_signatures_chunk.len()
-            );
-            statuses.extend(
+            if rpc_client
+                .get_fee_calculator_for_blockhash(&blockhash)?
+                .is_none()
+                && !has_unused_durable_nonce(rpc_client, transaction)?
+            {
+                expired_blockhashes.insert(blockhash);
+            }
+        }
+
+        let mut signature_statuses = vec![];
+        for pending_chunk in pending.chunks(MAX_GET_SIGNATURE_STATUSES_QUERY_ITEMS - 1) {
+            trace!("checking {} pending signatures", pending_chunk.len());
+            let signatures = pending_chunk
+                .iter()
+                .map(|i| transactions[*i].signatures[0])
+                .collect::<Vec<_>>();
+            signature_statuses.extend(
                 rpc_client
-                    .get_signature_statuses(pending_signatures_chunk)?
+                    .get_signature_statuses(&signatures)?
                     .value
                     .into_iter(),
             )
         }
-        assert_eq!(statuses.len(), pending_signatures.len());
-
-        let mut still_pending_signatures = HashSet::new();
-        for (signature, status) in pending_signatures.into_iter().zip(statuses.into_iter()) {
-            trace!("{}: status={:?}", signature, status);
-            let completed = if dry_run {
-                Some(true)
-            } else if let Some(status) = &status {
-                if status.satisfies_commitment(rpc_client.commitment()) {
-                    Some(status.err.is_none())
-                } else {
-                    None
+        assert_eq!(signature_statuses.len(), pending.len());
+
+        let mut still_pending = vec![];
+        for (i, status) in pending.into_iter().zip(signature_statuses.into_iter()) {
+            let transaction = &transactions[i];
+            trace!("{}: status={:?}", transaction.signatures[0], status);
+            let status = match status {
+                Some(status) if status.satisfies_commitment(rpc_client.commitment()) => {
+                    Some(match status.err {
+                        None => TransactionStatus::Succeeded,
+                        Some(err) => TransactionStatus::Failed(err.to_string()),
+                    })
+                }
+                // A processed transaction may still be confirmed after its blockhash expires
+                Some(_) => None,
+                None if expired_blockhashes.contains(&transaction.message.recent_blockhash) => {
+                    Some(TransactionStatus::Expired)
                 }
-            } else {
-                None
+                None => None,
             };
 
-            if let Some(success) = completed {
-                info!("{}: completed. success={}", signature, success);
-                if success {
-                    succeeded_transactions.insert(signature);
-                } else {
-                    failed_transactions.insert(signature);
+            match status {
+                Some(status) => {
+                    info!(
+                        "{}: completed. status={:?}",
+                        transaction.signatures[0], status
+                    );
+                    statuses[i] = Some(status);
                 }
-            } else {
-                still_pending_signatures.insert(signature);
+                None => still_pending.push(i),
             }
         }
-        pending_signatures = still_pending_signatures;
+        pending = still_pending;
+
+        if last_broadcast.elapsed() > REBROADCAST_INTERVAL {
+            for i in &pending {
+                // Errors are expected here, such as for transactions that were already processed
+                let _ = send_transaction(rpc_client, &transactions[*i]);
+            }
+            last_broadcast = Instant::now();
+        }
         sleep(Duration::from_millis(250));
     }
 
-    Ok(SendAndConfirmTransactionResult {
-        succeeded: succeeded_transactions,
-        failed: failed_transactions,
-    })
+    Ok(statuses.into_iter().map(|status| status.unwrap()).collect())
 }
 
 pub struct VoteAccountInfo {
@@ -309,6 +740,26 @@ pub fn get_all_stake(
     Ok((all_stake_addresses, total_stake_balance))
 }
 
+/// Activation of a stake account, or `None` if the account does not exist
+pub fn get_stake_activation_if_exists(
+    rpc_client: &RpcClient,
+    stake_address: Pubkey,
+) -> Result<Option<RpcStakeActivation>, Box<dyn error::Error>> {
+    if rpc_client.get_balance(&stake_address)? == 0 {
+        return Ok(None);
+    }
+    rpc_client
+        .get_stake_activation(stake_address, None)
+        .map(Some)
+        .map_err(|err| {
+            format!(
+                "Unable to get activation information for stake account: {}: {}",
+                stake_address, err
+            )
+            .into()
+        })
+}
+
 #[cfg(test)]
 pub mod test {
     use {
@@ -317,7 +768,7 @@ pub mod test {
         indicatif::{ProgressBar, ProgressStyle},
         solana_sdk::{
             borsh::get_packed_len, clock::Epoch, program_pack::Pack, pubkey::Pubkey,
-            system_instruction,
+            signature::Keypair, system_instruction,
         },
         solana_stake_program::{
             stake_instruction,
@@ -329,6 +780,7 @@ pub mod test {
             state::{Fee, StakePool, ValidatorList},
         },
         spl_token::state::{Account, Mint},
+        std::io,
     };
 
     fn new_spinner_progress_bar() -> ProgressBar {
@@ -670,4 +1122,86 @@ pub mod test {
             .send_and_confirm_transaction_with_spinner(&transaction)
             .map(|_| ())
     }
+
+    #[test]
+    fn test_merge_transactions() {
+        let payer = Pubkey::new_unique();
+        let transactions = (0..100)
+            .map(|i| {
+                let identity = Pubkey::new_unique();
+                DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &[system_instruction::transfer(&payer, &identity, i)],
+                        Some(&payer),
+                    ),
+                    memo: format!("Transfer {}", i),
+                    identities: vec![identity],
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let merged_transaction = merge_transactions(&transactions[..2]).unwrap();
+        assert_eq!(merged_transaction.memo, "Transfer 0; Transfer 1");
+        assert_eq!(
+            merged_transaction.identities,
+            vec![transactions[0].identities[0], transactions[1].identities[0]]
+        );
+        assert_eq!(
+            message_instructions(&merged_transaction.transaction.message),
+            vec![
+                system_instruction::transfer(&payer, &transactions[0].identities[0], 0),
+                system_instruction::transfer(&payer, &transactions[1].identities[0], 1),
+            ]
+        );
+
+        // Too many instructions to fit in a packet
+        assert!(merge_transactions(&transactions).is_none());
+        assert!(merge_transactions(&[]).is_none());
+    }
+
+    #[test]
+    fn test_send_error_status() {
+        let preflight_failure = |err| {
+            client_error::ClientError::from(RpcError::RpcResponseError {
+                code: -32002,
+                message: "Transaction simulation failed".to_string(),
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(
+                    RpcSimulateTransactionResult {
+                        err: Some(err),
+                        logs: None,
+                    },
+                ),
+            })
+        };
+
+        assert!(matches!(
+            send_error_status(&preflight_failure(TransactionError::AccountNotFound)),
+            Some(TransactionStatus::PreflightFailed(_))
+        ));
+        assert!(matches!(
+            send_error_status(&preflight_failure(TransactionError::BlockhashNotFound)),
+            Some(TransactionStatus::Rejected(_))
+        ));
+        assert!(matches!(
+            send_error_status(&client_error::ClientError::from(
+                RpcError::RpcResponseError {
+                    code: -32005,
+                    message: "Node is unhealthy".to_string(),
+                    data: RpcResponseErrorData::NodeUnhealthy {
+                        num_slots_behind: None
+                    },
+                }
+            )),
+            Some(TransactionStatus::Rejected(_))
+        ));
+
+        // The transaction may have been forwarded before the request timed out
+        assert_eq!(
+            send_error_status(&client_error::ClientError::from(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "timed out"
+            ))),
+            None
+        );
+    }
 }
@@ -0,0 +1,340 @@
+use {
+    crate::{
+        db::{ClassificationStore, EpochClassification, StakeStateHistory},
+        generic_stake_pool::ValidatorStakeState,
+    },
+    rusqlite::{params, Connection, OptionalExtension},
+    solana_sdk::{clock::Epoch, pubkey::Pubkey},
+    std::{fs, io, path::Path, str::FromStr},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS epochs (
+        epoch INTEGER PRIMARY KEY,
+        classification TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS validator_classifications (
+        epoch INTEGER NOT NULL REFERENCES epochs(epoch) ON DELETE CASCADE,
+        identity TEXT NOT NULL,
+        vote_address TEXT NOT NULL,
+        participant TEXT,
+        stake_state TEXT NOT NULL,
+        stake_state_reason TEXT NOT NULL,
+        current_data_center TEXT NOT NULL,
+        PRIMARY KEY (epoch, identity)
+    );
+    CREATE INDEX IF NOT EXISTS validator_classifications_identity
+        ON validator_classifications (identity, stake_state);
+    CREATE TABLE IF NOT EXISTS stake_actions (
+        epoch INTEGER NOT NULL REFERENCES epochs(epoch) ON DELETE CASCADE,
+        identity TEXT NOT NULL,
+        stake_action TEXT NOT NULL,
+        PRIMARY KEY (epoch, identity)
+    );
+    CREATE TABLE IF NOT EXISTS data_centers (
+        epoch INTEGER NOT NULL REFERENCES epochs(epoch) ON DELETE CASCADE,
+        data_center TEXT NOT NULL,
+        stake INTEGER NOT NULL,
+        stake_percent REAL NOT NULL,
+        validators INTEGER NOT NULL,
+        PRIMARY KEY (epoch, data_center)
+    );
+";
+
+fn into_io_error<E: std::fmt::Debug>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, format!("{:?}", err))
+}
+
+fn stake_state_from_str(s: &str) -> Result<ValidatorStakeState, io::Error> {
+    match s {
+        "None" => Ok(ValidatorStakeState::None),
+        "Baseline" => Ok(ValidatorStakeState::Baseline),
+        "Bonus" => Ok(ValidatorStakeState::Bonus),
+        _ => Err(into_io_error(format!("Invalid stake state: {}", s))),
+    }
+}
+
+// Stores epoch classifications in an SQLite database.
+//
+// The complete classification of each epoch is kept in the `epochs` table, while the
+// `validator_classifications`, `stake_actions` and `data_centers` tables hold a queryable copy of
+// its contents
+pub struct SqliteStore {
+    connection: Connection,
+}
+
+impl SqliteStore {
+    pub fn open<P>(path: P) -> Result<Self, io::Error>
+    where
+        P: AsRef<Path>,
+    {
+        if let Some(parent) = path.as_ref().parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(path).map_err(into_io_error)?;
+        connection
+            .execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(into_io_error)?;
+        connection.execute_batch(SCHEMA).map_err(into_io_error)?;
+        Ok(Self { connection })
+    }
+}
+
+impl ClassificationStore for SqliteStore {
+    fn epochs(&self) -> Result<Vec<Epoch>, io::Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT epoch FROM epochs ORDER BY epoch")
+            .map_err(into_io_error)?;
+        let epochs = statement
+            .query_map(params![], |row| row.get::<_, i64>(0))
+            .map_err(into_io_error)?
+            .map(|epoch| epoch.map(|epoch| epoch as Epoch))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(into_io_error)?;
+        Ok(epochs)
+    }
+
+    fn exists(&self, epoch: Epoch) -> Result<bool, io::Error> {
+        self.connection
+            .query_row(
+                "SELECT 1 FROM epochs WHERE epoch = ?1",
+                params![epoch as i64],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|row| row.is_some())
+            .map_err(into_io_error)
+    }
+
+    fn load(&self, epoch: Epoch) -> Result<EpochClassification, io::Error> {
+        let classification: String = self
+            .connection
+            .query_row(
+                "SELECT classification FROM epochs WHERE epoch = ?1",
+                params![epoch as i64],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(into_io_error)?
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("No classification for epoch {}", epoch),
+                )
+            })?;
+        serde_yaml::from_str(&classification).map_err(into_io_error)
+    }
+
+    // All tables are updated within a single transaction
+    fn save(
+        &self,
+        epoch: Epoch,
+        epoch_classification: &EpochClassification,
+    ) -> Result<(), io::Error> {
+        let classification = serde_yaml::to_string(epoch_classification).map_err(into_io_error)?;
+        let current = epoch_classification.clone().into_current();
+        let epoch = epoch as i64;
+
+        let transaction = self
+            .connection
+            .unchecked_transaction()
+            .map_err(into_io_error)?;
+
+        transaction
+            .execute("DELETE FROM epochs WHERE epoch = ?1", params![epoch])
+            .map_err(into_io_error)?;
+        transaction
+            .execute(
+                "INSERT INTO epochs (epoch, classification) VALUES (?1, ?2)",
+                params![epoch, classification],
+            )
+            .map_err(into_io_error)?;
+
+        for validator_classification in current
+            .validator_classifications
+            .iter()
+            .flat_map(|validator_classifications| validator_classifications.values())
+        {
+            let identity = validator_classification.identity.to_string();
+            transaction
+                .execute(
+                    "INSERT INTO validator_classifications (epoch, identity, vote_address, \
+                     participant, stake_state, stake_state_reason, current_data_center) \
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                    params![
+                        epoch,
+                        identity,
+                        validator_classification.vote_address.to_string(),
+                        validator_classification
+                            .participant
+                            .map(|participant| participant.to_string()),
+                        format!("{:?}", validator_classification.stake_state),
+                        validator_classification.stake_state_reason.to_string(),
+                        validator_classification.current_data_center.to_string(),
+                    ],
+                )
+                .map_err(into_io_error)?;
+
+            if let Some(ref stake_action) = validator_classification.stake_action {
+                transaction
+                    .execute(
+                        "INSERT INTO stake_actions (epoch, identity, stake_action) \
+                         VALUES (?1, ?2, ?3)",
+                        params![epoch, identity, stake_action.to_string()],
+                    )
+                    .map_err(into_io_error)?;
+            }
+        }
+
+        for data_center_info in &current.data_center_info {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO data_centers (epoch, data_center, stake, stake_percent, \
+                     validators) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        epoch,
+                        data_center_info.id.to_string(),
+                        data_center_info.stake as i64,
+                        data_center_info.stake_percent,
+                        data_center_info.validators.len() as i64,
+                    ],
+                )
+                .map_err(into_io_error)?;
+        }
+
+        transaction.commit().map_err(into_io_error)
+    }
+
+    // The history is derived from the `validator_classifications` table
+    fn load_stake_state_history(&self) -> Result<StakeStateHistory, io::Error> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT epoch, identity, stake_state FROM validator_classifications")
+            .map_err(into_io_error)?;
+        let rows = statement
+            .query_map(params![], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })
+            .map_err(into_io_error)?;
+
+        let mut history = StakeStateHistory::default();
+        for row in rows {
+            let (epoch, identity, stake_state) = row.map_err(into_io_error)?;
+            history.record_stake_state(
+                Pubkey::from_str(&identity).map_err(into_io_error)?,
+                epoch as Epoch,
+                stake_state_from_str(&stake_state)?,
+            );
+        }
+        Ok(history)
+    }
+
+    fn save_stake_state_history(&self, _history: &StakeStateHistory) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn destaked_epochs(&self, identity: &Pubkey) -> Result<Vec<Epoch>, io::Error> {
+        let mut statement = self
+            .connection
+            .prepare(
+                "SELECT epoch FROM validator_classifications \
+                 WHERE identity = ?1 AND stake_state = 'None' ORDER BY epoch",
+            )
+            .map_err(into_io_error)?;
+        let epochs = statement
+            .query_map(params![identity.to_string()], |row| row.get::<_, i64>(0))
+            .map_err(into_io_error)?
+            .map(|epoch| epoch.map(|epoch| epoch as Epoch))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(into_io_error)?;
+        Ok(epochs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::db::{EpochClassificationV2, ValidatorClassification},
+        std::collections::HashMap,
+    };
+
+    fn epoch_classification(
+        identity: Pubkey,
+        stake_state: ValidatorStakeState,
+    ) -> EpochClassification {
+        let mut validator_classifications = HashMap::new();
+        validator_classifications.insert(
+            identity,
+            ValidatorClassification {
+                identity,
+                stake_state,
+                ..ValidatorClassification::default()
+            },
+        );
+        EpochClassification::new(EpochClassificationV2 {
+            validator_classifications: Some(validator_classifications),
+            ..EpochClassificationV2::default()
+        })
+    }
+
+    #[test]
+    fn test_sqlite_store() {
+        let store = SqliteStore::open(":memory:").unwrap();
+        let identity = Pubkey::new_unique();
+
+        assert_eq!(store.epochs().unwrap(), Vec::<Epoch>::new());
+        assert!(!store.exists(1).unwrap());
+        assert!(store.load(1).is_err());
+
+        store
+            .save(
+                1,
+                &epoch_classification(identity, ValidatorStakeState::None),
+            )
+            .unwrap();
+        store
+            .save(
+                2,
+                &epoch_classification(identity, ValidatorStakeState::Bonus),
+            )
+            .unwrap();
+        store
+            .save(
+                3,
+                &epoch_classification(identity, ValidatorStakeState::Bonus),
+            )
+            .unwrap();
+        // Saving an epoch again replaces it
+        store
+            .save(
+                3,
+                &epoch_classification(identity, ValidatorStakeState::None),
+            )
+            .unwrap();
+
+        assert_eq!(store.epochs().unwrap(), vec![1, 2, 3]);
+        assert!(store.exists(2).unwrap());
+        assert_eq!(
+            store
+                .load(2)
+                .unwrap()
+                .into_current()
+                .validator_classifications
+                .unwrap()[&identity]
+                .stake_state,
+            ValidatorStakeState::Bonus
+        );
+        assert_eq!(store.destaked_epochs(&identity).unwrap(), vec![1, 3]);
+        assert_eq!(store.load_previous(3).unwrap().unwrap().0, 2);
+
+        let history = store.load_stake_state_history().unwrap();
+        assert_eq!(history.stake_state_streak(&identity, 2), 1);
+        assert!(history.staked_for(&identity, 1, 3));
+    }
+}
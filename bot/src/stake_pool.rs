@@ -1,16 +1,18 @@
 use {
     crate::{
         generic_stake_pool::*,
-        rpc_client_utils::{get_all_stake, send_and_confirm_transactions},
+        notes::{EpochNote, StakeAction, StakeOperation, ValidatorStakeAction},
+        rebalance::{deferred_stake_note, max_stake_churn, plan_rebalance, RebalanceBudget},
+        rpc_client_utils::{get_all_stake, get_stake_activation_if_exists, DescribedTransaction},
     },
     borsh::BorshDeserialize,
     log::*,
     solana_client::{rpc_client::RpcClient, rpc_response::StakeActivationState},
     solana_sdk::{
         borsh::try_from_slice_unchecked,
-        native_token::{Sol, LAMPORTS_PER_SOL},
+        native_token::{sol_to_lamports, Sol, LAMPORTS_PER_SOL},
         pubkey::Pubkey,
-        signature::{Keypair, Signer},
+        signature::Signer,
         system_instruction,
         transaction::Transaction,
     },
@@ -22,7 +24,8 @@ use {
     },
     std::{
         collections::{HashMap, HashSet},
-        error, mem,
+        error, fmt, mem,
+        str::FromStr,
     },
 };
 
@@ -72,10 +75,64 @@ fn staker_transient_stake_address(authorized_staker: Pubkey, vote_address: Pubke
     .unwrap()
 }
 
+/// Liquid stake to keep in the stake pool reserve for user withdrawals
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReserveTarget {
+    Lamports(u64),
+    /// Percentage of the total stake pool balance
+    Percentage(usize),
+}
+
+impl Default for ReserveTarget {
+    fn default() -> Self {
+        Self::Lamports(0)
+    }
+}
+
+impl ReserveTarget {
+    pub fn lamports(&self, total_stake_amount: u64) -> u64 {
+        match self {
+            Self::Lamports(lamports) => *lamports,
+            Self::Percentage(percentage) => {
+                (total_stake_amount as u128 * *percentage as u128 / 100) as u64
+            }
+        }
+    }
+}
+
+impl FromStr for ReserveTarget {
+    type Err = String;
+
+    // Either an amount of SOL, or a percentage such as "5%"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(percentage) = s.strip_suffix('%') {
+            match percentage.parse::<usize>() {
+                Ok(percentage) if percentage <= 100 => Ok(Self::Percentage(percentage)),
+                _ => Err(format!("Invalid reserve target percentage: {}", s)),
+            }
+        } else {
+            s.parse::<f64>()
+                .map(|sol| Self::Lamports(sol_to_lamports(sol)))
+                .map_err(|err| format!("Invalid reserve target: {}: {}", s, err))
+        }
+    }
+}
+
+impl fmt::Display for ReserveTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lamports(lamports) => write!(f, "{}", Sol(*lamports)),
+            Self::Percentage(percentage) => write!(f, "{}% of the pool", percentage),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct StakePoolOMatic {
-    authorized_staker: Keypair,
+    authorized_staker: Box<dyn Signer>,
     baseline_stake_amount: u64,
+    max_churn_percentage: usize,
+    reserve_target: ReserveTarget,
     stake_pool_address: Pubkey,
     stake_pool: StakePool,
     validator_list: ValidatorList,
@@ -83,9 +140,11 @@ pub struct StakePoolOMatic {
 
 pub fn new(
     rpc_client: &RpcClient,
-    authorized_staker: Keypair,
+    authorized_staker: Box<dyn Signer>,
     stake_pool_address: Pubkey,
     baseline_stake_amount: u64,
+    max_churn_percentage: usize,
+    reserve_target: ReserveTarget,
 ) -> Result<StakePoolOMatic, Box<dyn error::Error>> {
     if baseline_stake_amount < MIN_STAKE_CHANGE_AMOUNT {
         return Err(format!(
@@ -110,6 +169,8 @@ pub fn new(
     Ok(StakePoolOMatic {
         authorized_staker,
         baseline_stake_amount,
+        max_churn_percentage,
+        reserve_target,
         stake_pool_address,
         stake_pool,
         validator_list,
@@ -120,13 +181,20 @@ impl StakePoolOMatic {
     /// Perform the double update, required at the start of an epoch:
     /// * call into the stake pool program to update the accounting of lamports
     /// * update the StakePool and ValidatorList objects based on the accounting
-    pub fn epoch_update(&mut self, rpc_client: &RpcClient) -> Result<(), Box<dyn error::Error>> {
+    pub fn epoch_update(
+        &mut self,
+        rpc_client: &RpcClient,
+        dry_run: bool,
+        plan: &mut TransactionPlan,
+    ) -> Result<(), Box<dyn error::Error>> {
         update_stake_pool(
             rpc_client,
-            &self.authorized_staker,
+            dry_run,
+            &*self.authorized_staker,
             &self.stake_pool_address,
             &self.stake_pool,
             &self.validator_list,
+            plan,
         )?;
         self.update(rpc_client)?;
         Ok(())
@@ -156,8 +224,10 @@ impl GenericStakePool for StakePoolOMatic {
         rpc_client: &RpcClient,
         dry_run: bool,
         desired_validator_stake: &[ValidatorStake],
-    ) -> Result<(EpochStakeNotes, ValidatorStakeActions), Box<dyn error::Error>> {
+    ) -> Result<(EpochStakeNotes, ValidatorStakeActions, TransactionPlan), Box<dyn error::Error>>
+    {
         let mut validator_stake_actions = HashMap::default();
+        let mut plan = vec![];
         let mut bonus_stake_node_count = 0;
         let mut baseline_stake_node_count = 0;
 
@@ -180,10 +250,15 @@ impl GenericStakePool for StakePoolOMatic {
         }
 
         info!("Withdraw inactive transient stake accounts to the staker");
-        withdraw_inactive_stakes_to_staker(rpc_client, &self.authorized_staker)?;
+        withdraw_inactive_stakes_to_staker(
+            rpc_client,
+            dry_run,
+            &*self.authorized_staker,
+            &mut plan,
+        )?;
 
         info!("Update the stake pool, merging transient stakes and orphaned accounts");
-        self.epoch_update(rpc_client)?;
+        self.epoch_update(rpc_client, dry_run, &mut plan)?;
 
         let all_vote_addresses: HashSet<Pubkey> = self
             .validator_list
@@ -194,21 +269,25 @@ impl GenericStakePool for StakePoolOMatic {
         info!("Remove validators no longer present in the desired list");
         remove_validators_from_pool(
             rpc_client,
-            &self.authorized_staker,
+            dry_run,
+            &*self.authorized_staker,
             &self.stake_pool_address,
             &self.stake_pool,
             &self.validator_list,
             &all_vote_addresses - &inuse_vote_addresses,
+            &mut plan,
         )?;
 
         info!("Add new validators to pool if active");
         add_validators_to_pool(
             rpc_client,
-            &self.authorized_staker,
+            dry_run,
+            &*self.authorized_staker,
             desired_validator_stake,
             &self.stake_pool_address,
             &self.stake_pool,
             &self.validator_list,
+            &mut plan,
         )?;
         self.update(rpc_client)?;
 
@@ -223,10 +302,12 @@ impl GenericStakePool for StakePoolOMatic {
         info!("Create validator stake accounts if needed");
         create_validator_stake_accounts(
             rpc_client,
-            &self.authorized_staker,
+            dry_run,
+            &*self.authorized_staker,
             desired_validator_stake,
             &self.stake_pool_address,
             &mut validator_stake_actions,
+            &mut plan,
         )?;
 
         let total_stake_amount = self.stake_pool.total_stake_lamports;
@@ -247,9 +328,19 @@ impl GenericStakePool for StakePoolOMatic {
             return Err("Not enough stake to cover the baseline".into());
         }
 
+        // The reserve target is kept out of the bonus stake, so that once withdrawals drain the
+        // reserve the bonus stake is decreased to refill it
+        let reserve_target = self.reserve_target.lamports(total_stake_amount);
+        info!(
+            "Reserve target: {} ({})",
+            Sol(reserve_target),
+            self.reserve_target
+        );
+
         info!("Bonus node count: {}", bonus_stake_node_count);
-        let total_bonus_stake_amount =
-            total_stake_amount.saturating_sub(total_baseline_stake_amount);
+        let total_bonus_stake_amount = total_stake_amount
+            .saturating_sub(total_baseline_stake_amount)
+            .saturating_sub(reserve_target);
         info!(
             "Total bonus stake amount: {}",
             Sol(total_bonus_stake_amount)
@@ -279,15 +370,34 @@ impl GenericStakePool for StakePoolOMatic {
             "Reserve stake available balance before updates: {}",
             Sol(reserve_stake_balance)
         );
+        if reserve_stake_balance < reserve_target {
+            warn!(
+                "Reserve stake is {} below its target",
+                Sol(reserve_target - reserve_stake_balance)
+            );
+        }
 
-        let notes = vec![
-            format!(
-                "Stake pool size: {} (inactive: {})",
-                Sol(total_stake_amount),
-                Sol(reserve_stake_balance)
-            ),
-            format!("Baseline stake amount: {}", Sol(self.baseline_stake_amount)),
-            format!("Bonus stake amount: {}", Sol(bonus_stake_amount)),
+        let max_churn = max_stake_churn(total_stake_amount, self.max_churn_percentage);
+        info!("Maximum stake churn: {}", Sol(max_churn));
+
+        let mut notes = vec![
+            EpochNote::StakePoolSize {
+                total: total_stake_amount,
+                inactive: reserve_stake_balance,
+            },
+            EpochNote::BaselineStakeAmount {
+                lamports: self.baseline_stake_amount,
+            },
+            EpochNote::BonusStakeAmount {
+                lamports: bonus_stake_amount,
+            },
+            EpochNote::ReserveTarget {
+                lamports: reserve_target,
+            },
+            EpochNote::MaximumStakeChurn {
+                percentage: self.max_churn_percentage,
+                lamports: max_churn,
+            },
         ];
 
         let busy_validators = validator_stake_actions
@@ -297,7 +407,7 @@ impl GenericStakePool for StakePoolOMatic {
         distribute_validator_stake(
             rpc_client,
             dry_run,
-            &self.authorized_staker,
+            &*self.authorized_staker,
             &self.stake_pool_address,
             &self.stake_pool,
             &self.validator_list,
@@ -305,12 +415,127 @@ impl GenericStakePool for StakePoolOMatic {
                 .iter()
                 .filter(|vs| !busy_validators.contains(&vs.identity))
                 .cloned(),
-            reserve_stake_balance,
+            RebalanceBudget {
+                reserve: reserve_stake_balance.saturating_sub(reserve_target),
+                churn: max_churn,
+            },
             self.baseline_stake_amount,
             bonus_stake_amount,
             &mut validator_stake_actions,
+            &mut plan,
+        )?;
+        notes.extend(deferred_stake_note(&validator_stake_actions));
+        Ok((notes, validator_stake_actions, plan))
+    }
+
+    fn emergency_destake(
+        &mut self,
+        rpc_client: &RpcClient,
+        dry_run: bool,
+        identity: Pubkey,
+        vote_address: Pubkey,
+    ) -> Result<(ValidatorStakeAction, TransactionPlan), Box<dyn error::Error>> {
+        let mut validator_stake_actions = HashMap::default();
+        let mut plan = vec![];
+        let desired_validator_stake = [ValidatorStake {
+            identity,
+            vote_address,
+            stake_state: ValidatorStakeState::None,
+        }];
+
+        add_unmerged_transient_stake_accounts(
+            rpc_client,
+            &desired_validator_stake,
+            &self.stake_pool_address,
+            &mut validator_stake_actions,
         )?;
-        Ok((notes, validator_stake_actions))
+        if validator_stake_actions.is_empty() {
+            distribute_validator_stake(
+                rpc_client,
+                dry_run,
+                &*self.authorized_staker,
+                &self.stake_pool_address,
+                &self.stake_pool,
+                &self.validator_list,
+                desired_validator_stake.iter().cloned(),
+                RebalanceBudget {
+                    reserve: 0,
+                    churn: u64::MAX,
+                },
+                self.baseline_stake_amount,
+                0,
+                &mut validator_stake_actions,
+                &mut plan,
+            )?;
+        }
+
+        let validator_stake_action = validator_stake_actions
+            .remove(&identity)
+            .ok_or_else(|| format!("Validator {} is not in the stake pool", identity))?;
+        Ok((validator_stake_action, plan))
+    }
+
+    fn status(
+        &self,
+        rpc_client: &RpcClient,
+        desired_validator_stake: &[ValidatorStake],
+    ) -> Result<StakePoolStatus, Box<dyn error::Error>> {
+        let identities = desired_validator_stake
+            .iter()
+            .map(|vs| (vs.vote_address, vs.identity))
+            .collect::<HashMap<_, _>>();
+
+        let mut validators = vec![];
+        for validator_entry in &self.validator_list.validators {
+            let vote_address = validator_entry.vote_account_address;
+            let stake_address = find_stake_program_address(
+                &spl_stake_pool::id(),
+                &vote_address,
+                &self.stake_pool_address,
+            )
+            .0;
+            let transient_stake_address = find_transient_stake_program_address(
+                &spl_stake_pool::id(),
+                &vote_address,
+                &self.stake_pool_address,
+            )
+            .0;
+            let stake_activation = get_stake_activation_if_exists(rpc_client, stake_address)?;
+            let transient_stake_activation =
+                get_stake_activation_if_exists(rpc_client, transient_stake_address)?;
+
+            validators.push(ValidatorStakeStatus {
+                identity: identities.get(&vote_address).cloned(),
+                vote_address,
+                stake_address,
+                active_stake: stake_activation
+                    .map(|activation| activation.active)
+                    .unwrap_or_default(),
+                transient_stake: transient_stake_activation
+                    .as_ref()
+                    .map(|activation| activation.active + activation.inactive)
+                    .unwrap_or_default(),
+                transient_stake_address: transient_stake_activation
+                    .as_ref()
+                    .map(|_| transient_stake_address),
+                transient_stake_state: transient_stake_activation
+                    .map(|activation| format!("{:?}", activation.state)),
+                stake_status: Some(format!("{:?}", validator_entry.status)),
+                ..ValidatorStakeStatus::default()
+            });
+        }
+
+        // Removed validator stake accounts and staker transient stake accounts are owned by the
+        // staker until they are withdrawn
+        let (orphaned_stake_addresses, _) =
+            get_all_stake(rpc_client, self.authorized_staker.pubkey())?;
+
+        Ok(StakePoolStatus {
+            reserve_stake_address: self.stake_pool.reserve_stake,
+            reserve_balance: rpc_client.get_balance(&self.stake_pool.reserve_stake)?,
+            validators,
+            orphaned_stake_addresses: orphaned_stake_addresses.into_iter().collect(),
+        })
     }
 }
 
@@ -365,11 +590,10 @@ fn add_unmerged_transient_stake_accounts(
             .value;
 
         if transient_stake_account.is_some() {
-            let action = format!(
-                "busy due to transient stake account {}",
-                transient_stake_address
-            );
-            validator_stake_actions.insert(*identity, action);
+            let action = StakeAction::BusyTransientStakeAccount {
+                address: transient_stake_address,
+            };
+            validator_stake_actions.insert(*identity, action.into());
         }
     }
     Ok(())
@@ -387,7 +611,9 @@ fn add_unmerged_transient_stake_accounts(
 /// and withdraws the entirety back to the staker.
 fn withdraw_inactive_stakes_to_staker(
     rpc_client: &RpcClient,
-    authorized_staker: &Keypair,
+    dry_run: bool,
+    authorized_staker: &dyn Signer,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
     let mut transactions = vec![];
     let (all_stake_addresses, _all_stake_total_amount) =
@@ -411,27 +637,39 @@ fn withdraw_inactive_stakes_to_staker(
 
             if stake_activation.state == StakeActivationState::Inactive {
                 let stake_lamports = stake_account.lamports;
-                transactions.push(Transaction::new_with_payer(
-                    &[stake_instruction::withdraw(
-                        &stake_address,
-                        &authorized_staker.pubkey(),
-                        &authorized_staker.pubkey(),
-                        stake_lamports,
-                        None,
-                    )],
-                    Some(&authorized_staker.pubkey()),
-                ));
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &[stake_instruction::withdraw(
+                            &stake_address,
+                            &authorized_staker.pubkey(),
+                            &authorized_staker.pubkey(),
+                            stake_lamports,
+                            None,
+                        )],
+                        Some(&authorized_staker.pubkey()),
+                    ),
+                    memo: format!(
+                        "Withdraw {} from inactive stake account {}",
+                        Sol(stake_lamports),
+                        stake_address
+                    ),
+                    identities: vec![],
+                });
             } else {
                 debug!("Staker's stake at {} not inactive, skipping", stake_address);
             }
         }
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-        .failed
-        .is_empty()
-    {
-        Err("Failed to add validators to the stake pool".into())
+    if !process_transactions(
+        rpc_client,
+        dry_run,
+        "withdraw inactive stakes",
+        transactions,
+        authorized_staker,
+        plan,
+    )? {
+        Err("Failed to withdraw inactive stakes to the staker".into())
     } else {
         Ok(())
     }
@@ -441,11 +679,28 @@ fn withdraw_inactive_stakes_to_staker(
 /// once per epoch to perform any operations on the stake pool.
 fn update_stake_pool(
     rpc_client: &RpcClient,
-    payer: &Keypair,
+    dry_run: bool,
+    payer: &dyn Signer,
     stake_pool_address: &Pubkey,
     stake_pool: &StakePool,
     validator_list: &ValidatorList,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
+    // A plan is signed one phase at a time, so an update that is already done must not be planned
+    // again, or it would keep the later phases from being planned
+    if dry_run {
+        let epoch = rpc_client.get_epoch_info()?.epoch;
+        if stake_pool.last_update_epoch == epoch
+            && validator_list
+                .validators
+                .iter()
+                .all(|validator_entry| validator_entry.last_update_epoch == epoch)
+        {
+            info!("Stake pool already updated in epoch {}", epoch);
+            return Ok(());
+        }
+    }
+
     let instructions = spl_stake_pool::instruction::update_stake_pool(
         stake_pool,
         validator_list,
@@ -453,23 +708,36 @@ fn update_stake_pool(
         false, // no_merge
     );
 
-    let mut transactions: Vec<Transaction> = instructions
+    let mut transactions: Vec<DescribedTransaction> = instructions
         .into_iter()
-        .map(|i| Transaction::new_with_payer(&[i], Some(&payer.pubkey())))
+        .enumerate()
+        .map(|(i, instruction)| DescribedTransaction {
+            transaction: Transaction::new_with_payer(&[instruction], Some(&payer.pubkey())),
+            memo: format!("Update stake pool {} ({})", stake_pool_address, i),
+            identities: vec![],
+        })
         .collect();
     let update_balance_transaction = transactions.split_off(transactions.len() - 1);
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, payer)?
-        .failed
-        .is_empty()
-    {
+    if !process_transactions(
+        rpc_client,
+        dry_run,
+        "update validator list balances",
+        transactions,
+        payer,
+        plan,
+    )? {
         return Err("Failed to update stake pool".into());
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, update_balance_transaction, payer)?
-        .failed
-        .is_empty()
-    {
+    if !process_transactions(
+        rpc_client,
+        dry_run,
+        "update stake pool balance",
+        update_balance_transaction,
+        payer,
+        plan,
+    )? {
         Err("Failed to update stake pool".into())
     } else {
         Ok(())
@@ -482,13 +750,16 @@ fn update_stake_pool(
 /// account must first be reduced down to the minimum of rent-exemption + 1 SOL.
 /// The staker will take control of the validator stake account on removal, so
 /// this also deactivates the stake, to be reclaimed in the next epoch.
+#[allow(clippy::too_many_arguments)]
 fn remove_validators_from_pool(
     rpc_client: &RpcClient,
-    authorized_staker: &Keypair,
+    dry_run: bool,
+    authorized_staker: &dyn Signer,
     stake_pool_address: &Pubkey,
     stake_pool: &StakePool,
     validator_list: &ValidatorList,
     remove_vote_addresses: HashSet<Pubkey>,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
     let mut transactions = vec![];
     let stake_rent_exemption = get_minimum_stake_balance_for_rent_exemption(rpc_client)?;
@@ -527,10 +798,14 @@ fn remove_validators_from_pool(
                     &removed_stake_address,
                     &authorized_staker.pubkey(),
                 ));
-                transactions.push(Transaction::new_with_payer(
-                    &instructions,
-                    Some(&authorized_staker.pubkey()),
-                ));
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &instructions,
+                        Some(&authorized_staker.pubkey()),
+                    ),
+                    memo: format!("Remove validator {} from the pool", vote_address),
+                    identities: vec![],
+                });
             } else {
                 debug!("Validator {} already removed, ignoring", vote_address);
             }
@@ -542,11 +817,15 @@ fn remove_validators_from_pool(
         }
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-        .failed
-        .is_empty()
-    {
-        Err("Failed to add validators to the stake pool".into())
+    if !process_transactions(
+        rpc_client,
+        dry_run,
+        "remove validators",
+        transactions,
+        authorized_staker,
+        plan,
+    )? {
+        Err("Failed to remove validators from the stake pool".into())
     } else {
         Ok(())
     }
@@ -554,13 +833,16 @@ fn remove_validators_from_pool(
 
 /// Add validator stake accounts that have been created and delegated, but not
 /// included yet in the stake pool
+#[allow(clippy::too_many_arguments)]
 fn add_validators_to_pool(
     rpc_client: &RpcClient,
-    authorized_staker: &Keypair,
+    dry_run: bool,
+    authorized_staker: &dyn Signer,
     desired_validator_stake: &[ValidatorStake],
     stake_pool_address: &Pubkey,
     stake_pool: &StakePool,
     validator_list: &ValidatorList,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
     let mut transactions = vec![];
     let stake_rent_exemption = get_minimum_stake_balance_for_rent_exemption(rpc_client)?;
@@ -634,19 +916,27 @@ fn add_validators_to_pool(
                             vote_address,
                         ),
                     );
-                    transactions.push(Transaction::new_with_payer(
-                        &instructions,
-                        Some(&authorized_staker.pubkey()),
-                    ));
+                    transactions.push(DescribedTransaction {
+                        transaction: Transaction::new_with_payer(
+                            &instructions,
+                            Some(&authorized_staker.pubkey()),
+                        ),
+                        memo: format!("Add validator {} to the pool", identity),
+                        identities: vec![*identity],
+                    });
                 }
             }
         }
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-        .failed
-        .is_empty()
-    {
+    if !process_transactions(
+        rpc_client,
+        dry_run,
+        "add validators",
+        transactions,
+        authorized_staker,
+        plan,
+    )? {
         Err("Failed to add validators to the stake pool".into())
     } else {
         Ok(())
@@ -658,10 +948,12 @@ fn add_validators_to_pool(
 /// busy validators.
 fn create_validator_stake_accounts(
     rpc_client: &RpcClient,
-    authorized_staker: &Keypair,
+    dry_run: bool,
+    authorized_staker: &dyn Signer,
     desired_validator_stake: &[ValidatorStake],
     stake_pool_address: &Pubkey,
     validator_stake_actions: &mut ValidatorStakeActions,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
     let mut staker_balance = rpc_client.get_balance(&authorized_staker.pubkey()).unwrap();
     info!("Staker available balance: {}", Sol(staker_balance));
@@ -695,20 +987,18 @@ fn create_validator_stake_accounts(
 
             match stake_activation.state {
                 StakeActivationState::Activating => {
-                    let action = format!(
-                        "stake account busy due to stake activation of {}",
-                        stake_address
-                    );
+                    let action = StakeAction::BusyStakeActivation {
+                        address: stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
                 StakeActivationState::Deactivating => {
-                    let action = format!(
-                        "stake account busy due to stake deactivation of {}",
-                        stake_address
-                    );
+                    let action = StakeAction::BusyStakeDeactivation {
+                        address: stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
                 StakeActivationState::Active => {}
                 StakeActivationState::Inactive => {
@@ -716,23 +1006,31 @@ fn create_validator_stake_accounts(
                         "Validator {} busy due to inactive stake {}: {:?}",
                         identity, stake_address, stake_activation
                     );
-                    let action =
-                        format!("stake account busy due to inactive stake {}", stake_address);
+                    let action = StakeAction::BusyInactiveStake {
+                        address: stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
 
-                    transactions.push(Transaction::new_with_payer(
-                        &[stake_instruction::delegate_stake(
-                            &stake_address,
-                            &authorized_staker.pubkey(),
-                            vote_address,
-                        )],
-                        Some(&authorized_staker.pubkey()),
-                    ));
+                    transactions.push(DescribedTransaction {
+                        transaction: Transaction::new_with_payer(
+                            &[stake_instruction::delegate_stake(
+                                &stake_address,
+                                &authorized_staker.pubkey(),
+                                vote_address,
+                            )],
+                            Some(&authorized_staker.pubkey()),
+                        ),
+                        memo: format!(
+                            "Activate stake account {} for validator {}",
+                            stake_address, identity
+                        ),
+                        identities: vec![*identity],
+                    });
                     debug!(
                         "Activating stake account for validator {} ({})",
                         identity, stake_address
                     );
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
             }
         } else {
@@ -754,28 +1052,42 @@ fn create_validator_stake_accounts(
                         vote_address,
                     );
 
-                transactions.push(Transaction::new_with_payer(
-                    &[instruction],
-                    Some(&authorized_staker.pubkey()),
-                ));
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &[instruction],
+                        Some(&authorized_staker.pubkey()),
+                    ),
+                    memo: format!(
+                        "Create stake account {} for validator {}",
+                        stake_address, identity
+                    ),
+                    identities: vec![*identity],
+                });
                 info!(
                     "Creating stake account for validator {} ({})",
                     identity, stake_address
                 );
             }
-            let action = format!(
-                "stake account busy due to no stake account: {}",
-                stake_address
-            );
+            let action = StakeAction::BusyNoStakeAccount {
+                address: stake_address,
+            };
             warn!("Busy validator {}: {}", *identity, action);
-            validator_stake_actions.insert(*identity, action);
+            validator_stake_actions.insert(*identity, action.into());
         }
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-        .failed
-        .is_empty()
-    {
+    let first_planned_transaction = plan.len();
+    let ok = process_transactions(
+        rpc_client,
+        dry_run,
+        "create validator stake accounts",
+        transactions,
+        authorized_staker,
+        plan,
+    )?;
+    link_stake_actions(&plan[first_planned_transaction..], validator_stake_actions);
+
+    if !ok {
         Err("Failed to create validator stake accounts".into())
     } else {
         Ok(())
@@ -786,25 +1098,21 @@ fn create_validator_stake_accounts(
 fn distribute_validator_stake<V>(
     rpc_client: &RpcClient,
     dry_run: bool,
-    authorized_staker: &Keypair,
+    authorized_staker: &dyn Signer,
     stake_pool_address: &Pubkey,
     stake_pool: &StakePool,
     validator_list: &ValidatorList,
     desired_validator_stake: V,
-    mut reserve_stake_balance: u64,
+    budget: RebalanceBudget,
     baseline_stake_amount: u64,
     bonus_stake_amount: u64,
     validator_stake_actions: &mut ValidatorStakeActions,
+    plan: &mut TransactionPlan,
 ) -> Result<bool, Box<dyn error::Error>>
 where
     V: IntoIterator<Item = ValidatorStake>,
 {
-    // Prioritize funding smaller stake accounts to maximize the number of accounts that will be
-    // funded with the available reserve stake.
-    let mut min_stake = vec![];
-    let mut baseline_stake = vec![];
-    let mut bonus_stake = vec![];
-
+    let mut validators = vec![];
     for validator_stake in desired_validator_stake {
         match validator_list.find(&validator_stake.vote_address) {
             None => warn!(
@@ -812,120 +1120,100 @@ where
                 &validator_stake.vote_address
             ),
             Some(validator_entry) => {
-                let list = match validator_stake.stake_state {
-                    ValidatorStakeState::None => &mut min_stake,
-                    ValidatorStakeState::Baseline => &mut baseline_stake,
-                    ValidatorStakeState::Bonus => &mut bonus_stake,
+                let desired_balance = match validator_stake.stake_state {
+                    ValidatorStakeState::None => 0,
+                    ValidatorStakeState::Baseline => baseline_stake_amount,
+                    ValidatorStakeState::Bonus => bonus_stake_amount,
                 };
-
-                list.push((validator_entry.stake_lamports, validator_stake));
+                validators.push((
+                    validator_entry.stake_lamports,
+                    desired_balance,
+                    validator_stake,
+                ));
             }
         }
     }
 
-    // Sort from lowest to highest balance
-    min_stake.sort_by_key(|k| k.0);
-    baseline_stake.sort_by_key(|k| k.0);
-    bonus_stake.sort_by_key(|k| k.0);
+    let operations = plan_rebalance(
+        &validators
+            .iter()
+            .map(|(balance, desired_balance, _)| (*balance, *desired_balance))
+            .collect::<Vec<_>>(),
+        MIN_STAKE_CHANGE_AMOUNT,
+        budget,
+    );
 
     let mut transactions = vec![];
     for (
-        balance,
-        ValidatorStake {
-            identity,
-            stake_state,
-            vote_address,
-        },
-    ) in min_stake
-        .into_iter()
-        .chain(baseline_stake)
-        .chain(bonus_stake)
+        (
+            balance,
+            desired_balance,
+            ValidatorStake {
+                identity,
+                stake_state,
+                vote_address,
+            },
+        ),
+        operation,
+    ) in validators.into_iter().zip(operations)
     {
-        let desired_balance = match stake_state {
-            ValidatorStakeState::None => 0,
-            ValidatorStakeState::Baseline => baseline_stake_amount,
-            ValidatorStakeState::Bonus => bonus_stake_amount,
-        };
-
-        #[allow(clippy::comparison_chain)]
-        let op_msg = if balance > desired_balance {
-            let amount_to_remove = balance - desired_balance;
-            if amount_to_remove < MIN_STAKE_CHANGE_AMOUNT {
-                format!("not removing {} (amount too small)", Sol(amount_to_remove))
-            } else {
-                transactions.push(Transaction::new_with_payer(
-                    &[
-                        spl_stake_pool::instruction::decrease_validator_stake_with_vote(
-                            stake_pool,
-                            stake_pool_address,
-                            &vote_address,
-                            amount_to_remove,
-                        ),
-                    ],
-                    Some(&authorized_staker.pubkey()),
-                ));
-                format!("removing {}", Sol(amount_to_remove))
+        match operation {
+            StakeOperation::Remove { amount } | StakeOperation::RemovePartial { amount, .. } => {
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &[
+                            spl_stake_pool::instruction::decrease_validator_stake_with_vote(
+                                stake_pool,
+                                stake_pool_address,
+                                &vote_address,
+                                amount,
+                            ),
+                        ],
+                        Some(&authorized_staker.pubkey()),
+                    ),
+                    memo: format!("Remove {} stake from {}", Sol(amount), identity),
+                    identities: vec![identity],
+                });
             }
-        } else if balance < desired_balance {
-            let mut amount_to_add = desired_balance - balance;
-
-            if amount_to_add < MIN_STAKE_CHANGE_AMOUNT {
-                format!("not adding {} (amount too small)", Sol(amount_to_add))
-            } else {
-                if amount_to_add > reserve_stake_balance {
-                    trace!(
-                        "note: amount_to_add > reserve_stake_balance: {} > {}",
-                        amount_to_add,
-                        reserve_stake_balance
-                    );
-                    amount_to_add = reserve_stake_balance;
-                }
-
-                if amount_to_add < MIN_STAKE_CHANGE_AMOUNT {
-                    "reserve depleted".to_string()
-                } else {
-                    reserve_stake_balance -= amount_to_add;
-                    info!("adding {} stake", Sol(amount_to_add));
-
-                    transactions.push(Transaction::new_with_payer(
+            StakeOperation::Add { amount } | StakeOperation::AddPartial { amount, .. } => {
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
                         &[
                             spl_stake_pool::instruction::increase_validator_stake_with_vote(
                                 stake_pool,
                                 stake_pool_address,
                                 &vote_address,
-                                amount_to_add,
+                                amount,
                             ),
                         ],
                         Some(&authorized_staker.pubkey()),
-                    ));
-                    format!("adding {}", Sol(amount_to_add))
-                }
+                    ),
+                    memo: format!("Add {} stake to {}", Sol(amount), identity),
+                    identities: vec![identity],
+                });
             }
-        } else {
-            "no change".to_string()
-        };
+            _ => (),
+        }
 
-        let action = format!(
-            "target stake amount: {}, current stake amount: {} - {}",
-            Sol(desired_balance),
-            Sol(balance),
-            op_msg,
-        );
+        let action = StakeAction::Adjust {
+            target: desired_balance,
+            current: balance,
+            operation,
+        };
         info!("{} ({:?}): {}", identity, stake_state, action);
-        validator_stake_actions.insert(identity, action);
+        validator_stake_actions.insert(identity, action.into());
     }
-    info!(
-        "Reserve stake available balance after updates: {}",
-        Sol(reserve_stake_balance)
-    );
 
-    let ok = if dry_run {
-        true
-    } else {
-        send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-            .failed
-            .is_empty()
-    };
+    let first_planned_transaction = plan.len();
+    let ok = process_transactions(
+        rpc_client,
+        dry_run,
+        "distribute stake",
+        transactions,
+        authorized_staker,
+        plan,
+    )?;
+    link_stake_actions(&plan[first_planned_transaction..], validator_stake_actions);
 
     if !ok {
         error!("One or more transactions failed to execute")
@@ -1122,12 +1410,45 @@ mod test {
 
         let mut stake_o_matic = new(
             &rpc_client,
-            authorized_staker,
+            Box::new(Keypair::from_bytes(&authorized_staker.to_bytes()).unwrap()),
             stake_pool.pubkey(),
             baseline_stake_amount,
+            100,
+            ReserveTarget::default(),
         )
         .unwrap();
 
+        // ===========================================================
+        info!("A dry run only simulates the planned transactions");
+        let desired_validator_stake = validators
+            .iter()
+            .map(|vap| ValidatorStake {
+                identity: vap.identity,
+                vote_address: vap.vote_address,
+                stake_state: ValidatorStakeState::None,
+            })
+            .collect::<Vec<_>>();
+        let (_, _, plan) = stake_o_matic
+            .apply(&rpc_client, true, &desired_validator_stake)
+            .unwrap();
+        // any later phase depends on the validator stake accounts, but is simulated all the same
+        assert!(plan
+            .iter()
+            .all(|planned_transaction| planned_transaction.dependent
+                == (planned_transaction.phase != "create validator stake accounts")));
+        assert_eq!(
+            plan.iter()
+                .filter(|planned_transaction| !planned_transaction.dependent)
+                .map(|planned_transaction| planned_transaction.identities.len())
+                .sum::<usize>(),
+            validators.len()
+        );
+        assert_eq!(
+            num_stake_accounts(&rpc_client, authorized_staker.pubkey()),
+            0
+        );
+        assert_reserve_account_only(MIN_STAKE_RESERVE_BALANCE + stake_rent_exemption);
+
         // ===========================================================
         info!(
             "Start with creating validator stake accounts and deposit stake, no managed stake yet"
@@ -1150,8 +1471,8 @@ mod test {
 
         let deposit_stake_address = create_stake_account(
             &rpc_client,
-            &stake_o_matic.authorized_staker,
-            &stake_o_matic.authorized_staker.pubkey(),
+            &authorized_staker,
+            &authorized_staker.pubkey(),
             total_stake_amount,
         )
         .unwrap()
@@ -1159,7 +1480,7 @@ mod test {
         let deposit_vote_address = validators.first().unwrap().vote_address;
         delegate_stake(
             &rpc_client,
-            &stake_o_matic.authorized_staker,
+            &authorized_staker,
             &deposit_stake_address,
             &deposit_vote_address,
         )
@@ -1180,7 +1501,7 @@ mod test {
         }
         assert_eq!(num_stake_accounts(&rpc_client, pool_withdraw_authority), 1);
         assert_eq!(
-            num_stake_accounts(&rpc_client, stake_o_matic.authorized_staker.pubkey()),
+            num_stake_accounts(&rpc_client, authorized_staker.pubkey()),
             validators.len() + 1
         );
         let epoch = wait_for_next_epoch(&rpc_client).unwrap();
@@ -1197,17 +1518,13 @@ mod test {
                 &stake_o_matic.stake_pool_address,
             )
             .0;
-            transfer(
-                &rpc_client,
-                &stake_o_matic.authorized_staker,
-                &stake_address,
-                30,
-            )
-            .unwrap();
+            transfer(&rpc_client, &authorized_staker, &stake_address, 30).unwrap();
         }
 
         // ===========================================================
-        stake_o_matic.epoch_update(&rpc_client).unwrap();
+        stake_o_matic
+            .epoch_update(&rpc_client, false, &mut vec![])
+            .unwrap();
         info!("Add all validators to the pool");
         stake_o_matic
             .apply(
@@ -1227,14 +1544,14 @@ mod test {
         info!("Deposit stake");
         let staker_pool_token_address = create_token_account(
             &rpc_client,
-            &stake_o_matic.authorized_staker,
+            &authorized_staker,
             &pool_mint,
-            &stake_o_matic.authorized_staker.pubkey(),
+            &authorized_staker.pubkey(),
         )
         .unwrap();
         deposit_into_stake_pool(
             &rpc_client,
-            &stake_o_matic.authorized_staker,
+            &authorized_staker,
             &stake_o_matic.stake_pool_address,
             &stake_o_matic.stake_pool,
             &deposit_vote_address,
@@ -1261,7 +1578,9 @@ mod test {
         let _epoch = wait_for_next_epoch(&rpc_client).unwrap();
 
         // ===========================================================
-        stake_o_matic.epoch_update(&rpc_client).unwrap();
+        stake_o_matic
+            .epoch_update(&rpc_client, false, &mut vec![])
+            .unwrap();
         info!("All validators to baseline");
         uniform_stake_pool_apply(
             &mut stake_o_matic,
@@ -1324,6 +1643,32 @@ mod test {
             );
         }
 
+        // the status excludes rent, and reports the bonus stake that is still activating separately
+        let status = stake_o_matic
+            .status(&rpc_client, &desired_validator_stake)
+            .unwrap();
+        for (validator, expected_sol_balance) in validators.iter().zip(&[0., 10., 110.]) {
+            let validator_status = status
+                .validators
+                .iter()
+                .find(|vs| vs.vote_address == validator.vote_address)
+                .unwrap();
+            assert_eq!(validator_status.identity, Some(validator.identity));
+            let expected_balance = sol_to_lamports(*expected_sol_balance);
+            assert!(validator_status.active_stake >= expected_balance);
+            assert!(validator_status.active_stake <= expected_balance + MIN_STAKE_ACCOUNT_BALANCE);
+        }
+        let bonus_validator_status = status
+            .validators
+            .iter()
+            .find(|vs| vs.vote_address == validators[2].vote_address)
+            .unwrap();
+        assert!(bonus_validator_status.transient_stake > 0);
+        assert_eq!(
+            bonus_validator_status.transient_stake_state.as_deref(),
+            Some("Activating")
+        );
+
         assert_eq!(
             rpc_client
                 .get_balance(&stake_o_matic.stake_pool.reserve_stake)
@@ -1368,8 +1713,25 @@ mod test {
         );
         // staker has recovered all of their SOL from stake accounts
         assert_eq!(
-            num_stake_accounts(&rpc_client, stake_o_matic.authorized_staker.pubkey()),
+            num_stake_accounts(&rpc_client, authorized_staker.pubkey()),
             0
         );
     }
+
+    #[test]
+    fn test_reserve_target() {
+        assert_eq!(
+            "5%".parse::<ReserveTarget>(),
+            Ok(ReserveTarget::Percentage(5))
+        );
+        assert_eq!(
+            "1.5".parse::<ReserveTarget>(),
+            Ok(ReserveTarget::Lamports(sol_to_lamports(1.5)))
+        );
+        assert!("101%".parse::<ReserveTarget>().is_err());
+        assert!("five".parse::<ReserveTarget>().is_err());
+
+        assert_eq!(ReserveTarget::Percentage(5).lamports(1_000), 50);
+        assert_eq!(ReserveTarget::Lamports(20).lamports(1_000), 20);
+    }
 }
@@ -1,7 +1,9 @@
 use {
     crate::{
         generic_stake_pool::*,
-        rpc_client_utils::{get_all_stake, send_and_confirm_transactions},
+        notes::{EpochNote, StakeAction, StakeOperation, ValidatorStakeAction},
+        rebalance::{deferred_stake_note, max_stake_churn, plan_rebalance, RebalanceBudget},
+        rpc_client_utils::{get_all_stake, get_stake_activation_if_exists, DescribedTransaction},
     },
     log::*,
     solana_client::{rpc_client::RpcClient, rpc_response::StakeActivationState},
@@ -9,7 +11,7 @@ use {
         account::Account,
         native_token::{Sol, LAMPORTS_PER_SOL},
         pubkey::Pubkey,
-        signature::{Keypair, Signer},
+        signature::Signer,
         transaction::Transaction,
     },
     solana_stake_program::{stake_instruction, stake_state::StakeState},
@@ -28,16 +30,18 @@ const MIN_STAKE_CHANGE_AMOUNT: u64 = MIN_STAKE_ACCOUNT_BALANCE;
 
 #[derive(Debug)]
 pub struct StakePool {
-    authorized_staker: Keypair,
+    authorized_staker: Box<dyn Signer>,
     baseline_stake_amount: u64,
+    max_churn_percentage: usize,
     reserve_stake_address: Pubkey,
     min_reserve_stake_balance: u64,
 }
 
 pub fn new(
     _rpc_client: &RpcClient,
-    authorized_staker: Keypair,
+    authorized_staker: Box<dyn Signer>,
     baseline_stake_amount: u64,
+    max_churn_percentage: usize,
     reserve_stake_address: Pubkey,
     min_reserve_stake_balance: u64,
 ) -> Result<StakePool, Box<dyn error::Error>> {
@@ -60,6 +64,7 @@ pub fn new(
     Ok(StakePool {
         authorized_staker,
         baseline_stake_amount,
+        max_churn_percentage,
         reserve_stake_address,
         min_reserve_stake_balance,
     })
@@ -97,8 +102,10 @@ impl GenericStakePool for StakePool {
         rpc_client: &RpcClient,
         dry_run: bool,
         desired_validator_stake: &[ValidatorStake],
-    ) -> Result<(EpochStakeNotes, ValidatorStakeActions), Box<dyn error::Error>> {
+    ) -> Result<(EpochStakeNotes, ValidatorStakeActions, TransactionPlan), Box<dyn error::Error>>
+    {
         let mut validator_stake_actions = HashMap::default();
+        let mut plan = vec![];
 
         let mut inuse_stake_addresses = HashSet::default();
         inuse_stake_addresses.insert(self.reserve_stake_address);
@@ -134,28 +141,34 @@ impl GenericStakePool for StakePool {
         info!("Merge orphaned stake into the reserve");
         merge_orphaned_stake_accounts(
             rpc_client,
-            &self.authorized_staker,
+            dry_run,
+            &*self.authorized_staker,
             &all_stake_addresses - &inuse_stake_addresses,
             self.reserve_stake_address,
+            &mut plan,
         )?;
 
         info!("Merge transient stake back into either the reserve or validator stake");
         merge_transient_stake_accounts(
             rpc_client,
-            &self.authorized_staker,
+            dry_run,
+            &*self.authorized_staker,
             desired_validator_stake,
             self.reserve_stake_address,
             &mut validator_stake_actions,
+            &mut plan,
         )?;
 
         info!("Create validator stake accounts if needed");
         create_validator_stake_accounts(
             rpc_client,
-            &self.authorized_staker,
+            dry_run,
+            &*self.authorized_staker,
             desired_validator_stake,
             self.reserve_stake_address,
             self.min_reserve_stake_balance,
             &mut validator_stake_actions,
+            &mut plan,
         )?;
 
         // `total_stake_amount` excludes the amount that always remains in the reserve account
@@ -212,14 +225,24 @@ impl GenericStakePool for StakePool {
             Sol(reserve_stake_balance)
         );
 
-        let notes = vec![
-            format!(
-                "Stake pool size: {} (available for delegation: {})",
-                Sol(total_stake_amount),
-                Sol(reserve_stake_balance)
-            ),
-            format!("Baseline stake amount: {}", Sol(self.baseline_stake_amount)),
-            format!("Bonus stake amount: {}", Sol(bonus_stake_amount)),
+        let max_churn = max_stake_churn(total_stake_amount, self.max_churn_percentage);
+        info!("Maximum stake churn: {}", Sol(max_churn));
+
+        let mut notes = vec![
+            EpochNote::StakePoolV0Size {
+                total: total_stake_amount,
+                available_for_delegation: reserve_stake_balance,
+            },
+            EpochNote::BaselineStakeAmount {
+                lamports: self.baseline_stake_amount,
+            },
+            EpochNote::BonusStakeAmount {
+                lamports: bonus_stake_amount,
+            },
+            EpochNote::MaximumStakeChurn {
+                percentage: self.max_churn_percentage,
+                lamports: max_churn,
+            },
         ];
 
         let busy_validators = validator_stake_actions
@@ -229,18 +252,135 @@ impl GenericStakePool for StakePool {
         distribute_validator_stake(
             rpc_client,
             dry_run,
-            &self.authorized_staker,
+            &*self.authorized_staker,
             desired_validator_stake
                 .iter()
                 .filter(|vs| !busy_validators.contains(&vs.identity))
                 .cloned(),
             self.reserve_stake_address,
-            reserve_stake_balance,
+            RebalanceBudget {
+                reserve: reserve_stake_balance,
+                churn: max_churn,
+            },
             self.baseline_stake_amount,
             bonus_stake_amount,
             &mut validator_stake_actions,
+            &mut plan,
         )?;
-        Ok((notes, validator_stake_actions))
+        notes.extend(deferred_stake_note(&validator_stake_actions));
+        Ok((notes, validator_stake_actions, plan))
+    }
+
+    fn emergency_destake(
+        &mut self,
+        rpc_client: &RpcClient,
+        dry_run: bool,
+        identity: Pubkey,
+        vote_address: Pubkey,
+    ) -> Result<(ValidatorStakeAction, TransactionPlan), Box<dyn error::Error>> {
+        let mut validator_stake_actions = HashMap::default();
+        let mut plan = vec![];
+
+        let stake_address = validator_stake_address(self.authorized_staker.pubkey(), vote_address);
+        if rpc_client.get_balance(&stake_address)? == 0 {
+            return Err(format!("Validator {} has no stake account", identity).into());
+        }
+
+        // Stake can't be split into a transient stake account that is still in use
+        let transient_stake_address =
+            validator_transient_stake_address(self.authorized_staker.pubkey(), vote_address);
+        if rpc_client.get_balance(&transient_stake_address)? > 0 {
+            let action = StakeAction::BusyTransientStakeAccount {
+                address: transient_stake_address,
+            };
+            return Ok((action.into(), plan));
+        }
+
+        distribute_validator_stake(
+            rpc_client,
+            dry_run,
+            &*self.authorized_staker,
+            vec![ValidatorStake {
+                identity,
+                vote_address,
+                stake_state: ValidatorStakeState::None,
+            }],
+            self.reserve_stake_address,
+            RebalanceBudget {
+                reserve: 0,
+                churn: u64::MAX,
+            },
+            self.baseline_stake_amount,
+            0,
+            &mut validator_stake_actions,
+            &mut plan,
+        )?;
+
+        let validator_stake_action = validator_stake_actions
+            .remove(&identity)
+            .ok_or_else(|| format!("Validator {} is not in the stake pool", identity))?;
+        Ok((validator_stake_action, plan))
+    }
+
+    fn status(
+        &self,
+        rpc_client: &RpcClient,
+        desired_validator_stake: &[ValidatorStake],
+    ) -> Result<StakePoolStatus, Box<dyn error::Error>> {
+        let mut inuse_stake_addresses = HashSet::default();
+        inuse_stake_addresses.insert(self.reserve_stake_address);
+
+        let mut validators = vec![];
+        for ValidatorStake {
+            identity,
+            vote_address,
+            ..
+        } in desired_validator_stake
+        {
+            let stake_address =
+                validator_stake_address(self.authorized_staker.pubkey(), *vote_address);
+            let transient_stake_address =
+                validator_transient_stake_address(self.authorized_staker.pubkey(), *vote_address);
+            inuse_stake_addresses.insert(stake_address);
+            inuse_stake_addresses.insert(transient_stake_address);
+
+            let stake_activation = get_stake_activation_if_exists(rpc_client, stake_address)?;
+            let transient_stake_activation =
+                get_stake_activation_if_exists(rpc_client, transient_stake_address)?;
+            if stake_activation.is_none() && transient_stake_activation.is_none() {
+                continue;
+            }
+
+            validators.push(ValidatorStakeStatus {
+                identity: Some(*identity),
+                vote_address: *vote_address,
+                stake_address,
+                active_stake: stake_activation
+                    .map(|activation| activation.active)
+                    .unwrap_or_default(),
+                transient_stake: transient_stake_activation
+                    .as_ref()
+                    .map(|activation| activation.active + activation.inactive)
+                    .unwrap_or_default(),
+                transient_stake_address: transient_stake_activation
+                    .as_ref()
+                    .map(|_| transient_stake_address),
+                transient_stake_state: transient_stake_activation
+                    .map(|activation| format!("{:?}", activation.state)),
+                ..ValidatorStakeStatus::default()
+            });
+        }
+
+        let (all_stake_addresses, _) = get_all_stake(rpc_client, self.authorized_staker.pubkey())?;
+
+        Ok(StakePoolStatus {
+            reserve_stake_address: self.reserve_stake_address,
+            reserve_balance: rpc_client.get_balance(&self.reserve_stake_address)?,
+            validators,
+            orphaned_stake_addresses: (&all_stake_addresses - &inuse_stake_addresses)
+                .into_iter()
+                .collect(),
+        })
     }
 }
 
@@ -271,9 +411,11 @@ fn get_available_stake_balance(
 
 fn merge_orphaned_stake_accounts(
     rpc_client: &RpcClient,
-    authorized_staker: &Keypair,
+    dry_run: bool,
+    authorized_staker: &dyn Signer,
     source_stake_addresses: HashSet<Pubkey>,
     reserve_stake_address: Pubkey,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
     let mut transactions = vec![];
     for stake_address in source_stake_addresses {
@@ -289,24 +431,32 @@ fn merge_orphaned_stake_accounts(
         match stake_activation.state {
             StakeActivationState::Activating | StakeActivationState::Deactivating => {}
             StakeActivationState::Active => {
-                transactions.push(Transaction::new_with_payer(
-                    &[stake_instruction::deactivate_stake(
-                        &stake_address,
-                        &authorized_staker.pubkey(),
-                    )],
-                    Some(&authorized_staker.pubkey()),
-                ));
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &[stake_instruction::deactivate_stake(
+                            &stake_address,
+                            &authorized_staker.pubkey(),
+                        )],
+                        Some(&authorized_staker.pubkey()),
+                    ),
+                    memo: format!("Deactivate orphaned stake {}", stake_address),
+                    identities: vec![],
+                });
                 debug!("Deactivating stake {}", stake_address);
             }
             StakeActivationState::Inactive => {
-                transactions.push(Transaction::new_with_payer(
-                    &stake_instruction::merge(
-                        &reserve_stake_address,
-                        &stake_address,
-                        &authorized_staker.pubkey(),
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &stake_instruction::merge(
+                            &reserve_stake_address,
+                            &stake_address,
+                            &authorized_staker.pubkey(),
+                        ),
+                        Some(&authorized_staker.pubkey()),
                     ),
-                    Some(&authorized_staker.pubkey()),
-                ));
+                    memo: format!("Merge orphaned stake {} into the reserve", stake_address),
+                    identities: vec![],
+                });
 
                 debug!(
                     "Merging orphaned stake, {}, into reserve {}",
@@ -316,10 +466,14 @@ fn merge_orphaned_stake_accounts(
         }
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-        .failed
-        .is_empty()
-    {
+    if !process_transactions(
+        rpc_client,
+        dry_run,
+        "merge orphaned stake",
+        transactions,
+        authorized_staker,
+        plan,
+    )? {
         Err("Failed to merge orphaned stake accounts".into())
     } else {
         Ok(())
@@ -328,10 +482,12 @@ fn merge_orphaned_stake_accounts(
 
 fn merge_transient_stake_accounts(
     rpc_client: &RpcClient,
-    authorized_staker: &Keypair,
+    dry_run: bool,
+    authorized_staker: &dyn Signer,
     desired_validator_stake: &[ValidatorStake],
     reserve_stake_address: Pubkey,
     validator_stake_actions: &mut ValidatorStakeActions,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
     let mut transactions = vec![];
     for ValidatorStake {
@@ -360,20 +516,18 @@ fn merge_transient_stake_accounts(
 
             match transient_stake_activation.state {
                 StakeActivationState::Activating => {
-                    let action = format!(
-                        "stake account busy due to transient stake activation: {:?}",
-                        transient_stake_address,
-                    );
+                    let action = StakeAction::BusyTransientStakeActivation {
+                        address: transient_stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
                 StakeActivationState::Deactivating => {
-                    let action = format!(
-                        "stake account busy due to transient stake deactivation: {}",
-                        transient_stake_address,
-                    );
+                    let action = StakeAction::BusyTransientStakeDeactivation {
+                        address: transient_stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
                 StakeActivationState::Active => {
                     let stake_account = rpc_client
@@ -385,43 +539,54 @@ fn merge_transient_stake_accounts(
                         &stake_account,
                         &transient_stake_account,
                     )? {
-                        transactions.push(Transaction::new_with_payer(
-                            &stake_instruction::merge(
-                                &stake_address,
-                                &transient_stake_address,
-                                &authorized_staker.pubkey(),
+                        transactions.push(DescribedTransaction {
+                            transaction: Transaction::new_with_payer(
+                                &stake_instruction::merge(
+                                    &stake_address,
+                                    &transient_stake_address,
+                                    &authorized_staker.pubkey(),
+                                ),
+                                Some(&authorized_staker.pubkey()),
                             ),
-                            Some(&authorized_staker.pubkey()),
-                        ));
+                            memo: format!("Merge active transient stake for {}", identity),
+                            identities: vec![*identity],
+                        });
                         debug!("Merging active transient stake for {}", identity);
                     } else {
-                        let action = format!(
-                            "stake account busy due to credits observed mismatch with transient stake account {}",
-                            transient_stake_address
-                        );
+                        let action = StakeAction::BusyCreditsObservedMismatch {
+                            transient_address: transient_stake_address,
+                        };
                         warn!("Busy validator {}: {}", *identity, action);
-                        validator_stake_actions.insert(*identity, action);
+                        validator_stake_actions.insert(*identity, action.into());
                     }
                 }
                 StakeActivationState::Inactive => {
-                    transactions.push(Transaction::new_with_payer(
-                        &stake_instruction::merge(
-                            &reserve_stake_address,
-                            &transient_stake_address,
-                            &authorized_staker.pubkey(),
+                    transactions.push(DescribedTransaction {
+                        transaction: Transaction::new_with_payer(
+                            &stake_instruction::merge(
+                                &reserve_stake_address,
+                                &transient_stake_address,
+                                &authorized_staker.pubkey(),
+                            ),
+                            Some(&authorized_staker.pubkey()),
                         ),
-                        Some(&authorized_staker.pubkey()),
-                    ));
+                        memo: format!("Merge inactive transient stake for {}", identity),
+                        identities: vec![*identity],
+                    });
                     debug!("Merging inactive transient stake for {}", identity);
                 }
             }
         }
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-        .failed
-        .is_empty()
-    {
+    if !process_transactions(
+        rpc_client,
+        dry_run,
+        "merge transient stake",
+        transactions,
+        authorized_staker,
+        plan,
+    )? {
         Err("Failed to merge transient stake".into())
     } else {
         Ok(())
@@ -461,13 +626,16 @@ fn stake_accounts_have_same_credits_observed(
     Ok(false)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_validator_stake_accounts(
     rpc_client: &RpcClient,
-    authorized_staker: &Keypair,
+    dry_run: bool,
+    authorized_staker: &dyn Signer,
     desired_validator_stake: &[ValidatorStake],
     reserve_stake_address: Pubkey,
     min_reserve_stake_balance: u64,
     validator_stake_actions: &mut ValidatorStakeActions,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>> {
     let mut reserve_stake_balance =
         get_available_stake_balance(rpc_client, reserve_stake_address, min_reserve_stake_balance)
@@ -507,40 +675,46 @@ fn create_validator_stake_accounts(
 
             match stake_activation.state {
                 StakeActivationState::Activating => {
-                    let action = format!(
-                        "stake account busy due to stake activation of {}",
-                        stake_address
-                    );
+                    let action = StakeAction::BusyStakeActivation {
+                        address: stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
                 StakeActivationState::Deactivating => {
-                    let action = format!(
-                        "stake account busy due to stake deactivation of {}",
-                        stake_address
-                    );
+                    let action = StakeAction::BusyStakeDeactivation {
+                        address: stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
                 StakeActivationState::Active => {}
                 StakeActivationState::Inactive => {
-                    let action =
-                        format!("stake account busy due to inactive stake {}", stake_address);
+                    let action = StakeAction::BusyInactiveStake {
+                        address: stake_address,
+                    };
                     warn!("Busy validator {}: {}", *identity, action);
 
-                    transactions.push(Transaction::new_with_payer(
-                        &[stake_instruction::delegate_stake(
-                            &stake_address,
-                            &authorized_staker.pubkey(),
-                            vote_address,
-                        )],
-                        Some(&authorized_staker.pubkey()),
-                    ));
+                    transactions.push(DescribedTransaction {
+                        transaction: Transaction::new_with_payer(
+                            &[stake_instruction::delegate_stake(
+                                &stake_address,
+                                &authorized_staker.pubkey(),
+                                vote_address,
+                            )],
+                            Some(&authorized_staker.pubkey()),
+                        ),
+                        memo: format!(
+                            "Activate stake account {} for validator {}",
+                            stake_address, identity
+                        ),
+                        identities: vec![*identity],
+                    });
                     debug!(
                         "Activating stake account for validator {} ({})",
                         identity, stake_address
                     );
-                    validator_stake_actions.insert(*identity, action);
+                    validator_stake_actions.insert(*identity, action.into());
                 }
             }
         } else {
@@ -551,10 +725,9 @@ fn create_validator_stake_accounts(
                     Sol(MIN_STAKE_ACCOUNT_BALANCE), Sol(reserve_stake_balance)
                 );
 
-                format!(
-                    "insufficient funds in reserve account to create stake account {}",
-                    stake_address
-                )
+                StakeAction::InsufficientReserveForStakeAccount {
+                    address: stake_address,
+                }
             } else {
                 // Create a stake account for the validator
                 reserve_stake_balance -= MIN_STAKE_ACCOUNT_BALANCE;
@@ -573,21 +746,38 @@ fn create_validator_stake_accounts(
                     vote_address,
                 ));
 
-                transactions.push(Transaction::new_with_payer(
-                    &instructions,
-                    Some(&authorized_staker.pubkey()),
-                ));
-                format!("creating new stake account {}", stake_address)
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &instructions,
+                        Some(&authorized_staker.pubkey()),
+                    ),
+                    memo: format!(
+                        "Create stake account {} for validator {}",
+                        stake_address, identity
+                    ),
+                    identities: vec![*identity],
+                });
+                StakeAction::CreatingStakeAccount {
+                    address: stake_address,
+                }
             };
             warn!("Busy validator {}: {}", *identity, action);
-            validator_stake_actions.insert(*identity, action);
+            validator_stake_actions.insert(*identity, action.into());
         }
     }
 
-    if !send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-        .failed
-        .is_empty()
-    {
+    let first_planned_transaction = plan.len();
+    let ok = process_transactions(
+        rpc_client,
+        dry_run,
+        "create validator stake accounts",
+        transactions,
+        authorized_staker,
+        plan,
+    )?;
+    link_stake_actions(&plan[first_planned_transaction..], validator_stake_actions);
+
+    if !ok {
         Err("Failed to create validator stake accounts".into())
     } else {
         Ok(())
@@ -598,23 +788,19 @@ fn create_validator_stake_accounts(
 fn distribute_validator_stake<V>(
     rpc_client: &RpcClient,
     dry_run: bool,
-    authorized_staker: &Keypair,
+    authorized_staker: &dyn Signer,
     desired_validator_stake: V,
     reserve_stake_address: Pubkey,
-    mut reserve_stake_balance: u64,
+    budget: RebalanceBudget,
     baseline_stake_amount: u64,
     bonus_stake_amount: u64,
     validator_stake_actions: &mut ValidatorStakeActions,
+    plan: &mut TransactionPlan,
 ) -> Result<(), Box<dyn error::Error>>
 where
     V: IntoIterator<Item = ValidatorStake>,
 {
-    // Prioritize funding smaller stake accounts to maximize the number of accounts that will be
-    // funded with the available reserve stake.
-    let mut min_stake = vec![];
-    let mut baseline_stake = vec![];
-    let mut bonus_stake = vec![];
-
+    let mut validators = vec![];
     for validator_stake in desired_validator_stake {
         let stake_address =
             validator_stake_address(authorized_staker.pubkey(), validator_stake.vote_address);
@@ -637,56 +823,53 @@ where
                 )
             })?;
 
-        let list = match validator_stake.stake_state {
-            ValidatorStakeState::None => &mut min_stake,
-            ValidatorStakeState::Baseline => &mut baseline_stake,
-            ValidatorStakeState::Bonus => &mut bonus_stake,
+        let desired_balance = match validator_stake.stake_state {
+            ValidatorStakeState::None => MIN_STAKE_ACCOUNT_BALANCE,
+            ValidatorStakeState::Baseline => baseline_stake_amount,
+            ValidatorStakeState::Bonus => bonus_stake_amount,
         };
-        list.push((
+        validators.push((
             balance,
+            desired_balance,
             stake_address,
             transient_stake_address,
             validator_stake,
         ));
     }
 
-    // Sort from lowest to highest balance
-    min_stake.sort_by_key(|k| k.0);
-    baseline_stake.sort_by_key(|k| k.0);
-    bonus_stake.sort_by_key(|k| k.0);
+    let operations = plan_rebalance(
+        &validators
+            .iter()
+            .map(|(balance, desired_balance, ..)| (*balance, *desired_balance))
+            .collect::<Vec<_>>(),
+        MIN_STAKE_CHANGE_AMOUNT,
+        budget,
+    );
 
     let mut transactions = vec![];
     for (
-        balance,
-        stake_address,
-        transient_stake_address,
-        ValidatorStake {
-            identity,
-            stake_state,
-            vote_address,
-        },
-    ) in min_stake
-        .into_iter()
-        .chain(baseline_stake)
-        .chain(bonus_stake)
+        (
+            balance,
+            desired_balance,
+            stake_address,
+            transient_stake_address,
+            ValidatorStake {
+                identity,
+                stake_state,
+                vote_address,
+            },
+        ),
+        operation,
+    ) in validators.into_iter().zip(operations)
     {
-        let desired_balance = match stake_state {
-            ValidatorStakeState::None => MIN_STAKE_ACCOUNT_BALANCE,
-            ValidatorStakeState::Baseline => baseline_stake_amount,
-            ValidatorStakeState::Bonus => bonus_stake_amount,
-        };
         let transient_stake_address_seed = validator_transient_stake_address_seed(vote_address);
 
-        #[allow(clippy::comparison_chain)]
-        let op_msg = if balance > desired_balance {
-            let amount_to_remove = balance - desired_balance;
-            if amount_to_remove < MIN_STAKE_CHANGE_AMOUNT {
-                format!("not removing {} (amount too small)", Sol(amount_to_remove))
-            } else {
+        match operation {
+            StakeOperation::Remove { amount } | StakeOperation::RemovePartial { amount, .. } => {
                 let mut instructions = stake_instruction::split_with_seed(
                     &stake_address,
                     &authorized_staker.pubkey(),
-                    amount_to_remove,
+                    amount,
                     &transient_stake_address,
                     &authorized_staker.pubkey(),
                     &transient_stake_address_seed,
@@ -696,84 +879,67 @@ where
                     &authorized_staker.pubkey(),
                 ));
 
-                transactions.push(Transaction::new_with_payer(
-                    &instructions,
-                    Some(&authorized_staker.pubkey()),
-                ));
-                format!("removing {}", Sol(amount_to_remove))
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
+                        &instructions,
+                        Some(&authorized_staker.pubkey()),
+                    ),
+                    memo: format!("Remove {} stake from {}", Sol(amount), identity),
+                    identities: vec![identity],
+                });
             }
-        } else if balance < desired_balance {
-            let mut amount_to_add = desired_balance - balance;
-
-            if amount_to_add < MIN_STAKE_CHANGE_AMOUNT {
-                format!("not adding {} (amount too small)", Sol(amount_to_add))
-            } else {
-                if amount_to_add > reserve_stake_balance {
-                    trace!(
-                        "note: amount_to_add > reserve_stake_balance: {} > {}",
-                        amount_to_add,
-                        reserve_stake_balance
-                    );
-                    amount_to_add = reserve_stake_balance;
-                }
-
-                if amount_to_add < MIN_STAKE_CHANGE_AMOUNT {
-                    "reserve depleted".to_string()
-                } else {
-                    reserve_stake_balance -= amount_to_add;
-
-                    let mut instructions = stake_instruction::split_with_seed(
-                        &reserve_stake_address,
-                        &authorized_staker.pubkey(),
-                        amount_to_add,
-                        &transient_stake_address,
-                        &authorized_staker.pubkey(),
-                        &transient_stake_address_seed,
-                    );
-                    instructions.push(stake_instruction::delegate_stake(
-                        &transient_stake_address,
-                        &authorized_staker.pubkey(),
-                        &vote_address,
-                    ));
+            StakeOperation::Add { amount } | StakeOperation::AddPartial { amount, .. } => {
+                let mut instructions = stake_instruction::split_with_seed(
+                    &reserve_stake_address,
+                    &authorized_staker.pubkey(),
+                    amount,
+                    &transient_stake_address,
+                    &authorized_staker.pubkey(),
+                    &transient_stake_address_seed,
+                );
+                instructions.push(stake_instruction::delegate_stake(
+                    &transient_stake_address,
+                    &authorized_staker.pubkey(),
+                    &vote_address,
+                ));
 
-                    transactions.push(Transaction::new_with_payer(
+                transactions.push(DescribedTransaction {
+                    transaction: Transaction::new_with_payer(
                         &instructions,
                         Some(&authorized_staker.pubkey()),
-                    ));
-                    format!("adding {}", Sol(amount_to_add))
-                }
+                    ),
+                    memo: format!("Add {} stake to {}", Sol(amount), identity),
+                    identities: vec![identity],
+                });
             }
-        } else {
-            "no change".to_string()
-        };
+            _ => (),
+        }
 
-        let action = format!(
-            "target stake amount: {}, current stake amount: {} - {}",
-            Sol(desired_balance),
-            Sol(balance),
-            op_msg,
-        );
+        let action = StakeAction::Adjust {
+            target: desired_balance,
+            current: balance,
+            operation,
+        };
         info!("{} ({:?}): {}", identity, stake_state, action);
-        validator_stake_actions.insert(identity, action);
+        validator_stake_actions.insert(identity, action.into());
     }
-    info!(
-        "Reserve stake available balance after updates: {}",
-        Sol(reserve_stake_balance)
-    );
 
-    let ok = if dry_run {
-        true
-    } else {
-        send_and_confirm_transactions(rpc_client, false, transactions, authorized_staker)?
-            .failed
-            .is_empty()
-    };
+    let first_planned_transaction = plan.len();
+    let ok = process_transactions(
+        rpc_client,
+        dry_run,
+        "distribute stake",
+        transactions,
+        authorized_staker,
+        plan,
+    )?;
+    link_stake_actions(&plan[first_planned_transaction..], validator_stake_actions);
 
+    // The outcome of each transaction is recorded in its validator's stake action
     if !ok {
-        Err("One or more transactions failed to execute".into())
-    } else {
-        Ok(())
+        error!("One or more transactions failed to execute");
     }
+    Ok(())
 }
 
 #[cfg(test)]
@@ -785,12 +951,12 @@ mod test {
             clock::Epoch,
             epoch_schedule::{EpochSchedule, MINIMUM_SLOTS_PER_EPOCH},
             native_token::sol_to_lamports,
-            signature::{Keypair, Signer},
+            signature::Signer,
         },
         solana_validator::test_validator::*,
     };
 
-    fn num_stake_accounts(rpc_client: &RpcClient, authorized_staker: &Keypair) -> usize {
+    fn num_stake_accounts(rpc_client: &RpcClient, authorized_staker: &dyn Signer) -> usize {
         get_all_stake(&rpc_client, authorized_staker.pubkey())
             .unwrap()
             .0
@@ -828,7 +994,7 @@ mod test {
             .unwrap();
 
         assert_eq!(
-            num_stake_accounts(rpc_client, &stake_pool.authorized_staker),
+            num_stake_accounts(rpc_client, &*stake_pool.authorized_staker),
             1 + 2 * validators.len()
         );
         let _epoch = wait_for_next_epoch(&rpc_client).unwrap();
@@ -837,7 +1003,7 @@ mod test {
             .unwrap();
 
         assert_eq!(
-            num_stake_accounts(rpc_client, &stake_pool.authorized_staker),
+            num_stake_accounts(rpc_client, &*stake_pool.authorized_staker),
             1 + validators.len()
         );
         assert_eq!(
@@ -933,8 +1099,9 @@ mod test {
 
         let mut stake_pool = new(
             &rpc_client,
-            authorized_staker,
+            Box::new(authorized_staker),
             baseline_stake_amount,
+            100,
             reserve_stake_address,
             min_reserve_stake_balance,
         )
@@ -978,7 +1145,7 @@ mod test {
             );
         }
         assert_eq!(
-            num_stake_accounts(&rpc_client, &stake_pool.authorized_staker),
+            num_stake_accounts(&rpc_client, &*stake_pool.authorized_staker),
             1 + validators.len()
         );
         let epoch = wait_for_next_epoch(&rpc_client).unwrap();
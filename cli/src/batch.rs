@@ -0,0 +1,190 @@
+use {
+    registry_program::state::RejectionCode,
+    serde::{de, de::DeserializeOwned, Deserialize, Deserializer},
+    solana_sdk::{
+        instruction::Instruction, message::Message, packet::PACKET_DATA_SIZE, pubkey::Pubkey,
+    },
+    std::{error, fs::File, mem, path::Path, str::FromStr},
+};
+
+/// Upper bound on the instructions packed into one transaction, which keeps a transaction of
+/// `import` instructions within the compute budget
+const MAX_INSTRUCTIONS_PER_TRANSACTION: usize = 5;
+
+/// Values of `admin reject --reason` and the `reason` field of a batch file
+pub const REJECTION_CODE_NAMES: &[&str] = &[
+    "unspecified",
+    "ineligible",
+    "insufficient-testnet-participation",
+    "duplicate-registration",
+    "other",
+];
+
+pub fn parse_rejection_code(name: &str) -> Option<RejectionCode> {
+    match name {
+        "unspecified" => Some(RejectionCode::Unspecified),
+        "ineligible" => Some(RejectionCode::Ineligible),
+        "insufficient-testnet-participation" => {
+            Some(RejectionCode::InsufficientTestnetParticipation)
+        }
+        "duplicate-registration" => Some(RejectionCode::DuplicateRegistration),
+        "other" => Some(RejectionCode::Other),
+        _ => None,
+    }
+}
+
+/// Entry of an `admin approve --from-file` or `admin reject --from-file` batch file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParticipantEntry {
+    /// Participant address, or its mainnet or testnet validator identity
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub participant: Pubkey,
+
+    /// Rejection reason, overriding `--reason`. Only valid for `admin reject`
+    #[serde(default, deserialize_with = "deserialize_rejection_code")]
+    pub reason: Option<RejectionCode>,
+
+    /// Rejection message, overriding `--message`. Only valid for `admin reject`
+    pub message: Option<String>,
+}
+
+/// Entry of an `admin import --from-file` batch file
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportEntry {
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub mainnet: Pubkey,
+    #[serde(deserialize_with = "deserialize_pubkey")]
+    pub testnet: Pubkey,
+}
+
+/// Outcome of a batch entry
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchResult {
+    /// The entry was not sent, for the given reason
+    Skipped(String),
+    /// The transaction carrying the entry's instruction succeeded
+    Confirmed,
+    /// The transaction carrying the entry's instruction failed, with the given error
+    Failed(String),
+}
+
+fn deserialize_pubkey<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+    let pubkey = String::deserialize(deserializer)?;
+    Pubkey::from_str(&pubkey).map_err(|err| de::Error::custom(format!("{}: {}", pubkey, err)))
+}
+
+fn deserialize_rejection_code<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<RejectionCode>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    parse_rejection_code(&name)
+        .map(Some)
+        .ok_or_else(|| de::Error::unknown_variant(&name, REJECTION_CODE_NAMES))
+}
+
+/// Read a YAML list of batch entries
+pub fn read_batch_file<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, Box<dyn error::Error>> {
+    let file =
+        File::open(path).map_err(|err| format!("Unable to open {}: {}", path.display(), err))?;
+    serde_yaml::from_reader(file)
+        .map_err(|err| format!("Invalid batch file {}: {}", path.display(), err).into())
+}
+
+/// Serialized size of a transaction for `message`, including its signatures
+fn transaction_size(message: &Message) -> usize {
+    // A signature count below 128 is encoded as a single byte
+    1 + message.header.num_required_signatures as usize * 64 + message.serialize().len()
+}
+
+/// Pack instructions, in order, into as few batches as fit in a transaction
+pub fn pack_instructions(instructions: Vec<Instruction>, payer: &Pubkey) -> Vec<Vec<Instruction>> {
+    let mut batches = vec![];
+    let mut batch: Vec<Instruction> = vec![];
+    for instruction in instructions {
+        batch.push(instruction);
+        if batch.len() > 1
+            && (batch.len() > MAX_INSTRUCTIONS_PER_TRANSACTION
+                || transaction_size(&Message::new(&batch, Some(payer))) > PACKET_DATA_SIZE)
+        {
+            let instruction = batch.pop().unwrap();
+            batches.push(mem::replace(&mut batch, vec![instruction]));
+        }
+    }
+    if !batch.is_empty() {
+        batches.push(batch);
+    }
+    batches
+}
+
+#[cfg(test)]
+mod test {
+    use {super::*, registry_program::state::RejectionReason};
+
+    #[test]
+    fn test_parse_batch_entries() {
+        let participant = Pubkey::new_unique();
+        let entries: Vec<ParticipantEntry> = serde_yaml::from_str(&format!(
+            "
+- participant: {participant}
+- participant: {participant}
+  reason: insufficient-testnet-participation
+  message: fewer than 10 testnet epochs
+",
+            participant = participant
+        ))
+        .unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ParticipantEntry {
+                    participant,
+                    reason: None,
+                    message: None,
+                },
+                ParticipantEntry {
+                    participant,
+                    reason: Some(RejectionCode::InsufficientTestnetParticipation),
+                    message: Some("fewer than 10 testnet epochs".to_string()),
+                },
+            ]
+        );
+
+        assert!(serde_yaml::from_str::<Vec<ParticipantEntry>>(&format!(
+            "- participant: {}\n  reason: late",
+            participant
+        ))
+        .is_err());
+        assert!(serde_yaml::from_str::<Vec<ImportEntry>>(&format!(
+            "- mainnet: {}\n  testnet: 1nvalid",
+            participant
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_pack_instructions() {
+        let payer = Pubkey::new_unique();
+        let admin = Pubkey::new_unique();
+        let instructions = (0..23)
+            .map(|_| {
+                registry_program::instruction::reject(
+                    Pubkey::new_unique(),
+                    &[admin],
+                    RejectionReason::new(RejectionCode::Other, &"x".repeat(50)).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let batches = pack_instructions(instructions.clone(), &payer);
+        assert!(batches.len() > 1);
+        assert_eq!(batches.concat(), instructions);
+        for batch in batches {
+            assert!(batch.len() <= MAX_INSTRUCTIONS_PER_TRANSACTION);
+            assert!(transaction_size(&Message::new(&batch, Some(&payer))) <= PACKET_DATA_SIZE);
+        }
+
+        assert!(pack_instructions(vec![], &payer).is_empty());
+    }
+}
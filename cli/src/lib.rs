@@ -1,42 +1,62 @@
 use {
-    registry_program::state::{Participant, ParticipantState},
+    registry_program::{
+        event::StateTransition,
+        state::{unpack_participant, Participant, ParticipantState, ParticipantV2, RegistryConfig},
+    },
+    serde::{Deserialize, Serialize},
     solana_client::{
-        rpc_client::RpcClient,
+        rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
         rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
         rpc_filter::*,
     },
-    solana_sdk::{program_pack::Pack, pubkey::Pubkey},
-    std::collections::HashMap,
+    solana_sdk::{
+        clock::{Epoch, Slot, UnixTimestamp},
+        native_token::Sol,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::Signature,
+    },
+    solana_transaction_status::UiTransactionEncoding,
+    std::{collections::HashMap, fmt, fs, mem, path::Path, str::FromStr},
 };
 
+/// Fetch participants in both the `Participant` and `ParticipantV2` account layouts.
+/// Suspensions that have lapsed by the current epoch are returned as `ParticipantState::Approved`,
+/// and a `ParticipantState::Suspended` filter matches any `until_epoch`
 pub fn get_participants_with_state(
     rpc_client: &RpcClient,
     state: Option<ParticipantState>,
-) -> Result<HashMap<Pubkey, Participant>, Box<dyn std::error::Error>> {
-    let accounts = rpc_client.get_program_accounts_with_config(
-        &registry_program::id(),
-        RpcProgramAccountsConfig {
-            account_config: RpcAccountInfoConfig {
-                encoding: Some(solana_account_decoder::UiAccountEncoding::Base64Zstd),
-                commitment: Some(rpc_client.commitment()), // TODO: Remove this line after updating to solana v1.6.10
-                ..RpcAccountInfoConfig::default()
+) -> Result<HashMap<Pubkey, ParticipantV2>, Box<dyn std::error::Error>> {
+    let epoch = rpc_client.get_epoch_info()?.epoch;
+    let mut accounts = vec![];
+    for data_size in &[
+        Participant::get_packed_len(),
+        ParticipantV2::get_packed_len(),
+    ] {
+        accounts.extend(rpc_client.get_program_accounts_with_config(
+            &registry_program::id(),
+            RpcProgramAccountsConfig {
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(solana_account_decoder::UiAccountEncoding::Base64Zstd),
+                    commitment: Some(rpc_client.commitment()), // TODO: Remove this line after updating to solana v1.6.10
+                    ..RpcAccountInfoConfig::default()
+                },
+                filters: Some(vec![RpcFilterType::DataSize(*data_size as u64)]),
             },
-            filters: Some(vec![RpcFilterType::DataSize(
-                Participant::get_packed_len() as u64
-            )]),
-        },
-    )?;
+        )?);
+    }
 
     Ok(accounts
         .into_iter()
         .filter_map(|(address, account)| {
-            Participant::unpack_from_slice(&account.data)
-                .ok()
-                .map(|p| (address, p))
+            unpack_participant(&account.data).ok().map(|mut p| {
+                p.state = p.state.at_epoch(epoch);
+                (address, p)
+            })
         })
         .filter(|(_, p)| {
             if let Some(ref state) = state {
-                return p.state == *state;
+                return mem::discriminant(&p.state) == mem::discriminant(state);
             }
             true
         })
@@ -45,6 +65,373 @@ pub fn get_participants_with_state(
 
 pub fn get_participants(
     rpc_client: &RpcClient,
-) -> Result<HashMap<Pubkey, Participant>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<Pubkey, ParticipantV2>, Box<dyn std::error::Error>> {
     get_participants_with_state(rpc_client, None)
 }
+
+/// Fetch the registry config account, if it has been initialized
+pub fn get_registry_config(
+    rpc_client: &RpcClient,
+) -> Result<Option<RegistryConfig>, Box<dyn std::error::Error>> {
+    let config_address = registry_program::find_config_address(&registry_program::id()).0;
+    let account = rpc_client
+        .get_account_with_commitment(&config_address, rpc_client.commitment())?
+        .value;
+
+    match account {
+        Some(account) if account.owner == registry_program::id() => {
+            let registry_config = RegistryConfig::unpack_from_slice(&account.data)?;
+            Ok(Some(registry_config).filter(|registry_config| registry_config.is_initialized))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// A participant state transition recovered from the registry program's event logs
+#[derive(Debug)]
+pub struct HistoryEntry {
+    pub signature: Signature,
+    pub slot: Slot,
+    pub block_time: Option<UnixTimestamp>,
+    pub transition: StateTransition,
+}
+
+/// Rebuild the history of a participant account from the event logs of the transactions that
+/// reference it, oldest first. When the participant moved from another account, due to a mainnet
+/// identity change or a migration, the history of that account is included
+pub fn get_participant_history(
+    rpc_client: &RpcClient,
+    participant_address: Pubkey,
+) -> Result<Vec<HistoryEntry>, Box<dyn std::error::Error>> {
+    let mut history = vec![];
+    let mut next_account = Some((participant_address, None));
+
+    while let Some((address, mut before)) = next_account.take() {
+        loop {
+            let statuses = rpc_client.get_confirmed_signatures_for_address2_with_config(
+                &address,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    ..GetConfirmedSignaturesForAddress2Config::default()
+                },
+            )?;
+            let last_signature = match statuses.last() {
+                Some(status) => Signature::from_str(&status.signature)?,
+                None => break,
+            };
+
+            // Failed transactions do not modify the participant
+            for status in statuses.into_iter().filter(|status| status.err.is_none()) {
+                let signature = Signature::from_str(&status.signature)?;
+                let transaction = rpc_client
+                    .get_confirmed_transaction(&signature, UiTransactionEncoding::Json)?;
+                let transitions = transaction
+                    .transaction
+                    .meta
+                    .and_then(|meta| meta.log_messages)
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(|log_message| StateTransition::parse(log_message))
+                    .collect::<Vec<_>>();
+
+                // The participant moved here if another participant account was closed by the
+                // transaction that created this one
+                let created = transitions.iter().any(|transition| {
+                    transition.participant == address
+                        && transition.old_state == ParticipantState::Uninitialized
+                });
+                if created && next_account.is_none() {
+                    next_account = transitions
+                        .iter()
+                        .find(|transition| {
+                            transition.participant != address
+                                && transition.new_state == ParticipantState::Uninitialized
+                        })
+                        .map(|transition| (transition.participant, Some(signature)));
+                }
+
+                // Signatures are returned newest first, so this transaction's transitions are
+                // added in reverse and the whole history reversed at the end
+                history.extend(
+                    transitions
+                        .into_iter()
+                        .filter(|transition| transition.participant == address)
+                        .rev()
+                        .map(|transition| HistoryEntry {
+                            signature,
+                            slot: transaction.slot,
+                            block_time: transaction.block_time,
+                            transition,
+                        }),
+                );
+            }
+            before = Some(last_signature);
+        }
+    }
+
+    history.reverse();
+    history.sort_by_key(|entry| entry.slot);
+    Ok(history)
+}
+
+/// Vote account of a validator identity on the cluster
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoteAccountInfo {
+    pub vote_account: Pubkey,
+    pub activated_stake: u64,
+}
+
+/// Fetch the current and delinquent vote accounts of the cluster by validator identity. For an
+/// identity with several vote accounts, the one with the most activated stake is returned
+pub fn get_vote_accounts_by_identity(
+    rpc_client: &RpcClient,
+) -> Result<HashMap<Pubkey, VoteAccountInfo>, Box<dyn std::error::Error>> {
+    let vote_accounts = rpc_client.get_vote_accounts()?;
+
+    let mut vote_accounts_by_identity: HashMap<Pubkey, VoteAccountInfo> = HashMap::new();
+    for vote_account in vote_accounts
+        .current
+        .into_iter()
+        .chain(vote_accounts.delinquent)
+    {
+        let info = VoteAccountInfo {
+            vote_account: Pubkey::from_str(&vote_account.vote_pubkey)?,
+            activated_stake: vote_account.activated_stake,
+        };
+        vote_accounts_by_identity
+            .entry(Pubkey::from_str(&vote_account.node_pubkey)?)
+            .and_modify(|existing| {
+                if info.activated_stake > existing.activated_stake {
+                    *existing = info.clone();
+                }
+            })
+            .or_insert(info);
+    }
+    Ok(vote_accounts_by_identity)
+}
+
+/// Classification of a validator by stake-o-matic
+#[derive(Clone, Debug, PartialEq)]
+pub struct BotClassification {
+    pub epoch: Epoch,
+    pub stake_state: String,
+    pub stake_state_reason: String,
+}
+
+/// Block production observed for a validator over an epoch
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct BlockProductionStats {
+    pub blocks: usize,
+    pub slots: usize,
+    pub skip_rate: usize,
+}
+
+impl fmt::Display for BlockProductionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} blocks in {} slots, {:.2}% skip rate",
+            self.blocks, self.slots, self.skip_rate
+        )
+    }
+}
+
+/// Reason for the stake state of a validator in a stake-o-matic classification
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub enum StakeStateReason {
+    InfrastructureConcentrationTooHigh {
+        concentration: f64,
+    },
+    InsufficientSelfStake {
+        self_stake: u64,
+        required: u64,
+    },
+    CommissionTooHigh {
+        commission: u8,
+        max_commission: u8,
+    },
+    InsufficientTestnetParticipation,
+    InsufficientVoteCredits {
+        earned: u64,
+        required: u64,
+        epoch: Epoch,
+    },
+    OutdatedRelease {
+        version: String,
+        required: String,
+    },
+    GoodBlockProduction {
+        epoch: Epoch,
+        block_production: BlockProductionStats,
+    },
+    PoorBlockProduction {
+        epoch: Epoch,
+        block_production: BlockProductionStats,
+    },
+    NoLeaderSlots {
+        credits: u64,
+        epoch: Epoch,
+    },
+    /// The participant is suspended by the registry through `until_epoch`
+    ParticipantSuspended {
+        until_epoch: Epoch,
+    },
+    /// Destaked mid-epoch by the `emergency-destake` command
+    EmergencyDestake {
+        reason: String,
+    },
+    /// Stake state forced by a manual override
+    Override {
+        reason: String,
+    },
+
+    /// Free-form reason carried over from an `EpochClassificationV1`
+    Legacy(String),
+}
+
+impl Default for StakeStateReason {
+    fn default() -> Self {
+        Self::Legacy(String::default())
+    }
+}
+
+impl fmt::Display for StakeStateReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InfrastructureConcentrationTooHigh { concentration } => write!(
+                f,
+                "infrastructure concentration {:.1}% is too high; find a new data center",
+                concentration
+            ),
+            Self::InsufficientSelfStake { self_stake, .. } => {
+                write!(f, "insufficient self stake: {}", Sol(*self_stake))
+            }
+            Self::CommissionTooHigh { commission, .. } => {
+                write!(f, "commission is too high: {}% commission", commission)
+            }
+            Self::InsufficientTestnetParticipation => {
+                write!(f, "insufficient testnet participation")
+            }
+            Self::InsufficientVoteCredits { earned, epoch, .. } => write!(
+                f,
+                "insufficient vote credits: {} credits earned in epoch {}",
+                earned, epoch
+            ),
+            Self::OutdatedRelease { version, .. } => {
+                write!(f, "Outdated solana release: {}", version)
+            }
+            Self::GoodBlockProduction {
+                epoch,
+                block_production,
+            } => write!(
+                f,
+                "good block production during epoch {}: {}",
+                epoch, block_production
+            ),
+            Self::PoorBlockProduction {
+                epoch,
+                block_production,
+            } => write!(
+                f,
+                "poor block production during epoch {}: {}",
+                epoch, block_production
+            ),
+            Self::NoLeaderSlots { credits, epoch } => write!(
+                f,
+                "no leader slots; {} credits earned in epoch {}",
+                credits, epoch
+            ),
+            Self::ParticipantSuspended { until_epoch } => {
+                write!(f, "participant suspended through epoch {}", until_epoch)
+            }
+            Self::EmergencyDestake { reason } => write!(f, "emergency destake: {}", reason),
+            Self::Override { reason } => write!(f, "manual override: {}", reason),
+            Self::Legacy(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+// The parts of the bot's `EpochClassification` read by the CLI. The bot depends on this crate, so
+// its types cannot be used here
+#[derive(Deserialize)]
+enum EpochClassification {
+    V1(EpochClassificationSummary),
+    V2(EpochClassificationSummary),
+}
+
+#[derive(Deserialize)]
+struct EpochClassificationSummary {
+    validator_classifications: Option<HashMap<Pubkey, ValidatorClassificationSummary>>,
+}
+
+#[derive(Deserialize)]
+struct ValidatorClassificationSummary {
+    stake_state: String,
+    stake_state_reason: serde_yaml::Value,
+}
+
+/// Render a `StakeStateReason` as the bot does, which is a free-form string in `V1`
+/// classifications
+fn format_stake_state_reason(reason: serde_yaml::Value) -> String {
+    match reason {
+        serde_yaml::Value::String(reason) => reason,
+        reason => match serde_yaml::from_value::<StakeStateReason>(reason.clone()) {
+            Ok(reason) => reason.to_string(),
+            Err(_) => format!("{:?}", reason),
+        },
+    }
+}
+
+/// Load the latest classification of each validator from a stake-o-matic YAML database, the
+/// `data-<cluster>` directory under the bot's `--db-path`. Returns an empty map if the database has
+/// no classification
+pub fn get_latest_bot_classifications(
+    db_path: &Path,
+) -> Result<HashMap<Pubkey, BotClassification>, Box<dyn std::error::Error>> {
+    let mut epochs = fs::read_dir(db_path)
+        .map_err(|err| format!("Unable to read {}: {}", db_path.display(), err))?
+        .filter_map(|entry| {
+            entry
+                .ok()?
+                .file_name()
+                .to_str()?
+                .strip_prefix("epoch-")?
+                .strip_suffix(".yml")?
+                .parse::<Epoch>()
+                .ok()
+        })
+        .collect::<Vec<_>>();
+    epochs.sort_unstable();
+
+    // Like the bot, skip epochs where classification was paused
+    for epoch in epochs.into_iter().rev() {
+        let path = db_path.join(format!("epoch-{}.yml", epoch));
+        let file = fs::File::open(&path)
+            .map_err(|err| format!("Unable to open {}: {}", path.display(), err))?;
+        let summary = match serde_yaml::from_reader(file)
+            .map_err(|err| format!("Invalid classification {}: {}", path.display(), err))?
+        {
+            EpochClassification::V1(summary) => summary,
+            EpochClassification::V2(summary) => summary,
+        };
+
+        if let Some(validator_classifications) = summary.validator_classifications {
+            return Ok(validator_classifications
+                .into_iter()
+                .map(|(identity, classification)| {
+                    (
+                        identity,
+                        BotClassification {
+                            epoch,
+                            stake_state: classification.stake_state,
+                            stake_state_reason: format_stake_state_reason(
+                                classification.stake_state_reason,
+                            ),
+                        },
+                    )
+                })
+                .collect());
+        }
+    }
+    Ok(HashMap::new())
+}
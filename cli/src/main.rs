@@ -1,31 +1,55 @@
+mod batch;
+mod output;
+
 use {
+    batch::{
+        pack_instructions, parse_rejection_code, read_batch_file, BatchResult, ImportEntry,
+        ParticipantEntry, REJECTION_CODE_NAMES,
+    },
     clap::{
-        crate_description, crate_name, crate_version, value_t_or_exit, App, AppSettings, Arg,
-        SubCommand,
+        crate_description, crate_name, crate_version, value_t_or_exit, values_t_or_exit, App,
+        AppSettings, Arg, ArgMatches, SubCommand,
+    },
+    output::{format_records, IdentityContext, OutputFormat, ParticipantRecord},
+    registry_cli::{
+        get_latest_bot_classifications, get_participant_history, get_participants,
+        get_registry_config, get_vote_accounts_by_identity,
+    },
+    registry_program::{
+        error::RegistryError,
+        find_participant_address,
+        instruction::Network,
+        state::{
+            InfoUrl, Participant, ParticipantState, ParticipantV2, RegistryConfig, RejectionCode,
+            RejectionReason, TestnetIdentityIndex,
+        },
     },
-    registry_cli::{get_participants, get_participants_with_state},
-    registry_program::state::{Participant, ParticipantState},
     solana_clap_utils::{
         input_parsers::{pubkey_of, signer_of},
-        input_validators::{is_url, is_valid_pubkey, is_valid_signer},
-        keypair::DefaultSigner,
+        input_validators::{is_parsable, is_url, is_valid_pubkey, is_valid_signer},
+        keypair::{signer_from_path, DefaultSigner},
+    },
+    solana_client::{
+        client_error::ClientError, rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig,
     },
-    solana_client::rpc_client::RpcClient,
     solana_remote_wallet::remote_wallet::RemoteWalletManager,
     solana_sdk::{
+        clock::Epoch,
         commitment_config::CommitmentConfig,
+        instruction::{Instruction, InstructionError},
         message::Message,
         native_token::Sol,
         program_pack::Pack,
         pubkey::Pubkey,
-        signature::{Keypair, Signer},
+        signature::Signer,
         signers::Signers,
-        system_instruction,
-        transaction::Transaction,
+        transaction::{Transaction, TransactionError},
     },
     std::{
         collections::{HashMap, HashSet},
+        iter, mem,
         ops::Deref,
+        path::Path,
         process::exit,
         sync::Arc,
     },
@@ -68,16 +92,33 @@ fn send_and_confirm_message<T: Signers>(
 
     let signature = rpc_client
         .send_and_confirm_transaction_with_spinner(&transaction)
-        .map_err(|err| format!("error: send transaction: {}", err))?;
+        .map_err(|err| match registry_error(&transaction, &err) {
+            Some(registry_error) => format!("error: {}", registry_error),
+            None => format!("error: send transaction: {}", err),
+        })?;
 
     println!("{}", signature);
     Ok(())
 }
 
+/// The `RegistryError` a failed transaction was rejected with by the registry program, if any
+fn registry_error(transaction: &Transaction, err: &ClientError) -> Option<RegistryError> {
+    match err.get_transaction_error()? {
+        TransactionError::InstructionError(index, InstructionError::Custom(code))
+            if transaction.message.instructions[index as usize]
+                .program_id(&transaction.message.account_keys)
+                == &registry_program::id() =>
+        {
+            RegistryError::from_code(code)
+        }
+        _ => None,
+    }
+}
+
 fn get_participants_with_identity(
     rpc_client: &RpcClient,
     identities: HashSet<&Pubkey>,
-) -> Result<HashMap<Pubkey, Participant>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<Pubkey, ParticipantV2>, Box<dyn std::error::Error>> {
     let mut participants = get_participants(rpc_client)?;
     participants.retain(|_, p| {
         identities.contains(&p.testnet_identity) || identities.contains(&p.mainnet_identity)
@@ -88,7 +129,7 @@ fn get_participants_with_identity(
 fn get_participant_by_identity(
     rpc_client: &RpcClient,
     identity: Pubkey,
-) -> Result<Option<(Pubkey, Participant)>, Box<dyn std::error::Error>> {
+) -> Result<Option<(Pubkey, ParticipantV2)>, Box<dyn std::error::Error>> {
     let participant = get_participants(rpc_client)?
         .into_iter()
         .filter(|(_, p)| p.testnet_identity == identity || p.mainnet_identity == identity)
@@ -101,7 +142,34 @@ fn get_participant_by_identity(
     }
 }
 
-fn print_participant(participant: &Participant) {
+/// Rent for the participant and testnet identity index accounts of a participant
+fn get_participant_rent(rpc_client: &RpcClient) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(
+        rpc_client.get_minimum_balance_for_rent_exemption(ParticipantV2::get_packed_len())?
+            + rpc_client
+                .get_minimum_balance_for_rent_exemption(TestnetIdentityIndex::get_packed_len())?,
+    )
+}
+
+/// Participants created before participant addresses were derived from the mainnet identity
+/// must be migrated by an admin before they can be updated
+fn check_migrated(
+    participant_address: &Pubkey,
+    participant: &ParticipantV2,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let derived_address =
+        find_participant_address(&registry_program::id(), &participant.mainnet_identity).0;
+    if *participant_address != derived_address {
+        return Err(format!(
+            "Registration {} has not been migrated yet, please contact an admin",
+            participant_address
+        )
+        .into());
+    }
+    Ok(())
+}
+
+fn print_participant(participant: &ParticipantV2) {
     println!("State: {:?}", participant.state);
     println!(
         "Mainnet Validator Identity: {}",
@@ -111,16 +179,99 @@ fn print_participant(participant: &Participant) {
         "Testnet Validator Identity: {}",
         participant.testnet_identity
     );
+    if participant.application_slot > 0 {
+        println!(
+            "Applied: slot {} (epoch {})",
+            participant.application_slot, participant.application_epoch
+        );
+        println!("Last State Change: slot {}", participant.state_change_slot);
+    }
+    if participant.state == ParticipantState::Rejected
+        && participant.rejection_reason.code != RejectionCode::Unspecified
+    {
+        println!(
+            "Rejection Reason: {:?}: {}",
+            participant.rejection_reason.code,
+            participant.rejection_reason.text()
+        );
+    }
+    if let Some(url) = participant.info_url.url() {
+        println!("Info URL: {}", url);
+    }
+}
+
+fn print_identity_context(network: &str, identity: &Pubkey, context: &IdentityContext) {
+    match context.vote_accounts.get(identity) {
+        Some(info) => println!(
+            "{} Vote Account: {} ({} active stake)",
+            network,
+            info.vote_account,
+            Sol(info.activated_stake)
+        ),
+        None => println!("{} Vote Account: none", network),
+    }
+    if let Some(ref bot_classifications) = context.bot_classifications {
+        match bot_classifications.get(identity) {
+            Some(classification) => println!(
+                "{} Classification: {} in epoch {}: {}",
+                network,
+                classification.stake_state,
+                classification.epoch,
+                classification.stake_state_reason
+            ),
+            None => println!("{} Classification: none", network),
+        }
+    }
+}
+
+/// Fetch the vote accounts of the cluster if `--with-context` is present and, if `--bot-db` is
+/// also present, the latest stake-o-matic classifications
+fn get_identity_context(
+    rpc_client: &RpcClient,
+    arg_matches: &ArgMatches,
+) -> Result<Option<IdentityContext>, Box<dyn std::error::Error>> {
+    if !arg_matches.is_present("with_context") {
+        return Ok(None);
+    }
+    Ok(Some(IdentityContext {
+        vote_accounts: get_vote_accounts_by_identity(rpc_client)?,
+        bot_classifications: arg_matches
+            .value_of("bot_db")
+            .map(|path| get_latest_bot_classifications(Path::new(path)))
+            .transpose()?,
+    }))
 }
 
 fn process_status(
     _config: &Config,
     rpc_client: &RpcClient,
     identity: Pubkey,
+    output_format: Option<OutputFormat>,
+    context: Option<IdentityContext>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    match get_participant_by_identity(rpc_client, identity)? {
+    let participant = get_participant_by_identity(rpc_client, identity)?;
+
+    if let Some(output_format) = output_format {
+        let records = participant
+            .iter()
+            .map(|(address, participant)| {
+                ParticipantRecord::new(address, participant, context.as_ref())
+            })
+            .collect::<Vec<_>>();
+        println!(
+            "{}",
+            format_records(&records, output_format, context.is_some())?
+        );
+        return Ok(());
+    }
+
+    match participant {
         Some((_, participant)) => {
             print_participant(&participant);
+            if let Some(ref context) = context {
+                print_identity_context("Mainnet", &participant.mainnet_identity, context);
+                print_identity_context("Testnet", &participant.testnet_identity, context);
+            }
         }
         None => {
             println!("Registration not found for {}", identity);
@@ -152,30 +303,20 @@ fn process_apply(
 
     if !confirm {
         println!(
-            "\nWarning: Your mainnet and testnet identities cannot be changed after applying. \
-                    Add the --confirm flag to continue"
+            "\nWarning: Your mainnet and testnet identities can only be changed after applying \
+                    with the `update-identity` command. Add the --confirm flag to continue"
         );
         return Ok(());
     }
 
-    let rent = rpc_client.get_minimum_balance_for_rent_exemption(Participant::get_packed_len())?;
-    let participant: Box<dyn Signer> = Box::new(Keypair::new());
+    let rent = get_participant_rent(rpc_client)?;
 
     let message = Message::new(
-        &[
-            system_instruction::create_account(
-                &config.default_signer.pubkey(),
-                &participant.pubkey(),
-                rent,
-                Participant::get_packed_len() as u64,
-                &registry_program::id(),
-            ),
-            registry_program::instruction::apply(
-                participant.pubkey(),
-                mainnet_identity.pubkey(),
-                testnet_identity.pubkey(),
-            ),
-        ],
+        &[registry_program::instruction::apply(
+            config.default_signer.pubkey(),
+            mainnet_identity.pubkey(),
+            testnet_identity.pubkey(),
+        )],
         Some(&config.default_signer.pubkey()),
     );
 
@@ -183,7 +324,6 @@ fn process_apply(
         rpc_client,
         message,
         [
-            participant.deref(),
             mainnet_identity.deref(),
             testnet_identity.deref(),
             config.default_signer.deref(),
@@ -213,8 +353,9 @@ fn process_withdraw(
     }
 
     let message = Message::new(
-        &[registry_program::instruction::withdraw(
+        &[registry_program::instruction::withdraw_account(
             participant_address,
+            &Participant::from(&participant),
             identity.pubkey(),
             config.default_signer.pubkey(),
         )],
@@ -229,18 +370,162 @@ fn process_withdraw(
     )
 }
 
+fn process_update_identity(
+    config: &Config,
+    rpc_client: &RpcClient,
+    network: Network,
+    current_identity: Box<dyn Signer>,
+    new_identity: Box<dyn Signer>,
+    admin_signers: Vec<Box<dyn Signer>>,
+    confirm: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (participant_address, participant) =
+        get_participant_by_identity(rpc_client, current_identity.pubkey())?
+            .ok_or_else(|| format!("Registration not found for {}", current_identity.pubkey()))?;
+
+    if get_participant_by_identity(rpc_client, new_identity.pubkey())?.is_some() {
+        return Err(format!(
+            "A registration already exists for {}",
+            new_identity.pubkey()
+        )
+        .into());
+    }
+
+    print_participant(&participant);
+    check_migrated(&participant_address, &participant)?;
+    println!(
+        "New {:?} Validator Identity: {}",
+        network,
+        new_identity.pubkey()
+    );
+
+    let review = admin_signers.is_empty()
+        && matches!(
+            participant.state,
+            ParticipantState::Approved | ParticipantState::Suspended { .. }
+        );
+    if !confirm {
+        println!(
+            "\nWarning: this will replace the {:?} validator identity of this registration{}. \
+               Add the --confirm flag to continue",
+            network,
+            if review {
+                " and return it to the Pending state for review"
+            } else {
+                ""
+            }
+        );
+        return Ok(());
+    }
+
+    let current_identity_address = match network {
+        Network::Mainnet => participant.mainnet_identity,
+        Network::Testnet => participant.testnet_identity,
+    };
+    if current_identity.pubkey() != current_identity_address {
+        return Err(format!(
+            "{} is not the {:?} validator identity of this registration",
+            current_identity.pubkey(),
+            network
+        )
+        .into());
+    }
+
+    // The replaced account is refunded once the new account is created
+    let rent = rpc_client.get_minimum_balance_for_rent_exemption(match network {
+        Network::Mainnet => ParticipantV2::get_packed_len(),
+        Network::Testnet => TestnetIdentityIndex::get_packed_len(),
+    })?;
+
+    let message = Message::new(
+        &[registry_program::instruction::update_identity(
+            config.default_signer.pubkey(),
+            &Participant::from(&participant),
+            network,
+            new_identity.pubkey(),
+            &admin_addresses(&admin_signers),
+        )],
+        Some(&config.default_signer.pubkey()),
+    );
+
+    let mut signers = admin_and_fee_payer_signers(config, &admin_signers);
+    signers.extend(&[current_identity.deref(), new_identity.deref()]);
+    send_and_confirm_message(rpc_client, message, signers, Some(rent))
+}
+
+fn process_set_info_url(
+    config: &Config,
+    rpc_client: &RpcClient,
+    identity: Box<dyn Signer>,
+    info_url: InfoUrl,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (participant_address, participant) =
+        get_participant_by_identity(rpc_client, identity.pubkey())?
+            .ok_or_else(|| format!("Registration not found for {}", identity.pubkey()))?;
+
+    print_participant(&participant);
+    check_migrated(&participant_address, &participant)?;
+
+    let message = Message::new(
+        &[registry_program::instruction::set_info_url(
+            participant_address,
+            identity.pubkey(),
+            info_url,
+        )],
+        Some(&config.default_signer.pubkey()),
+    );
+
+    send_and_confirm_message(
+        rpc_client,
+        message,
+        [identity.deref(), config.default_signer.deref()],
+        None,
+    )
+}
+
+/// List participants in any of `states`, or all participants if `states` is empty. A
+/// `ParticipantState::Suspended` state matches any `until_epoch`
 fn process_list(
     config: &Config,
     rpc_client: &RpcClient,
-    state: Option<ParticipantState>,
+    states: &[ParticipantState],
+    output_format: Option<OutputFormat>,
+    context: Option<IdentityContext>,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let participants = get_participants_with_state(rpc_client, state)?;
+    let mut participants = get_participants(rpc_client)?
+        .into_iter()
+        .filter(|(_, participant)| {
+            states.is_empty()
+                || states
+                    .iter()
+                    .any(|state| mem::discriminant(state) == mem::discriminant(&participant.state))
+        })
+        .collect::<Vec<_>>();
+    participants.sort_by_key(|(address, _)| *address);
+
+    if let Some(output_format) = output_format {
+        let records = participants
+            .iter()
+            .map(|(address, participant)| {
+                ParticipantRecord::new(address, participant, context.as_ref())
+            })
+            .collect::<Vec<_>>();
+        println!(
+            "{}",
+            format_records(&records, output_format, context.is_some())?
+        );
+        return Ok(());
+    }
 
     for (address, participant) in &participants {
         if config.verbose {
             println!("Participant: {}", address);
         }
         print_participant(participant);
+        if let Some(ref context) = context {
+            print_identity_context("Mainnet", &participant.mainnet_identity, context);
+            print_identity_context("Testnet", &participant.testnet_identity, context);
+        }
         println!();
     }
 
@@ -248,10 +533,99 @@ fn process_list(
     Ok(())
 }
 
+fn process_history(
+    rpc_client: &RpcClient,
+    identity: Pubkey,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // A withdrawn participant no longer has an account, but its history remains at the address
+    // derived from its mainnet identity
+    let participant_address = match get_participant_by_identity(rpc_client, identity)? {
+        Some((participant_address, participant)) => {
+            print_participant(&participant);
+            println!();
+            participant_address
+        }
+        None => find_participant_address(&registry_program::id(), &identity).0,
+    };
+
+    let history = get_participant_history(rpc_client, participant_address)?;
+    for entry in &history {
+        println!(
+            "Slot {}: {:?} -> {:?}",
+            entry.slot, entry.transition.old_state, entry.transition.new_state
+        );
+        println!("  Participant: {}", entry.transition.participant);
+        println!(
+            "  Signed by: {}",
+            entry
+                .transition
+                .actors
+                .iter()
+                .map(|actor| actor.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        println!("  Signature: {}", entry.signature);
+    }
+
+    println!("{} entries found", history.len());
+    Ok(())
+}
+
+fn admin_addresses(admin_signers: &[Box<dyn Signer>]) -> Vec<Pubkey> {
+    admin_signers
+        .iter()
+        .map(|admin_signer| admin_signer.pubkey())
+        .collect()
+}
+
+fn admin_and_fee_payer_signers<'a>(
+    config: &'a Config,
+    admin_signers: &'a [Box<dyn Signer>],
+) -> Vec<&'a dyn Signer> {
+    let mut signers = admin_signers
+        .iter()
+        .map(|admin_signer| admin_signer.deref())
+        .collect::<Vec<_>>();
+    signers.push(config.default_signer.deref());
+    signers
+}
+
+/// Check that the admin signers are able to authorize admin instructions
+fn check_admin_signers(
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (admins, threshold) = match get_registry_config(rpc_client)? {
+        Some(registry_config) => (registry_config.admins, registry_config.threshold as usize),
+        None => (vec![registry_program::admin::id()], 1),
+    };
+
+    for admin in admin_addresses(admin_signers) {
+        if !admins.contains(&admin) {
+            return Err(format!("{} is not an admin authority", admin).into());
+        }
+    }
+    if admin_signers
+        .iter()
+        .map(|admin_signer| admin_signer.pubkey())
+        .collect::<HashSet<_>>()
+        .len()
+        < threshold
+    {
+        return Err(format!(
+            "{} admin authorities are required, specify --authority {} times",
+            threshold, threshold
+        )
+        .into());
+    }
+    Ok(())
+}
+
 fn process_admin_approve(
     config: &Config,
     rpc_client: &RpcClient,
-    admin_signer: Box<dyn Signer>,
+    admin_signers: &[Box<dyn Signer>],
     participant_address: Pubkey,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let participants = get_participants(rpc_client)?;
@@ -265,7 +639,7 @@ fn process_admin_approve(
     let message = Message::new(
         &[registry_program::instruction::approve(
             participant_address,
-            admin_signer.pubkey(),
+            &admin_addresses(admin_signers),
         )],
         Some(&config.default_signer.pubkey()),
     );
@@ -273,29 +647,604 @@ fn process_admin_approve(
     send_and_confirm_message(
         rpc_client,
         message,
-        [admin_signer.deref(), config.default_signer.deref()],
+        admin_and_fee_payer_signers(config, admin_signers),
         None,
     )
 }
 
-fn process_admin_reject(
+fn process_admin_reject(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    participant_address: Pubkey,
+    reason: RejectionReason,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let participants = get_participants(rpc_client)?;
+    let participant = participants
+        .get(&participant_address)
+        .ok_or_else(|| format!("Participant {} does not exist", participant_address))?;
+
+    print_participant(&participant);
+    println!("Rejecting...");
+
+    let message = Message::new(
+        &[registry_program::instruction::reject(
+            participant_address,
+            &admin_addresses(admin_signers),
+            reason,
+        )],
+        Some(&config.default_signer.pubkey()),
+    );
+
+    send_and_confirm_message(
+        rpc_client,
+        message,
+        admin_and_fee_payer_signers(config, admin_signers),
+        None,
+    )
+}
+
+fn process_admin_suspend(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    participant_address: Pubkey,
+    until_epoch: Epoch,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let participants = get_participants(rpc_client)?;
+    let participant = participants
+        .get(&participant_address)
+        .ok_or_else(|| format!("Participant {} does not exist", participant_address))?;
+
+    print_participant(&participant);
+    println!("Suspending through epoch {}...", until_epoch);
+
+    let message = Message::new(
+        &[registry_program::instruction::suspend(
+            participant_address,
+            &admin_addresses(admin_signers),
+            until_epoch,
+        )],
+        Some(&config.default_signer.pubkey()),
+    );
+
+    send_and_confirm_message(
+        rpc_client,
+        message,
+        admin_and_fee_payer_signers(config, admin_signers),
+        None,
+    )
+}
+
+fn process_admin_import(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    mainnet_identity: Pubkey,
+    testnet_identity: Pubkey,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let participants = get_participants_with_identity(
+        rpc_client,
+        [mainnet_identity, testnet_identity]
+            .iter()
+            .collect::<HashSet<_>>(),
+    )?;
+
+    if !participants.is_empty() {
+        return Err("A registration already exists with the provided identity".into());
+    }
+
+    let rent = get_participant_rent(rpc_client)?;
+
+    let message = Message::new(
+        &[registry_program::instruction::import(
+            config.default_signer.pubkey(),
+            &admin_addresses(admin_signers),
+            Participant {
+                state: ParticipantState::Approved,
+                testnet_identity,
+                mainnet_identity,
+            },
+        )],
+        Some(&config.default_signer.pubkey()),
+    );
+
+    send_and_confirm_message(
+        rpc_client,
+        message,
+        admin_and_fee_payer_signers(config, admin_signers),
+        Some(rent),
+    )
+}
+
+/// Simulate a transaction for `message`, returning the error it would fail with
+fn simulate_message(rpc_client: &RpcClient, message: &Message) -> Result<(), String> {
+    let mut transaction = Transaction::new_unsigned(message.clone());
+    transaction.message.recent_blockhash = rpc_client
+        .get_recent_blockhash()
+        .map_err(|err| format!("unable to get recent blockhash: {}", err))?
+        .0;
+    let result = rpc_client
+        .simulate_transaction_with_config(
+            &transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )
+        .map_err(|err| format!("unable to simulate transaction: {}", err))?;
+    match result.value.err {
+        Some(err) => Err(err.to_string()),
+        None => Ok(()),
+    }
+}
+
+/// Send a batch of instructions in one transaction, appending the result of each instruction to
+/// `results`. A batch that fails simulation is split in half and each half sent separately, so
+/// that an instruction that fails does not fail the others
+fn send_and_confirm_instructions(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    mut instructions: Vec<Instruction>,
+    additional_funds_per_instruction: Option<u64>,
+    results: &mut Vec<BatchResult>,
+) {
+    let instruction_count = instructions.len();
+    let message = Message::new(&instructions, Some(&config.default_signer.pubkey()));
+
+    if instruction_count > 1 {
+        if let Err(err) = simulate_message(rpc_client, &message) {
+            println!(
+                "Splitting {} instructions that failed simulation: {}",
+                instruction_count, err
+            );
+            let second_half = instructions.split_off(instruction_count / 2);
+            for half in vec![instructions, second_half] {
+                send_and_confirm_instructions(
+                    config,
+                    rpc_client,
+                    admin_signers,
+                    half,
+                    additional_funds_per_instruction,
+                    results,
+                );
+            }
+            return;
+        }
+    }
+
+    println!("Sending {} instructions...", instruction_count);
+    let result = match send_and_confirm_message(
+        rpc_client,
+        message,
+        admin_and_fee_payer_signers(config, admin_signers),
+        additional_funds_per_instruction.map(|funds| funds * instruction_count as u64),
+    ) {
+        Ok(()) => BatchResult::Confirmed,
+        Err(err) => {
+            eprintln!("{}", err);
+            BatchResult::Failed(err.to_string())
+        }
+    };
+    results.extend(iter::repeat(result).take(instruction_count));
+}
+
+/// Send instructions packed into as few transactions as fit, returning the result of each
+/// instruction
+fn send_and_confirm_batch(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    instructions: Vec<Instruction>,
+    additional_funds_per_instruction: Option<u64>,
+) -> Vec<BatchResult> {
+    let mut results = vec![];
+    for batch in pack_instructions(instructions, &config.default_signer.pubkey()) {
+        send_and_confirm_instructions(
+            config,
+            rpc_client,
+            admin_signers,
+            batch,
+            additional_funds_per_instruction,
+            &mut results,
+        );
+    }
+    results
+}
+
+/// Merge the results of the sent entries of a batch with the skipped ones, in entry order
+fn batch_results<T>(
+    resolved: &[Result<T, String>],
+    sent_results: Vec<BatchResult>,
+) -> Vec<BatchResult> {
+    let mut sent_results = sent_results.into_iter();
+    resolved
+        .iter()
+        .map(|resolved| match resolved {
+            Ok(_) => sent_results.next().unwrap(),
+            Err(reason) => BatchResult::Skipped(reason.clone()),
+        })
+        .collect()
+}
+
+fn print_batch_summary(entries: &[Pubkey], results: &[BatchResult], action: &str) {
+    println!();
+    println!("Summary:");
+    for (entry, result) in entries.iter().zip(results) {
+        match result {
+            BatchResult::Confirmed => println!("{:<44} {}", entry, action),
+            BatchResult::Skipped(reason) => println!("{:<44} skipped: {}", entry, reason),
+            BatchResult::Failed(err) => println!("{:<44} failed: {}", entry, err),
+        }
+    }
+    let count = |f: fn(&BatchResult) -> bool| results.iter().filter(|result| f(result)).count();
+    println!(
+        "{} {}, {} failed, {} skipped",
+        count(|result| *result == BatchResult::Confirmed),
+        action,
+        count(|result| matches!(result, BatchResult::Failed(_))),
+        count(|result| matches!(result, BatchResult::Skipped(_))),
+    );
+}
+
+fn print_participant_table(participants: &[&(Pubkey, ParticipantV2)]) {
+    println!(
+        "{:<44} {:<44} {:<44} Applied",
+        "Participant", "Mainnet Identity", "Testnet Identity"
+    );
+    for (address, participant) in participants {
+        println!(
+            "{:<44} {:<44} {:<44} {}",
+            address,
+            participant.mainnet_identity,
+            participant.testnet_identity,
+            if participant.application_slot > 0 {
+                format!("epoch {}", participant.application_epoch)
+            } else {
+                "-".to_string()
+            }
+        );
+    }
+}
+
+/// Resolve batch entries to the pending participants they name. Entries that do not name exactly
+/// one pending participant, or name one named by an earlier entry, resolve to the reason they are
+/// skipped
+fn resolve_pending_participants(
+    rpc_client: &RpcClient,
+    entries: &[ParticipantEntry],
+) -> Result<Vec<Result<(Pubkey, ParticipantV2), String>>, Box<dyn std::error::Error>> {
+    let participants = get_participants(rpc_client)?;
+    let mut resolved_addresses = HashSet::new();
+
+    Ok(entries
+        .iter()
+        .map(|entry| {
+            let matches = participants
+                .iter()
+                .filter(|(address, participant)| {
+                    **address == entry.participant
+                        || participant.mainnet_identity == entry.participant
+                        || participant.testnet_identity == entry.participant
+                })
+                .collect::<Vec<_>>();
+            match matches[..] {
+                [] => Err("no matching participant".to_string()),
+                [(address, participant)] => {
+                    if participant.state != ParticipantState::Pending {
+                        Err(format!(
+                            "participant {} is {:?}",
+                            address, participant.state
+                        ))
+                    } else if !resolved_addresses.insert(*address) {
+                        Err(format!(
+                            "participant {} is named by an earlier entry",
+                            address
+                        ))
+                    } else {
+                        Ok((*address, participant.clone()))
+                    }
+                }
+                _ => Err("matches multiple participants".to_string()),
+            }
+        })
+        .collect())
+}
+
+/// Show the pending participants named by a batch file, returning them when `confirm` is set
+fn preview_pending_participants(
+    rpc_client: &RpcClient,
+    entries: &[ParticipantEntry],
+    confirm: bool,
+) -> Result<Option<Vec<Result<(Pubkey, ParticipantV2), String>>>, Box<dyn std::error::Error>> {
+    let resolved = resolve_pending_participants(rpc_client, entries)?;
+    let pending = resolved
+        .iter()
+        .filter_map(|resolved| resolved.as_ref().ok())
+        .collect::<Vec<_>>();
+
+    print_participant_table(&pending);
+    println!(
+        "{} of {} entries match pending participants",
+        pending.len(),
+        entries.len()
+    );
+    for (entry, resolved) in entries.iter().zip(&resolved) {
+        if let Err(reason) = resolved {
+            println!("Skipping {}: {}", entry.participant, reason);
+        }
+    }
+
+    if pending.is_empty() {
+        return Ok(None);
+    }
+    if !confirm {
+        println!("Add the --confirm flag to continue");
+        return Ok(None);
+    }
+    Ok(Some(resolved))
+}
+
+fn process_admin_approve_batch(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    entries: Vec<ParticipantEntry>,
+    confirm: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(entry) = entries
+        .iter()
+        .find(|entry| entry.reason.is_some() || entry.message.is_some())
+    {
+        return Err(format!(
+            "{}: reason and message only apply to `admin reject`",
+            entry.participant
+        )
+        .into());
+    }
+
+    let resolved = match preview_pending_participants(rpc_client, &entries, confirm)? {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    println!("Approving...");
+
+    let admin_addresses = admin_addresses(admin_signers);
+    let instructions = resolved
+        .iter()
+        .filter_map(|resolved| resolved.as_ref().ok())
+        .map(|(address, _)| registry_program::instruction::approve(*address, &admin_addresses))
+        .collect();
+    let results = batch_results(
+        &resolved,
+        send_and_confirm_batch(config, rpc_client, admin_signers, instructions, None),
+    );
+
+    print_batch_summary(
+        &entries
+            .iter()
+            .map(|entry| entry.participant)
+            .collect::<Vec<_>>(),
+        &results,
+        "approved",
+    );
+    Ok(())
+}
+
+fn process_admin_reject_batch(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    entries: Vec<ParticipantEntry>,
+    default_code: RejectionCode,
+    default_message: &str,
+    confirm: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let reasons = entries
+        .iter()
+        .map(|entry| {
+            RejectionReason::new(
+                entry.reason.unwrap_or(default_code),
+                entry.message.as_deref().unwrap_or(default_message),
+            )
+            .map_err(|_| format!("{}: message is too long", entry.participant))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let resolved = match preview_pending_participants(rpc_client, &entries, confirm)? {
+        Some(resolved) => resolved,
+        None => return Ok(()),
+    };
+    println!("Rejecting...");
+
+    let admin_addresses = admin_addresses(admin_signers);
+    let instructions = resolved
+        .iter()
+        .zip(reasons)
+        .filter_map(|(resolved, reason)| {
+            let (address, _) = resolved.as_ref().ok()?;
+            Some(registry_program::instruction::reject(
+                *address,
+                &admin_addresses,
+                reason,
+            ))
+        })
+        .collect();
+    let results = batch_results(
+        &resolved,
+        send_and_confirm_batch(config, rpc_client, admin_signers, instructions, None),
+    );
+
+    print_batch_summary(
+        &entries
+            .iter()
+            .map(|entry| entry.participant)
+            .collect::<Vec<_>>(),
+        &results,
+        "rejected",
+    );
+    Ok(())
+}
+
+fn process_admin_import_batch(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+    entries: Vec<ImportEntry>,
+    confirm: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let registered_identities = get_participants(rpc_client)?
+        .values()
+        .flat_map(|participant| vec![participant.mainnet_identity, participant.testnet_identity])
+        .collect::<HashSet<_>>();
+
+    let mut imported_identities = HashSet::new();
+    let resolved = entries
+        .iter()
+        .map(|entry| {
+            if entry.mainnet == entry.testnet {
+                Err("mainnet and testnet identities must differ".to_string())
+            } else if registered_identities.contains(&entry.mainnet)
+                || registered_identities.contains(&entry.testnet)
+            {
+                Err("a registration already exists with the provided identity".to_string())
+            } else if imported_identities.contains(&entry.mainnet)
+                || imported_identities.contains(&entry.testnet)
+            {
+                Err("identity is named by an earlier entry".to_string())
+            } else {
+                imported_identities.insert(entry.mainnet);
+                imported_identities.insert(entry.testnet);
+                Ok(entry)
+            }
+        })
+        .collect::<Vec<_>>();
+    let imports = resolved
+        .iter()
+        .filter_map(|resolved| resolved.as_ref().ok())
+        .collect::<Vec<_>>();
+
+    println!("{:<44} Testnet Identity", "Mainnet Identity");
+    for entry in &imports {
+        println!("{:<44} {}", entry.mainnet, entry.testnet);
+    }
+    println!(
+        "{} of {} entries can be imported",
+        imports.len(),
+        entries.len()
+    );
+    for (entry, resolved) in entries.iter().zip(&resolved) {
+        if let Err(reason) = resolved {
+            println!("Skipping {}: {}", entry.mainnet, reason);
+        }
+    }
+
+    if imports.is_empty() {
+        return Ok(());
+    }
+    if !confirm {
+        println!("Add the --confirm flag to continue");
+        return Ok(());
+    }
+    println!("Importing...");
+
+    let rent = get_participant_rent(rpc_client)?;
+    let admin_addresses = admin_addresses(admin_signers);
+    let instructions = imports
+        .iter()
+        .map(|entry| {
+            registry_program::instruction::import(
+                config.default_signer.pubkey(),
+                &admin_addresses,
+                Participant {
+                    state: ParticipantState::Approved,
+                    testnet_identity: entry.testnet,
+                    mainnet_identity: entry.mainnet,
+                },
+            )
+        })
+        .collect();
+    let results = batch_results(
+        &resolved,
+        send_and_confirm_batch(config, rpc_client, admin_signers, instructions, Some(rent)),
+    );
+
+    print_batch_summary(
+        &entries
+            .iter()
+            .map(|entry| entry.mainnet)
+            .collect::<Vec<_>>(),
+        &results,
+        "imported",
+    );
+    Ok(())
+}
+
+fn process_admin_migrate(
+    config: &Config,
+    rpc_client: &RpcClient,
+    admin_signers: &[Box<dyn Signer>],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let rent = get_participant_rent(rpc_client)?;
+    let participants = get_participants(rpc_client)?
+        .into_iter()
+        .filter(|(address, participant)| check_migrated(address, participant).is_err())
+        .collect::<Vec<_>>();
+
+    let mut failures = 0;
+    for (address, participant) in &participants {
+        println!("Migrating {}", address);
+        print_participant(participant);
+
+        let message = Message::new(
+            &[registry_program::instruction::migrate(
+                config.default_signer.pubkey(),
+                &admin_addresses(admin_signers),
+                *address,
+                &Participant::from(participant),
+            )],
+            Some(&config.default_signer.pubkey()),
+        );
+
+        // Another participant with the same mainnet or testnet identity may already have been
+        // migrated. These are left behind for an admin to resolve
+        if let Err(err) = send_and_confirm_message(
+            rpc_client,
+            message,
+            admin_and_fee_payer_signers(config, admin_signers),
+            Some(rent),
+        ) {
+            eprintln!("Failed to migrate {}: {}", address, err);
+            failures += 1;
+        }
+        println!();
+    }
+
+    println!(
+        "{} of {} participants migrated",
+        participants.len() - failures,
+        participants.len()
+    );
+    Ok(())
+}
+
+fn process_admin_initialize_config(
     config: &Config,
     rpc_client: &RpcClient,
-    admin_signer: Box<dyn Signer>,
-    participant_address: Pubkey,
+    admin_signers: &[Box<dyn Signer>],
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let participants = get_participants(rpc_client)?;
-    let participant = participants
-        .get(&participant_address)
-        .ok_or_else(|| format!("Participant {} does not exist", participant_address))?;
-
-    print_participant(&participant);
-    println!("Rejecting...");
+    if get_registry_config(rpc_client)?.is_some() {
+        return Err("The registry config account is already initialized".into());
+    }
+    let admin = admin_addresses(admin_signers)[0];
 
+    let rent =
+        rpc_client.get_minimum_balance_for_rent_exemption(RegistryConfig::get_packed_len())?;
     let message = Message::new(
-        &[registry_program::instruction::reject(
-            participant_address,
-            admin_signer.pubkey(),
+        &[registry_program::instruction::initialize_config(
+            config.default_signer.pubkey(),
+            admin,
         )],
         Some(&config.default_signer.pubkey()),
     );
@@ -303,66 +1252,54 @@ fn process_admin_reject(
     send_and_confirm_message(
         rpc_client,
         message,
-        [admin_signer.deref(), config.default_signer.deref()],
-        None,
+        admin_and_fee_payer_signers(config, &admin_signers[..1]),
+        Some(rent),
     )
 }
 
-fn process_admin_import(
+fn process_admin_update_config(
     config: &Config,
     rpc_client: &RpcClient,
-    admin_signer: Box<dyn Signer>,
-    mainnet_identity: Pubkey,
-    testnet_identity: Pubkey,
+    admin_signers: &[Box<dyn Signer>],
+    instruction: Instruction,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let participants = get_participants_with_identity(
-        rpc_client,
-        [mainnet_identity, testnet_identity]
-            .iter()
-            .collect::<HashSet<_>>(),
-    )?;
-
-    if !participants.is_empty() {
-        return Err("A registration already exists with the provided identity".into());
+    if get_registry_config(rpc_client)?.is_none() {
+        return Err("The registry config account is not initialized".into());
     }
 
-    let rent = rpc_client.get_minimum_balance_for_rent_exemption(Participant::get_packed_len())?;
-    let participant: Box<dyn Signer> = Box::new(Keypair::new());
-
-    let message = Message::new(
-        &[
-            system_instruction::create_account(
-                &config.default_signer.pubkey(),
-                &participant.pubkey(),
-                rent,
-                Participant::get_packed_len() as u64,
-                &registry_program::id(),
-            ),
-            registry_program::instruction::rewrite(
-                participant.pubkey(),
-                admin_signer.pubkey(),
-                Participant {
-                    state: ParticipantState::Approved,
-                    testnet_identity,
-                    mainnet_identity,
-                },
-            ),
-        ],
-        Some(&config.default_signer.pubkey()),
-    );
+    let message = Message::new(&[instruction], Some(&config.default_signer.pubkey()));
 
     send_and_confirm_message(
         rpc_client,
         message,
-        [
-            participant.deref(),
-            admin_signer.deref(),
-            config.default_signer.deref(),
-        ],
-        Some(rent),
+        admin_and_fee_payer_signers(config, admin_signers),
+        None,
     )
 }
 
+fn process_admin_show_config(
+    _config: &Config,
+    rpc_client: &RpcClient,
+) -> Result<(), Box<dyn std::error::Error>> {
+    match get_registry_config(rpc_client)? {
+        Some(registry_config) => {
+            println!(
+                "Admin threshold: {} of {}",
+                registry_config.threshold,
+                registry_config.admins.len()
+            );
+            for admin in &registry_config.admins {
+                println!("Admin: {}", admin);
+            }
+        }
+        None => {
+            println!("Registry config account is not initialized");
+            println!("Admin: {}", registry_program::admin::id());
+        }
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app_matches = App::new(crate_name!())
@@ -449,6 +1386,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .index(1)
                         .required(true)
                         .help("Testnet or Mainnet validator identity"),
+                )
+                .arg(
+                    Arg::with_name("output_format")
+                        .long("output")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .possible_values(&["json", "yaml", "csv"])
+                        .help("Return information in the specified output format"),
+                )
+                .arg(
+                    Arg::with_name("with_context")
+                        .long("with-context")
+                        .help(
+                            "Include the vote account and activated stake of each validator \
+                             identity on the cluster",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("bot_db")
+                        .long("bot-db")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .requires("with_context")
+                        .help(
+                            "Include the latest stake-o-matic classification of each validator \
+                             identity, read from the `data-<cluster>` directory under the --db-path \
+                             of the bot. Only the yaml db backend is supported",
+                        ),
                 ),
         )
         .subcommand(
@@ -469,6 +1434,93 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .help("Add the --confirm flag to continue when you're ready to continue"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("update-identity")
+                .about("Replace the mainnet or testnet validator identity of your registration")
+                .arg(
+                    Arg::with_name("network")
+                        .long("network")
+                        .value_name("NETWORK")
+                        .takes_value(true)
+                        .possible_values(&["mainnet", "testnet"])
+                        .required(true)
+                        .help("Network of the validator identity to replace"),
+                )
+                .arg(
+                    Arg::with_name("current")
+                        .long("current")
+                        .validator(is_valid_signer)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("Current validator identity"),
+                )
+                .arg(
+                    Arg::with_name("new")
+                        .long("new")
+                        .validator(is_valid_signer)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .required(true)
+                        .help("New validator identity"),
+                )
+                .arg(
+                    Arg::with_name("authority")
+                        .long("authority")
+                        .validator(is_valid_signer)
+                        .value_name("KEYPAIR")
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help(
+                            "Administration authority that signs to keep an approved \
+                             registration approved. Without it, an approved registration \
+                             returns to the Pending state for review",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("confirm")
+                        .long("confirm")
+                        .help("Add the --confirm flag to continue when you're ready to continue"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("set-info-url")
+                .about("Set or clear the info URL of your registration")
+                .arg(
+                    Arg::with_name("identity")
+                        .validator(is_valid_signer)
+                        .value_name("KEYPAIR")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Testnet or Mainnet validator identity"),
+                )
+                .arg(
+                    Arg::with_name("url")
+                        .value_name("URL")
+                        .takes_value(true)
+                        .index(2)
+                        .validator(|url| {
+                            InfoUrl::new(&url)
+                                .map(|_| ())
+                                .map_err(|_| "URL is too long".to_string())
+                        })
+                        .help("Info URL. Omit to clear the current URL"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("history")
+                .about("Show the state changes of a registration")
+                .arg(
+                    Arg::with_name("identity")
+                        .validator(is_valid_pubkey)
+                        .value_name("ADDRESS")
+                        .takes_value(true)
+                        .index(1)
+                        .required(true)
+                        .help("Testnet or Mainnet validator identity"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("list")
                 .about("List registrations")
@@ -476,9 +1528,42 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     Arg::with_name("state")
                         .long("state")
                         .value_name("STATE")
-                        .possible_values(&["all", "pending", "approved", "rejected"])
+                        .multiple(true)
+                        .number_of_values(1)
+                        .possible_values(&["all", "pending", "approved", "rejected", "suspended"])
                         .default_value("all")
-                        .help("Restrict the list to registrations in the specified state"),
+                        .help(
+                            "Restrict the list to registrations in the specified state. \
+                             Specify it multiple times to list several states",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("output_format")
+                        .long("output")
+                        .value_name("FORMAT")
+                        .takes_value(true)
+                        .possible_values(&["json", "yaml", "csv"])
+                        .help("Return information in the specified output format"),
+                )
+                .arg(
+                    Arg::with_name("with_context")
+                        .long("with-context")
+                        .help(
+                            "Include the vote account and activated stake of each validator \
+                             identity on the cluster",
+                        ),
+                )
+                .arg(
+                    Arg::with_name("bot_db")
+                        .long("bot-db")
+                        .value_name("PATH")
+                        .takes_value(true)
+                        .requires("with_context")
+                        .help(
+                            "Include the latest stake-o-matic classification of each validator \
+                             identity, read from the `data-<cluster>` directory under the --db-path \
+                             of the bot. Only the yaml db backend is supported",
+                        ),
                 ),
         )
         .subcommand(
@@ -491,7 +1576,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                         .long("authority")
                         .validator(is_valid_signer)
                         .value_name("KEYPAIR")
-                        .help("Administration authority"),
+                        .multiple(true)
+                        .number_of_values(1)
+                        .help(
+                            "Administration authority. Specify it multiple times to meet \
+                             the admin threshold of the registry config",
+                        ),
                 )
                 .subcommand(
                     SubCommand::with_name("approve")
@@ -502,13 +1592,86 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .value_name("ADDRESS")
                                 .takes_value(true)
                                 .index(1)
-                                .required(true)
+                                .required_unless("from_file")
                                 .help("Participant address"),
+                        )
+                        .arg(
+                            Arg::with_name("from_file")
+                                .long("from-file")
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .conflicts_with("participant")
+                                .help(
+                                    "YAML file listing the participants to approve, \
+                                     one `- participant: ADDRESS` entry per participant",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("confirm")
+                                .long("confirm")
+                                .requires("from_file")
+                                .help("Add the --confirm flag when you're ready to continue"),
                         ),
                 )
                 .subcommand(
                     SubCommand::with_name("reject")
                         .about("Reject a participant")
+                        .arg(
+                            Arg::with_name("participant")
+                                .validator(is_valid_pubkey)
+                                .value_name("ADDRESS")
+                                .takes_value(true)
+                                .index(1)
+                                .required_unless("from_file")
+                                .help("Participant address"),
+                        )
+                        .arg(
+                            Arg::with_name("from_file")
+                                .long("from-file")
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .conflicts_with("participant")
+                                .help(
+                                    "YAML file listing the participants to reject, \
+                                     one `- participant: ADDRESS` entry per participant with \
+                                     optional `reason` and `message` fields that override \
+                                     --reason and --message",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("confirm")
+                                .long("confirm")
+                                .requires("from_file")
+                                .help("Add the --confirm flag when you're ready to continue"),
+                        )
+                        .arg(
+                            Arg::with_name("reason")
+                                .long("reason")
+                                .value_name("REASON")
+                                .takes_value(true)
+                                .possible_values(REJECTION_CODE_NAMES)
+                                .default_value("unspecified")
+                                .help("Reason for the rejection"),
+                        )
+                        .arg(
+                            Arg::with_name("message")
+                                .long("message")
+                                .value_name("TEXT")
+                                .takes_value(true)
+                                .validator(|text| {
+                                    RejectionReason::new(RejectionCode::Other, &text)
+                                        .map(|_| ())
+                                        .map_err(|_| "Message is too long".to_string())
+                                })
+                                .help("Short explanation of the rejection"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("suspend")
+                        .about(
+                            "Temporarily suspend an approved participant. \
+                             Use `approve` to lift the suspension early",
+                        )
                         .arg(
                             Arg::with_name("participant")
                                 .validator(is_valid_pubkey)
@@ -517,6 +1680,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .index(1)
                                 .required(true)
                                 .help("Participant address"),
+                        )
+                        .arg(
+                            Arg::with_name("until_epoch")
+                                .long("until-epoch")
+                                .value_name("EPOCH")
+                                .takes_value(true)
+                                .validator(is_parsable::<Epoch>)
+                                .required(true)
+                                .help("Last epoch of the suspension"),
                         ),
                 )
                 .subcommand(
@@ -528,7 +1700,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .validator(is_valid_pubkey)
                                 .value_name("ADDRESS")
                                 .takes_value(true)
-                                .required(true)
+                                .required_unless("from_file")
                                 .help("Testnet validator identity"),
                         )
                         .arg(
@@ -537,8 +1709,77 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                                 .validator(is_valid_pubkey)
                                 .value_name("ADDRESS")
                                 .takes_value(true)
-                                .required(true)
+                                .required_unless("from_file")
                                 .help("Mainnet validator identity"),
+                        )
+                        .arg(
+                            Arg::with_name("from_file")
+                                .long("from-file")
+                                .value_name("PATH")
+                                .takes_value(true)
+                                .conflicts_with_all(&["testnet", "mainnet"])
+                                .help(
+                                    "YAML file listing the participants to import, \
+                                     one `- mainnet: ADDRESS` entry with a `testnet: ADDRESS` \
+                                     field per participant",
+                                ),
+                        )
+                        .arg(
+                            Arg::with_name("confirm")
+                                .long("confirm")
+                                .requires("from_file")
+                                .help("Add the --confirm flag when you're ready to continue"),
+                        ),
+                )
+                .subcommand(SubCommand::with_name("migrate").about(
+                    "Move participants to the account address derived from their mainnet \
+                         validator identity",
+                ))
+                .subcommand(
+                    SubCommand::with_name("show-config")
+                        .about("Display the admins of the registry config"),
+                )
+                .subcommand(SubCommand::with_name("init-config").about(
+                    "Create the registry config account, with the compiled-in admin \
+                         authority as its only admin",
+                ))
+                .subcommand(
+                    SubCommand::with_name("add-admin")
+                        .about("Add an admin to the registry config")
+                        .arg(
+                            Arg::with_name("admin")
+                                .validator(is_valid_pubkey)
+                                .value_name("ADDRESS")
+                                .takes_value(true)
+                                .index(1)
+                                .required(true)
+                                .help("Admin address"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove-admin")
+                        .about("Remove an admin from the registry config")
+                        .arg(
+                            Arg::with_name("admin")
+                                .validator(is_valid_pubkey)
+                                .value_name("ADDRESS")
+                                .takes_value(true)
+                                .index(1)
+                                .required(true)
+                                .help("Admin address"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("set-threshold")
+                        .about("Set the number of admin authorities required for admin commands")
+                        .arg(
+                            Arg::with_name("threshold")
+                                .validator(is_parsable::<u8>)
+                                .value_name("COUNT")
+                                .takes_value(true)
+                                .index(1)
+                                .required(true)
+                                .help("Admin threshold"),
                         ),
                 ),
         )
@@ -617,7 +1858,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         ("status", Some(arg_matches)) => {
             let identity = pubkey_of(arg_matches, "identity").unwrap();
-            process_status(&config, &rpc_client, identity)?;
+            let output_format = arg_matches
+                .value_of("output_format")
+                .and_then(OutputFormat::from_name);
+            let context = get_identity_context(&rpc_client, arg_matches)?;
+            process_status(&config, &rpc_client, identity, output_format, context)?;
         }
         ("withdraw", Some(arg_matches)) => {
             let confirm = arg_matches.is_present("confirm");
@@ -632,50 +1877,241 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             process_withdraw(&config, &rpc_client, identity_signer, confirm)?;
         }
-        ("list", Some(arg_matches)) => {
-            let state = match value_t_or_exit!(arg_matches, "state", String).as_str() {
-                "all" => None,
-                "pending" => Some(ParticipantState::Pending),
-                "rejected" => Some(ParticipantState::Rejected),
-                "approved" => Some(ParticipantState::Approved),
+        ("update-identity", Some(arg_matches)) => {
+            let network = match value_t_or_exit!(arg_matches, "network", String).as_str() {
+                "mainnet" => Network::Mainnet,
+                "testnet" => Network::Testnet,
+                _ => unreachable!(),
+            };
+            let confirm = arg_matches.is_present("confirm");
+            let current_identity_signer =
+                match signer_of(arg_matches, "current", &mut wallet_manager) {
+                    Err(err) => {
+                        eprintln!("Failed to parse current identity: {}", err);
+                        exit(1);
+                    }
+                    Ok((Some(signer), _)) => signer,
+                    _ => unreachable!(),
+                };
+            let new_identity_signer = match signer_of(arg_matches, "new", &mut wallet_manager) {
+                Err(err) => {
+                    eprintln!("Failed to parse new identity: {}", err);
+                    exit(1);
+                }
+                Ok((Some(signer), _)) => signer,
                 _ => unreachable!(),
             };
 
-            process_list(&config, &rpc_client, state)?;
+            let admin_signers = arg_matches
+                .values_of("authority")
+                .map(|paths| {
+                    paths
+                        .map(|path| {
+                            signer_from_path(arg_matches, path, "authority", &mut wallet_manager)
+                                .unwrap_or_else(|err| {
+                                    eprintln!("Failed to parse admin authority: {}", err);
+                                    exit(1);
+                                })
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default();
+            if !admin_signers.is_empty() {
+                if let Err(err) = check_admin_signers(&rpc_client, &admin_signers) {
+                    eprintln!("Invalid admin authority: {}", err);
+                    exit(1);
+                }
+            }
+
+            process_update_identity(
+                &config,
+                &rpc_client,
+                network,
+                current_identity_signer,
+                new_identity_signer,
+                admin_signers,
+                confirm,
+            )?;
         }
-        ("admin", Some(admin_matches)) => {
-            let admin_signer = match signer_of(admin_matches, "authority", &mut wallet_manager) {
+        ("set-info-url", Some(arg_matches)) => {
+            let identity_signer = match signer_of(arg_matches, "identity", &mut wallet_manager) {
                 Err(err) => {
-                    eprintln!("Failed to parse admin authority: {}", err);
+                    eprintln!("Failed to parse identity: {}", err);
                     exit(1);
                 }
                 Ok((Some(signer), _)) => signer,
                 _ => unreachable!(),
             };
+            let info_url = InfoUrl::new(arg_matches.value_of("url").unwrap_or("")).unwrap();
+
+            process_set_info_url(&config, &rpc_client, identity_signer, info_url)?;
+        }
+        ("history", Some(arg_matches)) => {
+            let identity = pubkey_of(arg_matches, "identity").unwrap();
+            process_history(&rpc_client, identity)?;
+        }
+        ("list", Some(arg_matches)) => {
+            let states = values_t_or_exit!(arg_matches, "state", String);
+            let states = if states.iter().any(|state| state == "all") {
+                vec![]
+            } else {
+                states
+                    .iter()
+                    .map(|state| match state.as_str() {
+                        "pending" => ParticipantState::Pending,
+                        "rejected" => ParticipantState::Rejected,
+                        "approved" => ParticipantState::Approved,
+                        "suspended" => ParticipantState::Suspended { until_epoch: 0 },
+                        _ => unreachable!(),
+                    })
+                    .collect()
+            };
+            let output_format = arg_matches
+                .value_of("output_format")
+                .and_then(OutputFormat::from_name);
+            let context = get_identity_context(&rpc_client, arg_matches)?;
+
+            process_list(&config, &rpc_client, &states, output_format, context)?;
+        }
+        ("admin", Some(admin_matches)) => {
+            if let ("show-config", _) = admin_matches.subcommand() {
+                return process_admin_show_config(&config, &rpc_client);
+            }
 
-            if admin_signer.pubkey() != registry_program::admin::id() {
-                eprintln!("Invalid admin authority");
+            let admin_signers = admin_matches
+                .values_of("authority")
+                .unwrap_or_else(|| {
+                    eprintln!("--authority is required");
+                    exit(1);
+                })
+                .map(|path| {
+                    signer_from_path(admin_matches, path, "authority", &mut wallet_manager)
+                        .unwrap_or_else(|err| {
+                            eprintln!("Failed to parse admin authority: {}", err);
+                            exit(1);
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            if let Err(err) = check_admin_signers(&rpc_client, &admin_signers) {
+                eprintln!("Invalid admin authority: {}", err);
                 exit(1);
             }
 
             match admin_matches.subcommand() {
                 ("approve", Some(arg_matches)) => {
-                    let participant = pubkey_of(arg_matches, "participant").unwrap();
-                    process_admin_approve(&config, &rpc_client, admin_signer, participant)?;
+                    if let Some(path) = arg_matches.value_of("from_file") {
+                        process_admin_approve_batch(
+                            &config,
+                            &rpc_client,
+                            &admin_signers,
+                            read_batch_file(Path::new(path))?,
+                            arg_matches.is_present("confirm"),
+                        )?;
+                    } else {
+                        let participant = pubkey_of(arg_matches, "participant").unwrap();
+                        process_admin_approve(&config, &rpc_client, &admin_signers, participant)?;
+                    }
                 }
                 ("reject", Some(arg_matches)) => {
+                    let code =
+                        parse_rejection_code(&value_t_or_exit!(arg_matches, "reason", String))
+                            .unwrap();
+                    let message = arg_matches.value_of("message").unwrap_or("");
+                    if let Some(path) = arg_matches.value_of("from_file") {
+                        process_admin_reject_batch(
+                            &config,
+                            &rpc_client,
+                            &admin_signers,
+                            read_batch_file(Path::new(path))?,
+                            code,
+                            message,
+                            arg_matches.is_present("confirm"),
+                        )?;
+                    } else {
+                        let participant = pubkey_of(arg_matches, "participant").unwrap();
+                        let reason = RejectionReason::new(code, message).unwrap();
+                        process_admin_reject(
+                            &config,
+                            &rpc_client,
+                            &admin_signers,
+                            participant,
+                            reason,
+                        )?;
+                    }
+                }
+                ("suspend", Some(arg_matches)) => {
                     let participant = pubkey_of(arg_matches, "participant").unwrap();
-                    process_admin_reject(&config, &rpc_client, admin_signer, participant)?;
+                    let until_epoch = value_t_or_exit!(arg_matches, "until_epoch", Epoch);
+                    process_admin_suspend(
+                        &config,
+                        &rpc_client,
+                        &admin_signers,
+                        participant,
+                        until_epoch,
+                    )?;
                 }
                 ("import", Some(arg_matches)) => {
-                    let testnet_identity = pubkey_of(arg_matches, "testnet").unwrap();
-                    let mainnet_identity = pubkey_of(arg_matches, "mainnet").unwrap();
-                    process_admin_import(
+                    if let Some(path) = arg_matches.value_of("from_file") {
+                        process_admin_import_batch(
+                            &config,
+                            &rpc_client,
+                            &admin_signers,
+                            read_batch_file(Path::new(path))?,
+                            arg_matches.is_present("confirm"),
+                        )?;
+                    } else {
+                        let testnet_identity = pubkey_of(arg_matches, "testnet").unwrap();
+                        let mainnet_identity = pubkey_of(arg_matches, "mainnet").unwrap();
+                        process_admin_import(
+                            &config,
+                            &rpc_client,
+                            &admin_signers,
+                            mainnet_identity,
+                            testnet_identity,
+                        )?;
+                    }
+                }
+                ("migrate", Some(_)) => {
+                    process_admin_migrate(&config, &rpc_client, &admin_signers)?;
+                }
+                ("init-config", Some(_)) => {
+                    process_admin_initialize_config(&config, &rpc_client, &admin_signers)?;
+                }
+                ("add-admin", Some(arg_matches)) => {
+                    let admin = pubkey_of(arg_matches, "admin").unwrap();
+                    process_admin_update_config(
+                        &config,
+                        &rpc_client,
+                        &admin_signers,
+                        registry_program::instruction::add_admin(
+                            admin,
+                            &admin_addresses(&admin_signers),
+                        ),
+                    )?;
+                }
+                ("remove-admin", Some(arg_matches)) => {
+                    let admin = pubkey_of(arg_matches, "admin").unwrap();
+                    process_admin_update_config(
+                        &config,
+                        &rpc_client,
+                        &admin_signers,
+                        registry_program::instruction::remove_admin(
+                            admin,
+                            &admin_addresses(&admin_signers),
+                        ),
+                    )?;
+                }
+                ("set-threshold", Some(arg_matches)) => {
+                    let threshold = value_t_or_exit!(arg_matches, "threshold", u8);
+                    process_admin_update_config(
                         &config,
                         &rpc_client,
-                        admin_signer,
-                        mainnet_identity,
-                        testnet_identity,
+                        &admin_signers,
+                        registry_program::instruction::set_admin_threshold(
+                            threshold,
+                            &admin_addresses(&admin_signers),
+                        ),
                     )?;
                 }
                 _ => unreachable!(),
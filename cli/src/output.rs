@@ -0,0 +1,304 @@
+use {
+    crate::batch::{parse_rejection_code, REJECTION_CODE_NAMES},
+    registry_cli::{BotClassification, VoteAccountInfo},
+    registry_program::state::{ParticipantState, ParticipantV2},
+    serde::Serialize,
+    solana_sdk::{
+        clock::{Epoch, Slot},
+        pubkey::Pubkey,
+    },
+    std::{collections::HashMap, error},
+};
+
+/// Machine-readable output formats of `list` and `status`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    Yaml,
+    Csv,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "json" => Some(Self::Json),
+            "yaml" => Some(Self::Yaml),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// Cluster and stake-o-matic context of validator identities
+pub struct IdentityContext {
+    pub vote_accounts: HashMap<Pubkey, VoteAccountInfo>,
+
+    // `None` unless a bot database was provided
+    pub bot_classifications: Option<HashMap<Pubkey, BotClassification>>,
+}
+
+#[derive(Serialize)]
+pub struct ClassificationRecord {
+    pub epoch: Epoch,
+    pub stake_state: String,
+    pub stake_state_reason: String,
+}
+
+#[derive(Serialize)]
+pub struct IdentityRecord {
+    pub vote_account: Option<String>,
+    pub activated_stake: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bot_classification: Option<ClassificationRecord>,
+}
+
+impl IdentityRecord {
+    fn new(identity: &Pubkey, context: &IdentityContext) -> Self {
+        let vote_account = context.vote_accounts.get(identity);
+        Self {
+            vote_account: vote_account.map(|info| info.vote_account.to_string()),
+            activated_stake: vote_account
+                .map(|info| info.activated_stake)
+                .unwrap_or_default(),
+            bot_classification: context.bot_classifications.as_ref().and_then(
+                |bot_classifications| {
+                    bot_classifications
+                        .get(identity)
+                        .map(|classification| ClassificationRecord {
+                            epoch: classification.epoch,
+                            stake_state: classification.stake_state.clone(),
+                            stake_state_reason: classification.stake_state_reason.clone(),
+                        })
+                },
+            ),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ParticipantRecord {
+    pub address: String,
+    pub state: String,
+    pub suspended_until_epoch: Option<Epoch>,
+    pub mainnet_identity: String,
+    pub testnet_identity: String,
+    pub application_slot: Option<Slot>,
+    pub application_epoch: Option<Epoch>,
+    pub state_change_slot: Option<Slot>,
+    pub rejection_code: Option<String>,
+    pub rejection_message: Option<String>,
+    pub info_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mainnet_context: Option<IdentityRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub testnet_context: Option<IdentityRecord>,
+}
+
+impl ParticipantRecord {
+    pub fn new(
+        address: &Pubkey,
+        participant: &ParticipantV2,
+        context: Option<&IdentityContext>,
+    ) -> Self {
+        let (state, suspended_until_epoch) = match participant.state {
+            ParticipantState::Uninitialized => ("uninitialized", None),
+            ParticipantState::Pending => ("pending", None),
+            ParticipantState::Rejected => ("rejected", None),
+            ParticipantState::Approved => ("approved", None),
+            ParticipantState::Suspended { until_epoch } => ("suspended", Some(until_epoch)),
+        };
+        let applied = participant.application_slot > 0;
+        let rejected = participant.state == ParticipantState::Rejected;
+
+        Self {
+            address: address.to_string(),
+            state: state.to_string(),
+            suspended_until_epoch,
+            mainnet_identity: participant.mainnet_identity.to_string(),
+            testnet_identity: participant.testnet_identity.to_string(),
+            application_slot: Some(participant.application_slot).filter(|_| applied),
+            application_epoch: Some(participant.application_epoch).filter(|_| applied),
+            state_change_slot: Some(participant.state_change_slot).filter(|_| applied),
+            rejection_code: REJECTION_CODE_NAMES
+                .iter()
+                .find(|name| parse_rejection_code(name) == Some(participant.rejection_reason.code))
+                .map(|name| name.to_string())
+                .filter(|_| rejected),
+            rejection_message: Some(participant.rejection_reason.text())
+                .filter(|text| rejected && !text.is_empty()),
+            info_url: participant.info_url.url(),
+            mainnet_context: context
+                .map(|context| IdentityRecord::new(&participant.mainnet_identity, context)),
+            testnet_context: context
+                .map(|context| IdentityRecord::new(&participant.testnet_identity, context)),
+        }
+    }
+
+    fn csv_header(with_context: bool) -> Vec<String> {
+        let mut header = [
+            "address",
+            "state",
+            "suspended_until_epoch",
+            "mainnet_identity",
+            "testnet_identity",
+            "application_slot",
+            "application_epoch",
+            "state_change_slot",
+            "rejection_code",
+            "rejection_message",
+            "info_url",
+        ]
+        .iter()
+        .map(|column| column.to_string())
+        .collect::<Vec<_>>();
+        if with_context {
+            for network in &["mainnet", "testnet"] {
+                for column in &[
+                    "vote_account",
+                    "activated_stake",
+                    "classification_epoch",
+                    "stake_state",
+                    "stake_state_reason",
+                ] {
+                    header.push(format!("{}_{}", network, column));
+                }
+            }
+        }
+        header
+    }
+
+    fn csv_row(&self) -> Vec<String> {
+        fn optional<T: ToString>(value: &Option<T>) -> String {
+            value
+                .as_ref()
+                .map(|value| value.to_string())
+                .unwrap_or_default()
+        }
+
+        let mut row = vec![
+            self.address.clone(),
+            self.state.clone(),
+            optional(&self.suspended_until_epoch),
+            self.mainnet_identity.clone(),
+            self.testnet_identity.clone(),
+            optional(&self.application_slot),
+            optional(&self.application_epoch),
+            optional(&self.state_change_slot),
+            optional(&self.rejection_code),
+            optional(&self.rejection_message),
+            optional(&self.info_url),
+        ];
+        for context in [&self.mainnet_context, &self.testnet_context]
+            .iter()
+            .filter_map(|context| context.as_ref())
+        {
+            let classification = context.bot_classification.as_ref();
+            row.extend(vec![
+                optional(&context.vote_account),
+                context.activated_stake.to_string(),
+                optional(&classification.map(|classification| classification.epoch)),
+                optional(&classification.map(|classification| &classification.stake_state)),
+                optional(&classification.map(|classification| &classification.stake_state_reason)),
+            ]);
+        }
+        row
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            if field.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Format participant records. A CSV table has a header line and one line per record
+pub fn format_records(
+    records: &[ParticipantRecord],
+    output_format: OutputFormat,
+    with_context: bool,
+) -> Result<String, Box<dyn error::Error>> {
+    Ok(match output_format {
+        OutputFormat::Json => serde_json::to_string_pretty(records)?,
+        OutputFormat::Yaml => serde_yaml::to_string(records)?,
+        OutputFormat::Csv => {
+            let mut lines = vec![csv_line(&ParticipantRecord::csv_header(with_context))];
+            lines.extend(records.iter().map(|record| csv_line(&record.csv_row())));
+            lines.join("\n")
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        registry_program::state::{InfoUrl, RejectionCode, RejectionReason},
+    };
+
+    #[test]
+    fn test_format_records() {
+        let address = Pubkey::new_unique();
+        let participant = ParticipantV2 {
+            state: ParticipantState::Rejected,
+            mainnet_identity: Pubkey::new_unique(),
+            testnet_identity: Pubkey::new_unique(),
+            application_slot: 100,
+            application_epoch: 1,
+            state_change_slot: 200,
+            rejection_reason: RejectionReason::new(RejectionCode::Ineligible, "late, \"sorry\"")
+                .unwrap(),
+            info_url: InfoUrl::default(),
+        };
+        let context = IdentityContext {
+            vote_accounts: vec![(
+                participant.mainnet_identity,
+                VoteAccountInfo {
+                    vote_account: Pubkey::new_unique(),
+                    activated_stake: 42,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            bot_classifications: None,
+        };
+
+        let records = vec![ParticipantRecord::new(
+            &address,
+            &participant,
+            Some(&context),
+        )];
+        assert_eq!(records[0].state, "rejected");
+        assert_eq!(records[0].rejection_code.as_deref(), Some("ineligible"));
+
+        let csv = format_records(&records, OutputFormat::Csv, true).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0].split(',').count(),
+            ParticipantRecord::csv_header(true).len()
+        );
+        assert!(lines[1].starts_with(&format!("{},rejected,,", address)));
+        assert!(lines[1].contains(",ineligible,\"late, \"\"sorry\"\"\",,"));
+        assert!(lines[1].contains(",42,,,,,0,,,"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&format_records(&records, OutputFormat::Json, true).unwrap())
+                .unwrap();
+        assert_eq!(json[0]["mainnet_context"]["activated_stake"], 42);
+        assert_eq!(
+            json[0]["testnet_context"]["vote_account"],
+            serde_json::Value::Null
+        );
+        assert!(json[0]["mainnet_context"]
+            .get("bot_classification")
+            .is_none());
+    }
+}
@@ -0,0 +1,153 @@
+//! Program errors
+
+use solana_program::program_error::ProgramError;
+use std::fmt;
+
+/// Errors returned by the registry program as `ProgramError::Custom` codes
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RegistryError {
+    /// The participant account is not owned by the registry program
+    NotParticipantAccount,
+
+    /// The participant account was not passed as writable
+    ParticipantNotWritable,
+
+    /// The participant account does not hold enough lamports to be rent exempt
+    ParticipantNotRentExempt,
+
+    /// The participant account is not initialized
+    UninitializedParticipant,
+
+    /// The refundee is one of the accounts being closed
+    InvalidRefundee,
+
+    /// The participant account is not at the address derived from its mainnet identity, or is
+    /// in the original `Participant` layout
+    ParticipantNotMigrated,
+
+    /// The testnet identity index account is not the one of the participant
+    InvalidTestnetIdentityIndex,
+
+    /// The signer is not a validator identity of the participant
+    UnauthorizedIdentity,
+
+    /// The registry config account is not at its derived address
+    InvalidConfigAccount,
+
+    /// The registry config account is already initialized
+    ConfigAlreadyInitialized,
+
+    /// The registry config account is not initialized
+    ConfigNotInitialized,
+
+    /// An admin account is not an admin of the registry
+    NotAdmin,
+
+    /// Fewer admins signed than the admin threshold requires
+    InsufficientAdminSignatures,
+
+    /// The address is already an admin
+    AlreadyAdmin,
+
+    /// The registry already has `MAX_ADMINS` admins
+    TooManyAdmins,
+
+    /// The admin threshold is zero or exceeds the number of admins
+    InvalidAdminThreshold,
+
+    /// Validator identities can only be changed by `RegistryInstruction::UpdateIdentity`
+    IdentityChangeNotAllowed,
+
+    /// The instruction does not apply to a participant in its current state
+    InvalidStateTransition,
+
+    /// The epoch has already passed
+    EpochPassed,
+}
+
+impl RegistryError {
+    /// Every error, indexed by its code
+    const ALL: &'static [Self] = &[
+        Self::NotParticipantAccount,
+        Self::ParticipantNotWritable,
+        Self::ParticipantNotRentExempt,
+        Self::UninitializedParticipant,
+        Self::InvalidRefundee,
+        Self::ParticipantNotMigrated,
+        Self::InvalidTestnetIdentityIndex,
+        Self::UnauthorizedIdentity,
+        Self::InvalidConfigAccount,
+        Self::ConfigAlreadyInitialized,
+        Self::ConfigNotInitialized,
+        Self::NotAdmin,
+        Self::InsufficientAdminSignatures,
+        Self::AlreadyAdmin,
+        Self::TooManyAdmins,
+        Self::InvalidAdminThreshold,
+        Self::IdentityChangeNotAllowed,
+        Self::InvalidStateTransition,
+        Self::EpochPassed,
+    ];
+
+    /// The error for a `ProgramError::Custom` code returned by the registry program
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+}
+
+impl From<RegistryError> for ProgramError {
+    fn from(err: RegistryError) -> Self {
+        ProgramError::Custom(err as u32)
+    }
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::NotParticipantAccount => "account is not a participant account",
+            Self::ParticipantNotWritable => "participant account is not writable",
+            Self::ParticipantNotRentExempt => "participant account is not rent exempt",
+            Self::UninitializedParticipant => "participant account is not initialized",
+            Self::InvalidRefundee => "refundee cannot be an account that is being closed",
+            Self::ParticipantNotMigrated => "participant account has not been migrated",
+            Self::InvalidTestnetIdentityIndex => {
+                "testnet identity index account does not belong to the participant"
+            }
+            Self::UnauthorizedIdentity => "signer is not a validator identity of the participant",
+            Self::InvalidConfigAccount => "account is not the registry config account",
+            Self::ConfigAlreadyInitialized => "registry config is already initialized",
+            Self::ConfigNotInitialized => "registry config is not initialized",
+            Self::NotAdmin => "signer is not a registry admin",
+            Self::InsufficientAdminSignatures => "not enough admin signatures",
+            Self::AlreadyAdmin => "address is already a registry admin",
+            Self::TooManyAdmins => "registry has the maximum number of admins",
+            Self::InvalidAdminThreshold => "admin threshold is invalid for the number of admins",
+            Self::IdentityChangeNotAllowed => {
+                "validator identities can only be changed with update-identity"
+            }
+            Self::InvalidStateTransition => "participant is not in a state that allows this",
+            Self::EpochPassed => "epoch has already passed",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+impl std::error::Error for RegistryError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes() {
+        for (code, err) in RegistryError::ALL.iter().enumerate() {
+            assert_eq!(*err as usize, code);
+            assert_eq!(RegistryError::from_code(code as u32), Some(*err));
+            assert_eq!(ProgramError::from(*err), ProgramError::Custom(code as u32));
+        }
+        assert_eq!(
+            RegistryError::from_code(RegistryError::ALL.len() as u32),
+            None
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! Program event logs
+
+use crate::state::ParticipantState;
+use solana_program::{msg, pubkey::Pubkey};
+use std::{fmt, str::FromStr};
+
+/// Prefix of event log messages
+pub const EVENT_LOG_PREFIX: &str = "registry-event:";
+
+/// Prefix the runtime adds to messages logged by a program
+const PROGRAM_LOG_PREFIX: &str = "Program log: ";
+
+/// A change in the state of a participant account, logged as a single line of the form
+/// `registry-event: participant=ADDRESS old_state=STATE new_state=STATE actors=ADDRESS,..`
+///
+/// Creating a participant account is logged as a transition from
+/// `ParticipantState::Uninitialized`, and closing one as a transition to it
+#[derive(Clone, Debug, PartialEq)]
+pub struct StateTransition {
+    pub participant: Pubkey,
+    pub old_state: ParticipantState,
+    pub new_state: ParticipantState,
+
+    /// Validator identities or admins that signed for the transition
+    pub actors: Vec<Pubkey>,
+}
+
+impl StateTransition {
+    pub fn log(&self) {
+        msg!("{}", self);
+    }
+
+    /// Parse a transaction log message, with or without the runtime's "Program log: " prefix.
+    /// Returns `None` for any other log message
+    pub fn parse(log_message: &str) -> Option<Self> {
+        let log_message = log_message
+            .strip_prefix(PROGRAM_LOG_PREFIX)
+            .unwrap_or(log_message)
+            .strip_prefix(EVENT_LOG_PREFIX)?;
+
+        let mut participant = None;
+        let mut old_state = None;
+        let mut new_state = None;
+        let mut actors = None;
+        for field in log_message.split_whitespace() {
+            let mut key_value = field.splitn(2, '=');
+            match (key_value.next()?, key_value.next()?) {
+                ("participant", value) => participant = Pubkey::from_str(value).ok(),
+                ("old_state", value) => old_state = parse_state(value),
+                ("new_state", value) => new_state = parse_state(value),
+                ("actors", value) => {
+                    actors = value
+                        .split(',')
+                        .filter(|actor| !actor.is_empty())
+                        .map(|actor| Pubkey::from_str(actor).ok())
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+
+        Some(Self {
+            participant: participant?,
+            old_state: old_state?,
+            new_state: new_state?,
+            actors: actors?,
+        })
+    }
+}
+
+impl fmt::Display for StateTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} participant={} old_state={} new_state={} actors={}",
+            EVENT_LOG_PREFIX,
+            self.participant,
+            format_state(&self.old_state),
+            format_state(&self.new_state),
+            self.actors
+                .iter()
+                .map(|actor| actor.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+fn format_state(state: &ParticipantState) -> String {
+    match state {
+        ParticipantState::Uninitialized => "uninitialized".to_string(),
+        ParticipantState::Pending => "pending".to_string(),
+        ParticipantState::Rejected => "rejected".to_string(),
+        ParticipantState::Approved => "approved".to_string(),
+        ParticipantState::Suspended { until_epoch } => format!("suspended:{}", until_epoch),
+    }
+}
+
+fn parse_state(s: &str) -> Option<ParticipantState> {
+    match s {
+        "uninitialized" => Some(ParticipantState::Uninitialized),
+        "pending" => Some(ParticipantState::Pending),
+        "rejected" => Some(ParticipantState::Rejected),
+        "approved" => Some(ParticipantState::Approved),
+        s => s
+            .strip_prefix("suspended:")
+            .and_then(|until_epoch| until_epoch.parse().ok())
+            .map(|until_epoch| ParticipantState::Suspended { until_epoch }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let transition = StateTransition {
+            participant: Pubkey::new_unique(),
+            old_state: ParticipantState::Approved,
+            new_state: ParticipantState::Suspended { until_epoch: 200 },
+            actors: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+        };
+        let log_message = transition.to_string();
+        assert_eq!(
+            StateTransition::parse(&log_message),
+            Some(transition.clone())
+        );
+        assert_eq!(
+            StateTransition::parse(&format!("Program log: {}", log_message)),
+            Some(transition)
+        );
+
+        assert_eq!(StateTransition::parse("Program log: Approve"), None);
+        assert_eq!(
+            StateTransition::parse("registry-event: participant=x old_state=pending"),
+            None
+        );
+    }
+}
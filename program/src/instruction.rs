@@ -1,35 +1,57 @@
 //! Program instructions
 
-use crate::{id, state::Participant};
+use crate::{
+    find_config_address, find_participant_address, find_testnet_identity_index_address, id,
+    state::{InfoUrl, Participant, RejectionReason},
+};
 use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use solana_program::{
+    clock::Epoch,
     instruction::{AccountMeta, Instruction},
     msg,
     program_error::ProgramError,
     program_pack::{Pack, Sealed},
     pubkey::Pubkey,
+    system_program,
 };
 
+/// Network of a validator identity
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub enum Network {
+    Mainnet,
+    Testnet,
+}
+
 /// Instructions supported by the Feature Proposal program
 #[derive(Clone, Debug, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
 pub enum RegistryInstruction {
     /// Apply for the program
     ///
-    /// On success the participant will be moved to the `ParticipantState::Pending` state
+    /// On success the participant and its testnet identity index accounts will be created, with
+    /// the participant in the `ParticipantState::Pending` state and the `ParticipantV2` layout.
+    /// Fails if either validator identity is already registered
     ///
-    /// 0. `[writable]` Uninitialized `Participant` account
+    /// 0. `[writable]` `Participant` account address, from `find_participant_address()`
     /// 1. `[signer]` Mainnet validator identity
     /// 2. `[signer]` Testnet validator identity
+    /// 3. `[writable]` `TestnetIdentityIndex` account address, from
+    ///                 `find_testnet_identity_index_address()`
+    /// 4. `[writable, signer]` Funding account for the new accounts
+    /// 5. `[]` System program
     Apply,
 
     /// Withdraw from the program
     ///
-    /// On success the participant account will be deleted and lamports in it refunded
+    /// On success the participant and its testnet identity index accounts will be deleted and
+    /// lamports in them refunded
     ///
     /// 0. `[writable]` `Participant` account in the `ParticipantState::Pending` or
     ///                 `ParticipantState::Enrolled` state
     /// 1. `[signer]` Mainnet or Testnet validator identity
     /// 2. `[writable]`  The account to receive the closed account's lamports.
+    /// 3. `[writable]` `TestnetIdentityIndex` account of the participant. A participant account
+    ///                 that has not been migrated may not have one, in which case nothing is
+    ///                 closed
     ///
     Withdraw,
 
@@ -38,28 +60,141 @@ pub enum RegistryInstruction {
     /// On success the participant will be moved to the `ParticipantState::Enrolled` state
     ///
     /// 0. `[writable]` `Participant` account in the `ParticipantState::Pending` state
-    /// 1. `[signer]` Admin account
+    /// 1. `[]` Registry config account
+    /// 2. ..2+M `[signer]` M admin accounts, where M is the admin threshold
     Approve,
 
     /// Reject a participant
     ///
-    /// On success the participant will be moved to the `ParticipantState::Rejected` state
+    /// On success the participant will be moved to the `ParticipantState::Rejected` state, with an
+    /// unspecified rejection reason. See `RejectWithReason`
     ///
     /// 0. `[writable]` `Participant` account in the `ParticipantState::Pending` or
     ///                 `ParticipantState::Enrolled` state
-    /// 1. `[signer]` Admin account
+    /// 1. `[]` Registry config account
+    /// 2. ..2+M `[signer]` M admin accounts, where M is the admin threshold
     Reject,
 
-    /// Bypass the normal workflow and rewrite a participant account to the provided state
+    /// Bypass the normal workflow and rewrite a participant account to the provided state, other
+    /// than `ParticipantState::Uninitialized`. The validator identities cannot be changed, see
+    /// `UpdateIdentity`
     ///
     /// 0. `[writable]` `Participant` account in any state
-    /// 1. `[signer]` Admin account
+    /// 1. `[]` Registry config account
+    /// 2. ..2+M `[signer]` M admin accounts, where M is the admin threshold
     Rewrite(Participant),
+
+    /// Replace the mainnet or testnet validator identity of a participant
+    ///
+    /// On success a participant in the `ParticipantState::Approved` or
+    /// `ParticipantState::Suspended` state is moved back to the `ParticipantState::Pending`
+    /// state for review, unless the admins sign to keep its state. Participants in other states
+    /// keep their state.
+    ///
+    /// Replacing the mainnet identity moves the participant to the participant account of the new
+    /// identity, and replacing the testnet identity moves its testnet identity index. The
+    /// lamports of the closed account are refunded to the funding account
+    ///
+    /// 0. `[writable]` `Participant` account in any initialized state
+    /// 1. `[signer]` Current validator identity for `network`
+    /// 2. `[signer]` New validator identity for `network`
+    /// 3. `[writable]` New `Participant` account address for `Network::Mainnet`, or new
+    ///                 `TestnetIdentityIndex` account address for `Network::Testnet`
+    /// 4. `[writable, signer]` Funding account for the new account
+    /// 5. `[]` System program
+    /// 6. `[writable]` `TestnetIdentityIndex` account of the participant
+    /// 7. Optional `[]` Registry config account, to skip the review
+    /// 8. ..8+M `[signer]` M admin accounts, where M is the admin threshold
+    UpdateIdentity { network: Network },
+
+    /// Create the registry config account, with the signing admins as its admins and an admin
+    /// threshold of 1
+    ///
+    /// Until the registry config account is initialized, admin instructions are authorized by the
+    /// compiled-in `crate::admin` key
+    ///
+    /// 0. `[writable]` Uninitialized registry config account
+    /// 1. `[writable, signer]` Funding account for the registry config account
+    /// 2. `[]` System program
+    /// 3. `[signer]` Compiled-in admin account
+    InitializeConfig,
+
+    /// Add an admin to the registry config
+    ///
+    /// 0. `[writable]` Registry config account
+    /// 1. ..1+M `[signer]` M admin accounts, where M is the admin threshold
+    AddAdmin(Pubkey),
+
+    /// Remove an admin from the registry config. The remaining admins must be able to meet the
+    /// admin threshold
+    ///
+    /// 0. `[writable]` Registry config account
+    /// 1. ..1+M `[signer]` M admin accounts, where M is the admin threshold
+    RemoveAdmin(Pubkey),
+
+    /// Set the number of admin signatures required to authorize an admin instruction
+    ///
+    /// 0. `[writable]` Registry config account
+    /// 1. ..1+M `[signer]` M admin accounts, where M is the current admin threshold
+    SetAdminThreshold(u8),
+
+    /// Create a participant in the provided state, other than `ParticipantState::Uninitialized`,
+    /// on behalf of its validators
+    ///
+    /// 0. `[writable]` `Participant` account address, from `find_participant_address()`
+    /// 1. `[]` Registry config account
+    /// 2. `[writable]` `TestnetIdentityIndex` account address, from
+    ///                 `find_testnet_identity_index_address()`
+    /// 3. `[writable, signer]` Funding account for the new accounts
+    /// 4. `[]` System program
+    /// 5. ..5+M `[signer]` M admin accounts, where M is the admin threshold
+    Import(Participant),
+
+    /// Move a participant created before participant account addresses were derived from the
+    /// mainnet validator identity to its derived address, and create its testnet identity index.
+    /// The lamports of the previous account are refunded to the funding account
+    ///
+    /// 0. `[writable]` Previous `Participant` account
+    /// 1. `[]` Registry config account
+    /// 2. `[writable]` `Participant` account address, from `find_participant_address()`
+    /// 3. `[writable]` `TestnetIdentityIndex` account address, from
+    ///                 `find_testnet_identity_index_address()`
+    /// 4. `[writable, signer]` Funding account for the new accounts
+    /// 5. `[]` System program
+    /// 6. ..6+M `[signer]` M admin accounts, where M is the admin threshold
+    Migrate,
+
+    /// Set or clear the validator info URL of a participant
+    ///
+    /// 0. `[writable]` `Participant` account in the `ParticipantV2` layout
+    /// 1. `[signer]` Mainnet or Testnet validator identity
+    SetInfoUrl(InfoUrl),
+
+    /// Suspend a participant through `until_epoch`, after which it returns to the
+    /// `ParticipantState::Approved` state. A suspended participant may be suspended again to
+    /// change `until_epoch`, or approved to lift the suspension early
+    ///
+    /// 0. `[writable]` `Participant` account in the `ParticipantV2` layout and the
+    ///                 `ParticipantState::Approved` or `ParticipantState::Suspended` state
+    /// 1. `[]` Registry config account
+    /// 2. ..2+M `[signer]` M admin accounts, where M is the admin threshold
+    Suspend { until_epoch: Epoch },
+
+    /// Reject a participant
+    ///
+    /// On success the participant will be moved to the `ParticipantState::Rejected` state, and
+    /// the reason recorded if the participant account has the `ParticipantV2` layout
+    ///
+    /// 0. `[writable]` `Participant` account in the `ParticipantState::Pending` or
+    ///                 `ParticipantState::Enrolled` state
+    /// 1. `[]` Registry config account
+    /// 2. ..2+M `[signer]` M admin accounts, where M is the admin threshold
+    RejectWithReason(RejectionReason),
 }
 
 impl Sealed for RegistryInstruction {}
 impl Pack for RegistryInstruction {
-    const LEN: usize = 66; // see `test::get_packed_len()` for justification of "66"
+    const LEN: usize = 129; // see `test::get_packed_len()` for justification of "129"
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let data = self.pack_into_vec();
@@ -86,67 +221,289 @@ impl RegistryInstruction {
 
 /// Create a `RegistryInstruction::Apply` instruction
 pub fn apply(
-    participant: Pubkey,
+    funder: Pubkey,
     mainnet_validator_identity: Pubkey,
     testnet_validator_identity: Pubkey,
 ) -> Instruction {
     Instruction {
         program_id: id(),
         accounts: vec![
-            AccountMeta::new(participant, false),
+            AccountMeta::new(
+                find_participant_address(&id(), &mainnet_validator_identity).0,
+                false,
+            ),
             AccountMeta::new_readonly(mainnet_validator_identity, true),
             AccountMeta::new_readonly(testnet_validator_identity, true),
+            AccountMeta::new(
+                find_testnet_identity_index_address(&id(), &testnet_validator_identity).0,
+                false,
+            ),
+            AccountMeta::new(funder, true),
+            AccountMeta::new_readonly(system_program::id(), false),
         ],
         data: RegistryInstruction::Apply.pack_into_vec(),
     }
 }
 
 /// Create a `RegistryInstruction::Withdraw` instruction
-pub fn withdraw(participant: Pubkey, validator_identity: Pubkey, refundee: Pubkey) -> Instruction {
+pub fn withdraw(
+    participant: &Participant,
+    validator_identity: Pubkey,
+    refundee: Pubkey,
+) -> Instruction {
+    withdraw_account(
+        find_participant_address(&id(), &participant.mainnet_identity).0,
+        participant,
+        validator_identity,
+        refundee,
+    )
+}
+
+/// Create a `RegistryInstruction::Withdraw` instruction for the participant account at
+/// `participant_address`, which may not have been migrated yet
+pub fn withdraw_account(
+    participant_address: Pubkey,
+    participant: &Participant,
+    validator_identity: Pubkey,
+    refundee: Pubkey,
+) -> Instruction {
     Instruction {
         program_id: id(),
         accounts: vec![
-            AccountMeta::new(participant, false),
+            AccountMeta::new(participant_address, false),
             AccountMeta::new_readonly(validator_identity, true),
             AccountMeta::new(refundee, false),
+            AccountMeta::new(
+                find_testnet_identity_index_address(&id(), &participant.testnet_identity).0,
+                false,
+            ),
         ],
         data: RegistryInstruction::Withdraw.pack_into_vec(),
     }
 }
 
-/// Create a `RegistryInstruction::Admin` instruction
-pub fn approve(participant: Pubkey, admin: Pubkey) -> Instruction {
+fn admin_instruction(
+    mut accounts: Vec<AccountMeta>,
+    admins: &[Pubkey],
+    instruction: RegistryInstruction,
+) -> Instruction {
+    accounts.extend(
+        admins
+            .iter()
+            .map(|admin| AccountMeta::new_readonly(*admin, true)),
+    );
+
     Instruction {
         program_id: id(),
-        accounts: vec![
+        accounts,
+        data: instruction.pack_into_vec(),
+    }
+}
+
+/// Create a `RegistryInstruction::Approve` instruction
+pub fn approve(participant: Pubkey, admins: &[Pubkey]) -> Instruction {
+    admin_instruction(
+        vec![
             AccountMeta::new(participant, false),
-            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new_readonly(find_config_address(&id()).0, false),
+        ],
+        admins,
+        RegistryInstruction::Approve,
+    )
+}
+
+/// Create a `RegistryInstruction::RejectWithReason` instruction
+pub fn reject(participant: Pubkey, admins: &[Pubkey], reason: RejectionReason) -> Instruction {
+    admin_instruction(
+        vec![
+            AccountMeta::new(participant, false),
+            AccountMeta::new_readonly(find_config_address(&id()).0, false),
+        ],
+        admins,
+        RegistryInstruction::RejectWithReason(reason),
+    )
+}
+
+/// Create a `RegistryInstruction::Suspend` instruction
+pub fn suspend(participant: Pubkey, admins: &[Pubkey], until_epoch: Epoch) -> Instruction {
+    admin_instruction(
+        vec![
+            AccountMeta::new(participant, false),
+            AccountMeta::new_readonly(find_config_address(&id()).0, false),
+        ],
+        admins,
+        RegistryInstruction::Suspend { until_epoch },
+    )
+}
+
+/// Create a `RegistryInstruction::Rewrite` instruction
+pub fn rewrite(participant: Pubkey, admins: &[Pubkey], new_state: Participant) -> Instruction {
+    admin_instruction(
+        vec![
+            AccountMeta::new(participant, false),
+            AccountMeta::new_readonly(find_config_address(&id()).0, false),
         ],
-        data: RegistryInstruction::Approve.pack_into_vec(),
+        admins,
+        RegistryInstruction::Rewrite(new_state),
+    )
+}
+
+/// Create a `RegistryInstruction::UpdateIdentity` instruction. An approved participant is
+/// returned to review unless `admins` is not empty
+pub fn update_identity(
+    funder: Pubkey,
+    participant: &Participant,
+    network: Network,
+    new_validator_identity: Pubkey,
+    admins: &[Pubkey],
+) -> Instruction {
+    let (current_validator_identity, new_address) = match network {
+        Network::Mainnet => (
+            participant.mainnet_identity,
+            find_participant_address(&id(), &new_validator_identity).0,
+        ),
+        Network::Testnet => (
+            participant.testnet_identity,
+            find_testnet_identity_index_address(&id(), &new_validator_identity).0,
+        ),
+    };
+
+    let mut accounts = vec![
+        AccountMeta::new(
+            find_participant_address(&id(), &participant.mainnet_identity).0,
+            false,
+        ),
+        AccountMeta::new_readonly(current_validator_identity, true),
+        AccountMeta::new_readonly(new_validator_identity, true),
+        AccountMeta::new(new_address, false),
+        AccountMeta::new(funder, true),
+        AccountMeta::new_readonly(system_program::id(), false),
+        AccountMeta::new(
+            find_testnet_identity_index_address(&id(), &participant.testnet_identity).0,
+            false,
+        ),
+    ];
+    if admins.is_empty() {
+        Instruction {
+            program_id: id(),
+            accounts,
+            data: RegistryInstruction::UpdateIdentity { network }.pack_into_vec(),
+        }
+    } else {
+        accounts.push(AccountMeta::new_readonly(
+            find_config_address(&id()).0,
+            false,
+        ));
+        admin_instruction(
+            accounts,
+            admins,
+            RegistryInstruction::UpdateIdentity { network },
+        )
     }
 }
 
-/// Create a `RegistryInstruction::Reject` instruction
-pub fn reject(participant: Pubkey, admin: Pubkey) -> Instruction {
+/// Create a `RegistryInstruction::InitializeConfig` instruction
+pub fn initialize_config(funder: Pubkey, admin: Pubkey) -> Instruction {
     Instruction {
         program_id: id(),
         accounts: vec![
-            AccountMeta::new(participant, false),
+            AccountMeta::new(find_config_address(&id()).0, false),
+            AccountMeta::new(funder, true),
+            AccountMeta::new_readonly(system_program::id(), false),
             AccountMeta::new_readonly(admin, true),
         ],
-        data: RegistryInstruction::Reject.pack_into_vec(),
+        data: RegistryInstruction::InitializeConfig.pack_into_vec(),
     }
 }
 
-/// Create a `RegistryInstruction::Rewrite` instruction
-pub fn rewrite(participant: Pubkey, admin: Pubkey, new_state: Participant) -> Instruction {
+/// Create a `RegistryInstruction::AddAdmin` instruction
+pub fn add_admin(admin: Pubkey, admins: &[Pubkey]) -> Instruction {
+    admin_instruction(
+        vec![AccountMeta::new(find_config_address(&id()).0, false)],
+        admins,
+        RegistryInstruction::AddAdmin(admin),
+    )
+}
+
+/// Create a `RegistryInstruction::RemoveAdmin` instruction
+pub fn remove_admin(admin: Pubkey, admins: &[Pubkey]) -> Instruction {
+    admin_instruction(
+        vec![AccountMeta::new(find_config_address(&id()).0, false)],
+        admins,
+        RegistryInstruction::RemoveAdmin(admin),
+    )
+}
+
+/// Create a `RegistryInstruction::SetAdminThreshold` instruction
+pub fn set_admin_threshold(threshold: u8, admins: &[Pubkey]) -> Instruction {
+    admin_instruction(
+        vec![AccountMeta::new(find_config_address(&id()).0, false)],
+        admins,
+        RegistryInstruction::SetAdminThreshold(threshold),
+    )
+}
+
+/// Create a `RegistryInstruction::Import` instruction
+pub fn import(funder: Pubkey, admins: &[Pubkey], participant: Participant) -> Instruction {
+    admin_instruction(
+        vec![
+            AccountMeta::new(
+                find_participant_address(&id(), &participant.mainnet_identity).0,
+                false,
+            ),
+            AccountMeta::new_readonly(find_config_address(&id()).0, false),
+            AccountMeta::new(
+                find_testnet_identity_index_address(&id(), &participant.testnet_identity).0,
+                false,
+            ),
+            AccountMeta::new(funder, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        admins,
+        RegistryInstruction::Import(participant),
+    )
+}
+
+/// Create a `RegistryInstruction::Migrate` instruction
+pub fn migrate(
+    funder: Pubkey,
+    admins: &[Pubkey],
+    previous_participant_address: Pubkey,
+    participant: &Participant,
+) -> Instruction {
+    admin_instruction(
+        vec![
+            AccountMeta::new(previous_participant_address, false),
+            AccountMeta::new_readonly(find_config_address(&id()).0, false),
+            AccountMeta::new(
+                find_participant_address(&id(), &participant.mainnet_identity).0,
+                false,
+            ),
+            AccountMeta::new(
+                find_testnet_identity_index_address(&id(), &participant.testnet_identity).0,
+                false,
+            ),
+            AccountMeta::new(funder, true),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        admins,
+        RegistryInstruction::Migrate,
+    )
+}
+
+/// Create a `RegistryInstruction::SetInfoUrl` instruction
+pub fn set_info_url(
+    participant: Pubkey,
+    validator_identity: Pubkey,
+    info_url: InfoUrl,
+) -> Instruction {
     Instruction {
         program_id: id(),
         accounts: vec![
             AccountMeta::new(participant, false),
-            AccountMeta::new_readonly(admin, true),
+            AccountMeta::new_readonly(validator_identity, true),
         ],
-        data: RegistryInstruction::Rewrite(new_state).pack_into_vec(),
+        data: RegistryInstruction::SetInfoUrl(info_url).pack_into_vec(),
     }
 }
 
@@ -186,5 +543,15 @@ mod tests {
             RegistryInstruction::unpack_from_slice(&[2]),
             Ok(RegistryInstruction::Approve),
         );
+        assert_eq!(
+            RegistryInstruction::unpack_from_slice(&[3]),
+            Ok(RegistryInstruction::Reject),
+        );
+        assert_eq!(
+            RegistryInstruction::unpack_from_slice(&[5, 1]),
+            Ok(RegistryInstruction::UpdateIdentity {
+                network: Network::Testnet,
+            }),
+        );
     }
 }
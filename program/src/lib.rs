@@ -1,12 +1,45 @@
 mod entrypoint;
+pub mod error;
+pub mod event;
 pub mod instruction;
 pub mod processor;
 pub mod state;
 
 pub use solana_program;
+use solana_program::pubkey::Pubkey;
 
 solana_program::declare_id!("reg8X1V65CSdmrtEjMgnXZk96b9SUSQrJ8n1rP1ZMg7");
 
+/// Seed of the registry config account address
+pub const CONFIG_SEED: &[u8] = b"config";
+
+/// Seed of participant account addresses
+pub const PARTICIPANT_SEED: &[u8] = b"participant";
+
+/// Seed of testnet identity index account addresses
+pub const TESTNET_IDENTITY_SEED: &[u8] = b"testnet";
+
+/// Find the address of the registry config account
+pub fn find_config_address(program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[CONFIG_SEED], program_id)
+}
+
+/// Find the address of the participant account for a mainnet validator identity
+pub fn find_participant_address(program_id: &Pubkey, mainnet_identity: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[PARTICIPANT_SEED, mainnet_identity.as_ref()], program_id)
+}
+
+/// Find the address of the index account for a testnet validator identity
+pub fn find_testnet_identity_index_address(
+    program_id: &Pubkey,
+    testnet_identity: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[TESTNET_IDENTITY_SEED, testnet_identity.as_ref()],
+        program_id,
+    )
+}
+
 pub mod admin {
     solana_program::declare_id!("AdminzUFhXiGmLZBCBeFQT5ZjQkZsHc2rUh28egNnMwd");
 }
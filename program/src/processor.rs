@@ -1,13 +1,16 @@
 //! Program state processor
 
-use crate::{instruction::*, state::*, *};
+use crate::{error::RegistryError, event::StateTransition, instruction::*, state::*, *};
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     msg,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     program_pack::Pack,
     pubkey::Pubkey,
+    system_instruction,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 
 #[cfg(test)]
@@ -15,7 +18,7 @@ mod test_admin {
     solana_program::declare_id!("563B79TEFBRx8f6vwJH1XWo85MSsJRaV3E2EdmwUtjmG");
 }
 
-fn is_admin(address: &Pubkey) -> bool {
+fn is_bootstrap_admin(address: &Pubkey) -> bool {
     if crate::admin::id() == *address {
         return true;
     }
@@ -28,57 +31,663 @@ fn is_admin(address: &Pubkey) -> bool {
     false
 }
 
-fn authenticate_admin(admin_info: &AccountInfo) -> ProgramResult {
-    if !is_admin(admin_info.key) {
-        msg!("Error: {} is not the admin", admin_info.key);
+/// Load the registry config account, which is in its default uninitialized state until
+/// `RegistryInstruction::InitializeConfig` is processed
+fn load_config(
+    program_id: &Pubkey,
+    config_info: &AccountInfo,
+) -> Result<RegistryConfig, ProgramError> {
+    if *config_info.key != find_config_address(program_id).0 {
+        msg!(
+            "Error: {} is not the registry config account",
+            config_info.key
+        );
+        return Err(RegistryError::InvalidConfigAccount.into());
+    }
+    if config_info.owner != program_id {
+        return Ok(RegistryConfig::default());
+    }
+    RegistryConfig::unpack_from_slice(&config_info.data.borrow())
+}
+
+/// Returns the distinct admins that signed
+fn authenticate_admin(
+    program_id: &Pubkey,
+    config_info: &AccountInfo,
+    admin_infos: &[AccountInfo],
+) -> Result<Vec<Pubkey>, ProgramError> {
+    let config = load_config(program_id, config_info)?;
+
+    // Until the config account is initialized only the compiled-in admin is accepted
+    let is_admin = |address: &Pubkey| {
+        if config.is_initialized {
+            config.admins.contains(address)
+        } else {
+            is_bootstrap_admin(address)
+        }
+    };
+    let threshold = if config.is_initialized {
+        config.threshold as usize
+    } else {
+        1
+    };
+
+    let mut signers = vec![];
+    for admin_info in admin_infos {
+        if !is_admin(admin_info.key) {
+            msg!("Error: {} is not an admin", admin_info.key);
+            return Err(RegistryError::NotAdmin.into());
+        }
+        if !admin_info.is_signer {
+            msg!("Error: {} is not a signer", admin_info.key);
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+        if !signers.contains(admin_info.key) {
+            signers.push(*admin_info.key);
+        }
+    }
+
+    if signers.len() < threshold {
+        msg!(
+            "Error: {} of {} required admin signatures",
+            signers.len(),
+            threshold
+        );
+        return Err(RegistryError::InsufficientAdminSignatures.into());
+    }
+
+    Ok(signers)
+}
+
+/// Create the account at the program address derived from `seeds`, failing if the program
+/// already owns it
+fn create_program_account<'a>(
+    program_id: &Pubkey,
+    account_info: &AccountInfo<'a>,
+    funder_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    space: usize,
+    seeds: &[&[u8]],
+) -> ProgramResult {
+    let (address, bump_seed) = Pubkey::find_program_address(seeds, program_id);
+    if address != *account_info.key {
+        msg!(
+            "Error: {} is not the expected account address {}",
+            account_info.key,
+            address
+        );
         return Err(ProgramError::InvalidArgument);
     }
-    if !admin_info.is_signer {
-        msg!("Error: {} is not a signer", admin_info.key);
-        return Err(ProgramError::MissingRequiredSignature);
+    if account_info.owner == program_id {
+        msg!("Error: account {} already exists", account_info.key);
+        return Err(ProgramError::AccountAlreadyInitialized);
+    }
+
+    let bump_seed = [bump_seed];
+    let mut signer_seeds = seeds.to_vec();
+    signer_seeds.push(&bump_seed);
+
+    let rent = Rent::get()?.minimum_balance(space);
+    let lamports = account_info.lamports();
+    if lamports == 0 {
+        return invoke_signed(
+            &system_instruction::create_account(
+                funder_info.key,
+                account_info.key,
+                rent,
+                space as u64,
+                program_id,
+            ),
+            &[
+                funder_info.clone(),
+                account_info.clone(),
+                system_program_info.clone(),
+            ],
+            &[&signer_seeds],
+        );
+    }
+
+    // Lamports may have been transferred to the address in advance, which `create_account` rejects
+    if lamports < rent {
+        invoke(
+            &system_instruction::transfer(funder_info.key, account_info.key, rent - lamports),
+            &[
+                funder_info.clone(),
+                account_info.clone(),
+                system_program_info.clone(),
+            ],
+        )?;
+    }
+    invoke_signed(
+        &system_instruction::allocate(account_info.key, space as u64),
+        &[account_info.clone(), system_program_info.clone()],
+        &[&signer_seeds],
+    )?;
+    invoke_signed(
+        &system_instruction::assign(account_info.key, program_id),
+        &[account_info.clone(), system_program_info.clone()],
+        &[&signer_seeds],
+    )
+}
+
+fn close_account(account_info: &AccountInfo, refundee_info: &AccountInfo) {
+    **refundee_info.lamports.borrow_mut() += account_info.lamports();
+    **account_info.lamports.borrow_mut() = 0;
+    for byte in account_info.data.borrow_mut().iter_mut() {
+        *byte = 0;
+    }
+}
+
+/// Create the participant and testnet identity index accounts of a new participant
+fn create_participant<'a>(
+    program_id: &Pubkey,
+    participant_info: &AccountInfo<'a>,
+    testnet_identity_index_info: &AccountInfo<'a>,
+    funder_info: &AccountInfo<'a>,
+    system_program_info: &AccountInfo<'a>,
+    participant: &ParticipantV2,
+) -> ProgramResult {
+    create_program_account(
+        program_id,
+        participant_info,
+        funder_info,
+        system_program_info,
+        ParticipantV2::get_packed_len(),
+        &[PARTICIPANT_SEED, participant.mainnet_identity.as_ref()],
+    )?;
+    create_program_account(
+        program_id,
+        testnet_identity_index_info,
+        funder_info,
+        system_program_info,
+        TestnetIdentityIndex::get_packed_len(),
+        &[TESTNET_IDENTITY_SEED, participant.testnet_identity.as_ref()],
+    )?;
+
+    participant.pack_into_slice(&mut participant_info.data.borrow_mut());
+    TestnetIdentityIndex {
+        participant: *participant_info.key,
     }
+    .pack_into_slice(&mut testnet_identity_index_info.data.borrow_mut());
+
+    Ok(())
+}
 
+/// Check that the participant and its testnet identity index are at their derived addresses
+fn check_participant_addresses(
+    program_id: &Pubkey,
+    participant_info: &AccountInfo,
+    testnet_identity_index_info: &AccountInfo,
+    participant: &ParticipantV2,
+) -> ProgramResult {
+    if *participant_info.key
+        != find_participant_address(program_id, &participant.mainnet_identity).0
+    {
+        msg!(
+            "Error: participant account {} has not been migrated",
+            participant_info.key
+        );
+        return Err(RegistryError::ParticipantNotMigrated.into());
+    }
+    if *testnet_identity_index_info.key
+        != find_testnet_identity_index_address(program_id, &participant.testnet_identity).0
+    {
+        msg!(
+            "Error: {} is not the testnet identity index account of the participant",
+            testnet_identity_index_info.key
+        );
+        return Err(RegistryError::InvalidTestnetIdentityIndex.into());
+    }
     Ok(())
 }
 
+/// Load a participant account, checking that it is a writable, rent exempt and initialized
+/// account of the program
+fn load_participant(
+    program_id: &Pubkey,
+    participant_info: &AccountInfo,
+) -> Result<ParticipantV2, ProgramError> {
+    if participant_info.owner != program_id {
+        msg!(
+            "Error: {} is not a participant account",
+            participant_info.key
+        );
+        return Err(RegistryError::NotParticipantAccount.into());
+    }
+    if !participant_info.is_writable {
+        msg!(
+            "Error: participant account {} is not writable",
+            participant_info.key
+        );
+        return Err(RegistryError::ParticipantNotWritable.into());
+    }
+    if !Rent::get()?.is_exempt(participant_info.lamports(), participant_info.data_len()) {
+        msg!(
+            "Error: participant account {} is not rent exempt",
+            participant_info.key
+        );
+        return Err(RegistryError::ParticipantNotRentExempt.into());
+    }
+
+    let participant = unpack_participant(&participant_info.data.borrow())?;
+    if participant.state == ParticipantState::Uninitialized {
+        msg!("Error: participant account is not initialized");
+        return Err(RegistryError::UninitializedParticipant.into());
+    }
+    Ok(participant)
+}
+
 pub fn process_instruction(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     input: &[u8],
 ) -> ProgramResult {
     let instruction = RegistryInstruction::unpack_from_slice(input)?;
-    let account_info_iter = &mut accounts.iter();
-    let participant_info = next_account_info(account_info_iter)?;
-    let mut participant = Participant::unpack_from_slice(&participant_info.data.borrow())?;
 
     match instruction {
         RegistryInstruction::Apply => {
             msg!("Apply");
-            if participant.state != ParticipantState::Uninitialized {
-                msg!("Error: participant account is already initialized");
-                return Err(ProgramError::AccountAlreadyInitialized);
-            }
+            process_apply(program_id, accounts)
+        }
+        RegistryInstruction::UpdateIdentity { network } => {
+            msg!("UpdateIdentity");
+            process_update_identity(program_id, accounts, network)
+        }
+        RegistryInstruction::Import(participant) => {
+            msg!("Import");
+            process_import(program_id, accounts, participant)
+        }
+        RegistryInstruction::Migrate => {
+            msg!("Migrate");
+            process_migrate(program_id, accounts)
+        }
+        RegistryInstruction::InitializeConfig => {
+            msg!("InitializeConfig");
+            process_initialize_config(program_id, accounts)
+        }
+        RegistryInstruction::AddAdmin(admin) => {
+            msg!("AddAdmin");
+            process_update_config(program_id, accounts, |config| {
+                if config.admins.contains(&admin) {
+                    msg!("Error: {} is already an admin", admin);
+                    return Err(RegistryError::AlreadyAdmin.into());
+                }
+                if config.admins.len() >= MAX_ADMINS {
+                    msg!("Error: the registry already has {} admins", MAX_ADMINS);
+                    return Err(RegistryError::TooManyAdmins.into());
+                }
+                config.admins.push(admin);
+                Ok(())
+            })
+        }
+        RegistryInstruction::RemoveAdmin(admin) => {
+            msg!("RemoveAdmin");
+            process_update_config(program_id, accounts, |config| {
+                if !config.admins.contains(&admin) {
+                    msg!("Error: {} is not an admin", admin);
+                    return Err(RegistryError::NotAdmin.into());
+                }
+                config.admins.retain(|address| *address != admin);
+                Ok(())
+            })
+        }
+        RegistryInstruction::SetAdminThreshold(threshold) => {
+            msg!("SetAdminThreshold");
+            process_update_config(program_id, accounts, |config| {
+                config.threshold = threshold;
+                Ok(())
+            })
+        }
+        instruction => process_participant_instruction(program_id, accounts, instruction),
+    }
+}
 
-            let mainnet_identity_info = next_account_info(account_info_iter)?;
-            let testnet_identity_info = next_account_info(account_info_iter)?;
+fn process_initialize_config(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let config_info = next_account_info(account_info_iter)?;
+    let funder_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let admin_infos = account_info_iter.as_slice();
 
-            if !mainnet_identity_info.is_signer {
-                msg!("Error: {} is not a signer", mainnet_identity_info.key);
-                return Err(ProgramError::MissingRequiredSignature);
+    let config = load_config(program_id, config_info)?;
+    if config.is_initialized {
+        msg!("Error: registry config account is already initialized");
+        return Err(RegistryError::ConfigAlreadyInitialized.into());
+    }
+    authenticate_admin(program_id, config_info, admin_infos)?;
+
+    create_program_account(
+        program_id,
+        config_info,
+        funder_info,
+        system_program_info,
+        RegistryConfig::get_packed_len(),
+        &[CONFIG_SEED],
+    )?;
+
+    // The admins that bootstrapped the registry become its first admins
+    let mut admins = vec![];
+    for admin_info in admin_infos {
+        if !admins.contains(admin_info.key) {
+            admins.push(*admin_info.key);
+        }
+    }
+
+    RegistryConfig {
+        is_initialized: true,
+        threshold: 1,
+        admins,
+    }
+    .pack_into_slice(&mut config_info.data.borrow_mut());
+
+    Ok(())
+}
+
+fn process_update_config<F>(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    update: F,
+) -> ProgramResult
+where
+    F: FnOnce(&mut RegistryConfig) -> ProgramResult,
+{
+    let account_info_iter = &mut accounts.iter();
+    let config_info = next_account_info(account_info_iter)?;
+
+    let mut config = load_config(program_id, config_info)?;
+    if !config.is_initialized {
+        msg!("Error: registry config account is not initialized");
+        return Err(RegistryError::ConfigNotInitialized.into());
+    }
+    authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
+
+    update(&mut config)?;
+
+    // The remaining admins must always be able to reach the threshold
+    if config.threshold == 0 || config.threshold as usize > config.admins.len() {
+        msg!(
+            "Error: threshold of {} is invalid for {} admins",
+            config.threshold,
+            config.admins.len()
+        );
+        return Err(RegistryError::InvalidAdminThreshold.into());
+    }
+    config.pack_into_slice(&mut config_info.data.borrow_mut());
+
+    Ok(())
+}
+
+fn process_apply(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let participant_info = next_account_info(account_info_iter)?;
+    let mainnet_identity_info = next_account_info(account_info_iter)?;
+    let testnet_identity_info = next_account_info(account_info_iter)?;
+    let testnet_identity_index_info = next_account_info(account_info_iter)?;
+    let funder_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+
+    if !mainnet_identity_info.is_signer {
+        msg!("Error: {} is not a signer", mainnet_identity_info.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !testnet_identity_info.is_signer {
+        msg!("Error: {} is not a signer", testnet_identity_info.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let clock = Clock::get()?;
+    create_participant(
+        program_id,
+        participant_info,
+        testnet_identity_index_info,
+        funder_info,
+        system_program_info,
+        &ParticipantV2 {
+            testnet_identity: *testnet_identity_info.key,
+            mainnet_identity: *mainnet_identity_info.key,
+            state: ParticipantState::Pending,
+            application_slot: clock.slot,
+            application_epoch: clock.epoch,
+            state_change_slot: clock.slot,
+            ..ParticipantV2::default()
+        },
+    )?;
+
+    StateTransition {
+        participant: *participant_info.key,
+        old_state: ParticipantState::Uninitialized,
+        new_state: ParticipantState::Pending,
+        actors: vec![*mainnet_identity_info.key, *testnet_identity_info.key],
+    }
+    .log();
+    Ok(())
+}
+
+fn process_update_identity(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    network: Network,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let participant_info = next_account_info(account_info_iter)?;
+    let current_identity_info = next_account_info(account_info_iter)?;
+    let new_identity_info = next_account_info(account_info_iter)?;
+    let new_account_info = next_account_info(account_info_iter)?;
+    let funder_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let testnet_identity_index_info = next_account_info(account_info_iter)?;
+
+    let mut participant = load_participant(program_id, participant_info)?;
+    check_participant_addresses(
+        program_id,
+        participant_info,
+        testnet_identity_index_info,
+        &participant,
+    )?;
+
+    if !current_identity_info.is_signer {
+        msg!("Error: {} is not a signer", current_identity_info.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+    if !new_identity_info.is_signer {
+        msg!("Error: {} is not a signer", new_identity_info.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let identity = match network {
+        Network::Mainnet => &mut participant.mainnet_identity,
+        Network::Testnet => &mut participant.testnet_identity,
+    };
+    if *current_identity_info.key != *identity {
+        msg!("Error: {} is not authorized", current_identity_info.key);
+        return Err(RegistryError::UnauthorizedIdentity.into());
+    }
+
+    *identity = *new_identity_info.key;
+    let old_state = participant.state.clone();
+    let mut actors = vec![*current_identity_info.key, *new_identity_info.key];
+
+    // A new identity has not been reviewed, so approval is only kept if the admins agree
+    let admins = match account_info_iter.next() {
+        Some(config_info) => {
+            authenticate_admin(program_id, config_info, account_info_iter.as_slice())?
+        }
+        None => vec![],
+    };
+    match participant.state {
+        ParticipantState::Approved | ParticipantState::Suspended { .. } if admins.is_empty() => {
+            participant.state = ParticipantState::Pending;
+            participant.state_change_slot = Clock::get()?.slot;
+        }
+        _ => {}
+    }
+    actors.extend(admins);
+
+    match network {
+        Network::Mainnet => {
+            create_program_account(
+                program_id,
+                new_account_info,
+                funder_info,
+                system_program_info,
+                ParticipantV2::get_packed_len(),
+                &[PARTICIPANT_SEED, new_identity_info.key.as_ref()],
+            )?;
+            close_account(participant_info, funder_info);
+            participant.pack_into_slice(&mut new_account_info.data.borrow_mut());
+            TestnetIdentityIndex {
+                participant: *new_account_info.key,
             }
-            if !testnet_identity_info.is_signer {
-                msg!("Error: {} is not a signer", testnet_identity_info.key);
-                return Err(ProgramError::MissingRequiredSignature);
+            .pack_into_slice(&mut testnet_identity_index_info.data.borrow_mut());
+
+            // The participant moves to the account of its new mainnet identity
+            StateTransition {
+                participant: *participant_info.key,
+                old_state,
+                new_state: ParticipantState::Uninitialized,
+                actors: actors.clone(),
+            }
+            .log();
+            StateTransition {
+                participant: *new_account_info.key,
+                old_state: ParticipantState::Uninitialized,
+                new_state: participant.state,
+                actors,
             }
+            .log();
+        }
+        Network::Testnet => {
+            create_program_account(
+                program_id,
+                new_account_info,
+                funder_info,
+                system_program_info,
+                TestnetIdentityIndex::get_packed_len(),
+                &[TESTNET_IDENTITY_SEED, new_identity_info.key.as_ref()],
+            )?;
+            close_account(testnet_identity_index_info, funder_info);
+            TestnetIdentityIndex {
+                participant: *participant_info.key,
+            }
+            .pack_into_slice(&mut new_account_info.data.borrow_mut());
+            pack_participant(&participant, &mut participant_info.data.borrow_mut())?;
 
-            participant.testnet_identity = *testnet_identity_info.key;
-            participant.mainnet_identity = *mainnet_identity_info.key;
-            participant.state = ParticipantState::Pending;
+            if participant.state != old_state {
+                StateTransition {
+                    participant: *participant_info.key,
+                    old_state,
+                    new_state: participant.state,
+                    actors,
+                }
+                .log();
+            }
         }
+    }
+
+    Ok(())
+}
+
+fn process_import(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    participant: Participant,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let participant_info = next_account_info(account_info_iter)?;
+    let config_info = next_account_info(account_info_iter)?;
+    let testnet_identity_index_info = next_account_info(account_info_iter)?;
+    let funder_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let admins = authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
+
+    if participant.state == ParticipantState::Uninitialized {
+        msg!("Error: a participant cannot be imported in the Uninitialized state");
+        return Err(RegistryError::InvalidStateTransition.into());
+    }
+
+    let clock = Clock::get()?;
+    let participant = ParticipantV2 {
+        application_slot: clock.slot,
+        application_epoch: clock.epoch,
+        state_change_slot: clock.slot,
+        ..ParticipantV2::from(participant)
+    };
+    create_participant(
+        program_id,
+        participant_info,
+        testnet_identity_index_info,
+        funder_info,
+        system_program_info,
+        &participant,
+    )?;
+
+    StateTransition {
+        participant: *participant_info.key,
+        old_state: ParticipantState::Uninitialized,
+        new_state: participant.state,
+        actors: admins,
+    }
+    .log();
+    Ok(())
+}
+
+fn process_migrate(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let previous_participant_info = next_account_info(account_info_iter)?;
+    let config_info = next_account_info(account_info_iter)?;
+    let participant_info = next_account_info(account_info_iter)?;
+    let testnet_identity_index_info = next_account_info(account_info_iter)?;
+    let funder_info = next_account_info(account_info_iter)?;
+    let system_program_info = next_account_info(account_info_iter)?;
+    let admins = authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
+
+    let participant = load_participant(program_id, previous_participant_info)?;
+
+    // Fails if the participant has already been migrated, or another participant has already
+    // been migrated with the same mainnet or testnet identity
+    create_participant(
+        program_id,
+        participant_info,
+        testnet_identity_index_info,
+        funder_info,
+        system_program_info,
+        &participant,
+    )?;
+    close_account(previous_participant_info, funder_info);
+
+    StateTransition {
+        participant: *previous_participant_info.key,
+        old_state: participant.state.clone(),
+        new_state: ParticipantState::Uninitialized,
+        actors: admins.clone(),
+    }
+    .log();
+    StateTransition {
+        participant: *participant_info.key,
+        old_state: ParticipantState::Uninitialized,
+        new_state: participant.state,
+        actors: admins,
+    }
+    .log();
+    Ok(())
+}
+
+fn process_participant_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    instruction: RegistryInstruction,
+) -> ProgramResult {
+    let account_info_iter = &mut accounts.iter();
+    let participant_info = next_account_info(account_info_iter)?;
+    let mut participant = load_participant(program_id, participant_info)?;
+    let old_state = participant.state.clone();
+
+    let actors = match instruction {
         RegistryInstruction::Withdraw => {
             msg!("Withdraw");
             let identity_info = next_account_info(account_info_iter)?;
             let refundee_info = next_account_info(account_info_iter)?;
+            let testnet_identity_index_info = next_account_info(account_info_iter)?;
 
             if !identity_info.is_signer {
                 msg!("Error: {} is not a signer", identity_info.key);
@@ -89,31 +698,168 @@ pub fn process_instruction(
                 && *identity_info.key != participant.mainnet_identity
             {
                 msg!("Error: {} is not authorized", identity_info.key);
-                return Err(ProgramError::MissingRequiredSignature);
+                return Err(RegistryError::UnauthorizedIdentity.into());
+            }
+
+            // Participants that have not been migrated can still withdraw. Their testnet
+            // identity index account is only closed if it exists and points at them
+            let migrated = *participant_info.key
+                == find_participant_address(program_id, &participant.mainnet_identity).0;
+            let close_index = if migrated {
+                check_participant_addresses(
+                    program_id,
+                    participant_info,
+                    testnet_identity_index_info,
+                    &participant,
+                )?;
+                true
+            } else {
+                *testnet_identity_index_info.key
+                    == find_testnet_identity_index_address(
+                        program_id,
+                        &participant.testnet_identity,
+                    )
+                    .0
+                    && testnet_identity_index_info.owner == program_id
+                    && TestnetIdentityIndex::unpack_from_slice(
+                        &testnet_identity_index_info.data.borrow(),
+                    )
+                    .map(|index| index.participant == *participant_info.key)
+                    .unwrap_or(false)
+            };
+
+            if refundee_info.key == participant_info.key
+                || (close_index && refundee_info.key == testnet_identity_index_info.key)
+            {
+                msg!("Error: refundee {} is being closed", refundee_info.key);
+                return Err(RegistryError::InvalidRefundee.into());
             }
 
+            if close_index {
+                close_account(testnet_identity_index_info, refundee_info);
+            }
             **refundee_info.lamports.borrow_mut() += participant_info.lamports();
             **participant_info.lamports.borrow_mut() = 0;
-            participant = Participant::default()
+            participant = ParticipantV2::default();
+            vec![*identity_info.key]
         }
         RegistryInstruction::Approve => {
             msg!("Approve");
-            authenticate_admin(next_account_info(account_info_iter)?)?;
+            let config_info = next_account_info(account_info_iter)?;
+            let admins = authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
             participant.state = ParticipantState::Approved;
+            participant.state_change_slot = Clock::get()?.slot;
+            admins
         }
         RegistryInstruction::Reject => {
             msg!("Reject");
-            authenticate_admin(next_account_info(account_info_iter)?)?;
+            let config_info = next_account_info(account_info_iter)?;
+            let admins = authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
+            participant.state = ParticipantState::Rejected;
+            participant.state_change_slot = Clock::get()?.slot;
+            participant.rejection_reason = RejectionReason::default();
+            admins
+        }
+        RegistryInstruction::RejectWithReason(reason) => {
+            msg!("RejectWithReason");
+            let config_info = next_account_info(account_info_iter)?;
+            let admins = authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
             participant.state = ParticipantState::Rejected;
+            participant.state_change_slot = Clock::get()?.slot;
+            participant.rejection_reason = reason;
+            admins
+        }
+        RegistryInstruction::Suspend { until_epoch } => {
+            msg!("Suspend");
+            let config_info = next_account_info(account_info_iter)?;
+            let admins = authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
+
+            let clock = Clock::get()?;
+            match participant.state.at_epoch(clock.epoch) {
+                ParticipantState::Approved | ParticipantState::Suspended { .. } => {}
+                state => {
+                    msg!(
+                        "Error: participant in the {:?} state cannot be suspended",
+                        state
+                    );
+                    return Err(RegistryError::InvalidStateTransition.into());
+                }
+            }
+            if until_epoch < clock.epoch {
+                msg!("Error: epoch {} has already passed", until_epoch);
+                return Err(RegistryError::EpochPassed.into());
+            }
+            participant.state = ParticipantState::Suspended { until_epoch };
+            participant.state_change_slot = clock.slot;
+            admins
         }
         RegistryInstruction::Rewrite(new_participant) => {
             msg!("Rewrite");
-            authenticate_admin(next_account_info(account_info_iter)?)?;
-            participant = new_participant;
+            let config_info = next_account_info(account_info_iter)?;
+            let admins = authenticate_admin(program_id, config_info, account_info_iter.as_slice())?;
+
+            if new_participant.mainnet_identity != participant.mainnet_identity
+                || new_participant.testnet_identity != participant.testnet_identity
+            {
+                msg!("Error: validator identities can only be changed by UpdateIdentity");
+                return Err(RegistryError::IdentityChangeNotAllowed.into());
+            }
+            if new_participant.state == ParticipantState::Uninitialized {
+                msg!("Error: a participant cannot be rewritten to the Uninitialized state");
+                return Err(RegistryError::InvalidStateTransition.into());
+            }
+            if new_participant.state != participant.state {
+                participant.state = new_participant.state;
+                participant.state_change_slot = Clock::get()?.slot;
+            }
+            admins
         }
-    }
-    participant.pack_into_slice(&mut participant_info.data.borrow_mut());
+        RegistryInstruction::SetInfoUrl(info_url) => {
+            msg!("SetInfoUrl");
+            let identity_info = next_account_info(account_info_iter)?;
+
+            if !identity_info.is_signer {
+                msg!("Error: {} is not a signer", identity_info.key);
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+
+            if *identity_info.key != participant.testnet_identity
+                && *identity_info.key != participant.mainnet_identity
+            {
+                msg!("Error: {} is not authorized", identity_info.key);
+                return Err(RegistryError::UnauthorizedIdentity.into());
+            }
+
+            if participant_info.data_len() != ParticipantV2::get_packed_len() {
+                msg!(
+                    "Error: participant account {} has not been migrated",
+                    participant_info.key
+                );
+                return Err(RegistryError::ParticipantNotMigrated.into());
+            }
+            participant.info_url = info_url;
+            vec![*identity_info.key]
+        }
+        RegistryInstruction::Apply
+        | RegistryInstruction::UpdateIdentity { .. }
+        | RegistryInstruction::Import(_)
+        | RegistryInstruction::Migrate
+        | RegistryInstruction::InitializeConfig
+        | RegistryInstruction::AddAdmin(_)
+        | RegistryInstruction::RemoveAdmin(_)
+        | RegistryInstruction::SetAdminThreshold(_) => unreachable!(),
+    };
+    pack_participant(&participant, &mut participant_info.data.borrow_mut())?;
 
+    if participant.state != old_state {
+        StateTransition {
+            participant: *participant_info.key,
+            old_state,
+            new_state: participant.state,
+            actors,
+        }
+        .log();
+    }
     Ok(())
 }
 
@@ -122,11 +868,14 @@ mod test {
     use {
         super::*,
         assert_matches::*,
-        solana_program::{system_instruction::create_account, sysvar::rent::Rent},
+        solana_program::sysvar::rent::Rent,
         solana_program_test::*,
         solana_sdk::{
+            account::Account,
+            hash::Hash,
             signature::{Keypair, Signer},
             transaction::Transaction,
+            transport::TransportError,
         },
     };
 
@@ -142,13 +891,57 @@ mod test {
         keypair
     }
 
+    fn participant_address(mainnet_identity: &Keypair) -> Pubkey {
+        find_participant_address(&crate::id(), &mainnet_identity.pubkey()).0
+    }
+
+    fn testnet_identity_index_address(testnet_identity: &Keypair) -> Pubkey {
+        find_testnet_identity_index_address(&crate::id(), &testnet_identity.pubkey()).0
+    }
+
+    async fn get_participant_v2(banks_client: &mut BanksClient, address: Pubkey) -> ParticipantV2 {
+        let account = banks_client.get_account(address).await.unwrap().unwrap();
+        unpack_participant(&account.data).unwrap()
+    }
+
+    async fn get_participant(banks_client: &mut BanksClient, address: Pubkey) -> Participant {
+        Participant::from(&get_participant_v2(banks_client, address).await)
+    }
+
+    async fn apply_participant(
+        banks_client: &mut BanksClient,
+        payer: &Keypair,
+        recent_blockhash: Hash,
+        mainnet_validator_identity: &Keypair,
+        testnet_validator_identity: &Keypair,
+    ) -> Result<(), TransportError> {
+        let mut transaction = Transaction::new_with_payer(
+            &[apply(
+                payer.pubkey(),
+                mainnet_validator_identity.pubkey(),
+                testnet_validator_identity.pubkey(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[
+                payer,
+                mainnet_validator_identity,
+                testnet_validator_identity,
+            ],
+            recent_blockhash,
+        );
+        banks_client.process_transaction(transaction).await
+    }
+
     #[tokio::test]
     async fn test_signup() {
         let program_id = crate::id();
 
-        let participant = Keypair::new();
         let mainnet_validator_identity = Keypair::new();
         let testnet_validator_identity = Keypair::new();
+        let participant = participant_address(&mainnet_validator_identity);
+        let testnet_identity_index = testnet_identity_index_address(&testnet_validator_identity);
 
         let (mut banks_client, payer, recent_blockhash) = ProgramTest::new(
             "registry_program",
@@ -158,41 +951,23 @@ mod test {
         .start()
         .await;
 
-        let rent = Rent::default().minimum_balance(Participant::get_packed_len());
+        let rent = Rent::default().minimum_balance(ParticipantV2::get_packed_len());
+        let index_rent = Rent::default().minimum_balance(TestnetIdentityIndex::get_packed_len());
 
-        // Create/Apply...
-        let mut transaction = Transaction::new_with_payer(
-            &[
-                create_account(
-                    &payer.pubkey(),
-                    &participant.pubkey(),
-                    rent,
-                    Participant::get_packed_len() as u64,
-                    &program_id,
-                ),
-                apply(
-                    participant.pubkey(),
-                    mainnet_validator_identity.pubkey(),
-                    testnet_validator_identity.pubkey(),
-                ),
-            ],
-            Some(&payer.pubkey()),
-        );
-        transaction.sign(
-            &[
+        // Apply...
+        assert_matches!(
+            apply_participant(
+                &mut banks_client,
                 &payer,
-                &participant,
+                recent_blockhash,
                 &mainnet_validator_identity,
                 &testnet_validator_identity,
-            ],
-            recent_blockhash,
+            )
+            .await,
+            Ok(())
         );
-        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
 
-        let participant_state = banks_client
-            .get_packed_account_data::<Participant>(participant.pubkey())
-            .await
-            .unwrap();
+        let participant_state = get_participant(&mut banks_client, participant).await;
         assert_eq!(
             participant_state,
             Participant {
@@ -201,11 +976,18 @@ mod test {
                 mainnet_identity: mainnet_validator_identity.pubkey()
             }
         );
+        assert_eq!(
+            banks_client
+                .get_packed_account_data::<TestnetIdentityIndex>(testnet_identity_index)
+                .await
+                .unwrap(),
+            TestnetIdentityIndex { participant }
+        );
 
         // Cannot Apply twice...
         let mut transaction = Transaction::new_with_payer(
             &[apply(
-                participant.pubkey(),
+                payer.pubkey(),
                 mainnet_validator_identity.pubkey(),
                 testnet_validator_identity.pubkey(),
             )],
@@ -222,45 +1004,41 @@ mod test {
         assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
 
         // Reject..
+        let rejection_reason =
+            RejectionReason::new(RejectionCode::Ineligible, "testnet validator is delinquent")
+                .unwrap();
         let mut transaction = Transaction::new_with_payer(
-            &[reject(participant.pubkey(), test_admin::id())],
+            &[reject(participant, &[test_admin::id()], rejection_reason)],
             Some(&payer.pubkey()),
         );
         transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
         assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
 
+        let participant_state = get_participant_v2(&mut banks_client, participant).await;
+        assert_eq!(participant_state.rejection_reason, rejection_reason);
+        assert!(participant_state.state_change_slot >= participant_state.application_slot);
+
         assert_eq!(
-            banks_client
-                .get_packed_account_data::<Participant>(participant.pubkey())
-                .await
-                .unwrap()
-                .state,
+            get_participant(&mut banks_client, participant).await.state,
             ParticipantState::Rejected
         );
 
         // Approve...
         let mut transaction = Transaction::new_with_payer(
-            &[approve(participant.pubkey(), test_admin::id())],
+            &[approve(participant, &[test_admin::id()])],
             Some(&payer.pubkey()),
         );
         transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
         assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
 
         assert_eq!(
-            banks_client
-                .get_packed_account_data::<Participant>(participant.pubkey())
-                .await
-                .unwrap()
-                .state,
+            get_participant(&mut banks_client, participant).await.state,
             ParticipantState::Approved
         );
 
         // Approve with wrong admin key, failure...
         let mut transaction = Transaction::new_with_payer(
-            &[approve(
-                participant.pubkey(),
-                testnet_validator_identity.pubkey(),
-            )],
+            &[approve(participant, &[testnet_validator_identity.pubkey()])],
             Some(&payer.pubkey()),
         );
         transaction.sign(&[&payer, &testnet_validator_identity], recent_blockhash);
@@ -269,8 +1047,8 @@ mod test {
         // Rewrite with wrong admin key, failure...
         let mut transaction = Transaction::new_with_payer(
             &[rewrite(
-                participant.pubkey(),
-                testnet_validator_identity.pubkey(),
+                participant,
+                &[testnet_validator_identity.pubkey()],
                 Participant::default(),
             )],
             Some(&payer.pubkey()),
@@ -278,11 +1056,11 @@ mod test {
         transaction.sign(&[&payer, &testnet_validator_identity], recent_blockhash);
         assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
 
-        // Rewrite...
+        // Rewrite the validator identities, failure...
         let mut transaction = Transaction::new_with_payer(
             &[rewrite(
-                participant.pubkey(),
-                test_admin::id(),
+                participant,
+                &[test_admin::id()],
                 Participant {
                     state: ParticipantState::Pending,
                     testnet_identity: testnet_validator_identity.pubkey(),
@@ -292,21 +1070,59 @@ mod test {
             Some(&payer.pubkey()),
         );
         transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Rewrite...
+        let mut transaction = Transaction::new_with_payer(
+            &[rewrite(
+                participant,
+                &[test_admin::id()],
+                Participant {
+                    state: ParticipantState::Pending,
+                    testnet_identity: testnet_validator_identity.pubkey(),
+                    mainnet_identity: mainnet_validator_identity.pubkey(),
+                },
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
         assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
 
-        let participant_state = banks_client
-            .get_packed_account_data::<Participant>(participant.pubkey())
-            .await
-            .unwrap();
+        let participant_state = get_participant(&mut banks_client, participant).await;
         assert_eq!(
             participant_state,
             Participant {
                 state: ParticipantState::Pending,
                 testnet_identity: testnet_validator_identity.pubkey(),
-                mainnet_identity: Pubkey::default(),
+                mainnet_identity: mainnet_validator_identity.pubkey(),
             }
         );
 
+        // Set the info URL with a key other than the validator identities, failure...
+        let info_url = InfoUrl::new("https://example.com/validator").unwrap();
+        let mut transaction = Transaction::new_with_payer(
+            &[set_info_url(participant, payer.pubkey(), info_url)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Set the info URL...
+        let mut transaction = Transaction::new_with_payer(
+            &[set_info_url(
+                participant,
+                mainnet_validator_identity.pubkey(),
+                info_url,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &mainnet_validator_identity], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        let participant_v2 = get_participant_v2(&mut banks_client, participant).await;
+        assert_eq!(participant_v2.info_url.url(), info_url.url());
+        assert_eq!(participant_v2.rejection_reason, rejection_reason);
+
         // Withdraw...
         assert_eq!(
             banks_client
@@ -315,16 +1131,17 @@ mod test {
                 .unwrap(),
             0
         );
+        assert_eq!(banks_client.get_balance(participant).await.unwrap(), rent);
         assert_eq!(
             banks_client
-                .get_balance(participant.pubkey())
+                .get_balance(testnet_identity_index)
                 .await
                 .unwrap(),
-            rent
+            index_rent
         );
         let mut transaction = Transaction::new_with_payer(
             &[withdraw(
-                participant.pubkey(),
+                &participant_state,
                 testnet_validator_identity.pubkey(),
                 testnet_validator_identity.pubkey(),
             )],
@@ -338,14 +1155,704 @@ mod test {
                 .get_balance(testnet_validator_identity.pubkey())
                 .await
                 .unwrap(),
-            rent
+            rent + index_rent
+        );
+        assert_eq!(banks_client.get_balance(participant).await.unwrap(), 0);
+        assert_eq!(
+            banks_client
+                .get_balance(testnet_identity_index)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unique_identities() {
+        let program_id = crate::id();
+
+        let mainnet_validator_identity = Keypair::new();
+        let testnet_validator_identity = Keypair::new();
+        let other_validator_identity = Keypair::new();
+
+        let (mut banks_client, payer, recent_blockhash) = ProgramTest::new(
+            "registry_program",
+            program_id,
+            processor!(process_instruction),
+        )
+        .start()
+        .await;
+
+        // Lamports transferred to the participant address in advance do not block Apply...
+        let mut transaction = Transaction::new_with_payer(
+            &[system_instruction::transfer(
+                &payer.pubkey(),
+                &participant_address(&mainnet_validator_identity),
+                1,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_matches!(
+            apply_participant(
+                &mut banks_client,
+                &payer,
+                recent_blockhash,
+                &mainnet_validator_identity,
+                &testnet_validator_identity,
+            )
+            .await,
+            Ok(())
+        );
+
+        // Reusing the mainnet identity, failure...
+        assert_matches!(
+            apply_participant(
+                &mut banks_client,
+                &payer,
+                recent_blockhash,
+                &mainnet_validator_identity,
+                &other_validator_identity,
+            )
+            .await,
+            Err(_)
+        );
+
+        // Reusing the testnet identity, failure...
+        assert_matches!(
+            apply_participant(
+                &mut banks_client,
+                &payer,
+                recent_blockhash,
+                &other_validator_identity,
+                &testnet_validator_identity,
+            )
+            .await,
+            Err(_)
+        );
+
+        // Importing with a registered identity, failure...
+        let mut transaction = Transaction::new_with_payer(
+            &[import(
+                payer.pubkey(),
+                &[test_admin::id()],
+                Participant {
+                    state: ParticipantState::Approved,
+                    testnet_identity: testnet_validator_identity.pubkey(),
+                    mainnet_identity: other_validator_identity.pubkey(),
+                },
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Importing with unregistered identities...
+        let imported_participant = Participant {
+            state: ParticipantState::Approved,
+            testnet_identity: Pubkey::new_unique(),
+            mainnet_identity: other_validator_identity.pubkey(),
+        };
+        let mut transaction = Transaction::new_with_payer(
+            &[import(
+                payer.pubkey(),
+                &[test_admin::id()],
+                imported_participant.clone(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_eq!(
+            get_participant(
+                &mut banks_client,
+                participant_address(&other_validator_identity)
+            )
+            .await,
+            imported_participant
+        );
+    }
+
+    #[tokio::test]
+    async fn test_migrate() {
+        let program_id = crate::id();
+
+        let previous_participant = Pubkey::new_unique();
+        let duplicate_participant = Pubkey::new_unique();
+        let mainnet_validator_identity = Keypair::new();
+        let testnet_validator_identity = Keypair::new();
+        let participant = Participant {
+            state: ParticipantState::Approved,
+            testnet_identity: testnet_validator_identity.pubkey(),
+            mainnet_identity: mainnet_validator_identity.pubkey(),
+        };
+
+        let rent = Rent::default().minimum_balance(Participant::get_packed_len());
+        let mut program_test = ProgramTest::new(
+            "registry_program",
+            program_id,
+            processor!(process_instruction),
+        );
+        for address in &[previous_participant, duplicate_participant] {
+            let mut data = vec![0; Participant::get_packed_len()];
+            participant.pack_into_slice(&mut data);
+            program_test.add_account(
+                *address,
+                Account {
+                    lamports: rent,
+                    data,
+                    owner: program_id,
+                    ..Account::default()
+                },
+            );
+        }
+        let (mut banks_client, payer, recent_blockhash) = program_test.start().await;
+
+        // Migrate without an admin, failure...
+        let mut transaction = Transaction::new_with_payer(
+            &[migrate(
+                payer.pubkey(),
+                &[mainnet_validator_identity.pubkey()],
+                previous_participant,
+                &participant,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &mainnet_validator_identity], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Migrate...
+        let mut transaction = Transaction::new_with_payer(
+            &[migrate(
+                payer.pubkey(),
+                &[test_admin::id()],
+                previous_participant,
+                &participant,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_eq!(
+            get_participant(
+                &mut banks_client,
+                participant_address(&mainnet_validator_identity)
+            )
+            .await,
+            participant
+        );
+        assert_eq!(
+            banks_client
+                .get_packed_account_data::<TestnetIdentityIndex>(testnet_identity_index_address(
+                    &testnet_validator_identity
+                ))
+                .await
+                .unwrap(),
+            TestnetIdentityIndex {
+                participant: participant_address(&mainnet_validator_identity)
+            }
+        );
+        assert_eq!(
+            banks_client
+                .get_balance(previous_participant)
+                .await
+                .unwrap(),
+            0
+        );
+
+        // Migrating a duplicate of a migrated participant, failure...
+        let mut transaction = Transaction::new_with_payer(
+            &[migrate(
+                payer.pubkey(),
+                &[test_admin::id()],
+                duplicate_participant,
+                &participant,
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+    }
+
+    #[tokio::test]
+    async fn test_suspend() {
+        let program_id = crate::id();
+
+        let mainnet_validator_identity = Keypair::new();
+        let testnet_validator_identity = Keypair::new();
+        let participant = participant_address(&mainnet_validator_identity);
+
+        let (mut banks_client, payer, recent_blockhash) = ProgramTest::new(
+            "registry_program",
+            program_id,
+            processor!(process_instruction),
+        )
+        .start()
+        .await;
+
+        assert_matches!(
+            apply_participant(
+                &mut banks_client,
+                &payer,
+                recent_blockhash,
+                &mainnet_validator_identity,
+                &testnet_validator_identity,
+            )
+            .await,
+            Ok(())
+        );
+
+        // Suspend a Pending participant, failure...
+        let mut transaction = Transaction::new_with_payer(
+            &[suspend(participant, &[test_admin::id()], 10)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Approve...
+        let mut transaction = Transaction::new_with_payer(
+            &[approve(participant, &[test_admin::id()])],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        // Suspend...
+        let mut transaction = Transaction::new_with_payer(
+            &[suspend(participant, &[test_admin::id()], 5)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+        assert_eq!(
+            get_participant_v2(&mut banks_client, participant)
+                .await
+                .state,
+            ParticipantState::Suspended { until_epoch: 5 }
+        );
+
+        // Extend the suspension...
+        let mut transaction = Transaction::new_with_payer(
+            &[suspend(participant, &[test_admin::id()], 10)],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+        let participant_state = get_participant_v2(&mut banks_client, participant).await;
+        assert_eq!(
+            participant_state.state,
+            ParticipantState::Suspended { until_epoch: 10 }
+        );
+        assert_eq!(
+            participant_state.state.at_epoch(11),
+            ParticipantState::Approved
+        );
+
+        // Approve to lift the suspension early. The info URL update keeps the transaction distinct
+        // from the earlier Approve...
+        let mut transaction = Transaction::new_with_payer(
+            &[
+                approve(participant, &[test_admin::id()]),
+                set_info_url(
+                    participant,
+                    mainnet_validator_identity.pubkey(),
+                    InfoUrl::default(),
+                ),
+            ],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[&payer, &test_admin_keypair(), &mainnet_validator_identity],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+        assert_eq!(
+            get_participant_v2(&mut banks_client, participant)
+                .await
+                .state,
+            ParticipantState::Approved
+        );
+    }
+
+    #[tokio::test]
+    async fn test_update_identity() {
+        let program_id = crate::id();
+
+        let mainnet_validator_identity = Keypair::new();
+        let testnet_validator_identity = Keypair::new();
+        let new_mainnet_validator_identity = Keypair::new();
+        let new_testnet_validator_identity = Keypair::new();
+
+        let (mut banks_client, payer, recent_blockhash) = ProgramTest::new(
+            "registry_program",
+            program_id,
+            processor!(process_instruction),
+        )
+        .start()
+        .await;
+
+        let participant = Participant {
+            state: ParticipantState::Approved,
+            testnet_identity: testnet_validator_identity.pubkey(),
+            mainnet_identity: mainnet_validator_identity.pubkey(),
+        };
+
+        // Cannot update an unregistered participant...
+        let mut transaction = Transaction::new_with_payer(
+            &[update_identity(
+                payer.pubkey(),
+                &participant,
+                Network::Mainnet,
+                new_mainnet_validator_identity.pubkey(),
+                &[],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[
+                &payer,
+                &mainnet_validator_identity,
+                &new_mainnet_validator_identity,
+            ],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Apply and Approve...
+        let mut transaction = Transaction::new_with_payer(
+            &[
+                apply(
+                    payer.pubkey(),
+                    mainnet_validator_identity.pubkey(),
+                    testnet_validator_identity.pubkey(),
+                ),
+                approve(
+                    participant_address(&mainnet_validator_identity),
+                    &[test_admin::id()],
+                ),
+            ],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[
+                &payer,
+                &mainnet_validator_identity,
+                &testnet_validator_identity,
+                &test_admin_keypair(),
+            ],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        // Update with the identity of the other network, failure...
+        let mut instruction = update_identity(
+            payer.pubkey(),
+            &participant,
+            Network::Mainnet,
+            new_mainnet_validator_identity.pubkey(),
+            &[],
+        );
+        instruction.accounts[1].pubkey = testnet_validator_identity.pubkey();
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(
+            &[
+                &payer,
+                &testnet_validator_identity,
+                &new_mainnet_validator_identity,
+            ],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Update without the new identity signing, failure...
+        let mut instruction = update_identity(
+            payer.pubkey(),
+            &participant,
+            Network::Mainnet,
+            new_mainnet_validator_identity.pubkey(),
+            &[],
+        );
+        instruction.accounts[2].is_signer = false;
+        let mut transaction = Transaction::new_with_payer(&[instruction], Some(&payer.pubkey()));
+        transaction.sign(&[&payer, &mainnet_validator_identity], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Update the mainnet identity, with an admin signing to keep the Approved state...
+        let mut transaction = Transaction::new_with_payer(
+            &[update_identity(
+                payer.pubkey(),
+                &participant,
+                Network::Mainnet,
+                new_mainnet_validator_identity.pubkey(),
+                &[test_admin::id()],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[
+                &payer,
+                &mainnet_validator_identity,
+                &new_mainnet_validator_identity,
+                &test_admin_keypair(),
+            ],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        let participant = Participant {
+            state: ParticipantState::Approved,
+            testnet_identity: testnet_validator_identity.pubkey(),
+            mainnet_identity: new_mainnet_validator_identity.pubkey(),
+        };
+        assert_eq!(
+            get_participant(
+                &mut banks_client,
+                participant_address(&new_mainnet_validator_identity)
+            )
+            .await,
+            participant
+        );
+        assert_eq!(
+            banks_client
+                .get_balance(participant_address(&mainnet_validator_identity))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            banks_client
+                .get_packed_account_data::<TestnetIdentityIndex>(testnet_identity_index_address(
+                    &testnet_validator_identity
+                ))
+                .await
+                .unwrap(),
+            TestnetIdentityIndex {
+                participant: participant_address(&new_mainnet_validator_identity)
+            }
+        );
+
+        // Update the testnet identity without an admin, returning the approved participant to
+        // review...
+        let mut transaction = Transaction::new_with_payer(
+            &[update_identity(
+                payer.pubkey(),
+                &participant,
+                Network::Testnet,
+                new_testnet_validator_identity.pubkey(),
+                &[],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[
+                &payer,
+                &testnet_validator_identity,
+                &new_testnet_validator_identity,
+            ],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_eq!(
+            get_participant(
+                &mut banks_client,
+                participant_address(&new_mainnet_validator_identity)
+            )
+            .await,
+            Participant {
+                state: ParticipantState::Pending,
+                testnet_identity: new_testnet_validator_identity.pubkey(),
+                mainnet_identity: new_mainnet_validator_identity.pubkey(),
+            }
         );
         assert_eq!(
             banks_client
-                .get_balance(participant.pubkey())
+                .get_balance(testnet_identity_index_address(&testnet_validator_identity))
                 .await
                 .unwrap(),
             0
         );
+        assert_eq!(
+            banks_client
+                .get_packed_account_data::<TestnetIdentityIndex>(testnet_identity_index_address(
+                    &new_testnet_validator_identity
+                ))
+                .await
+                .unwrap(),
+            TestnetIdentityIndex {
+                participant: participant_address(&new_mainnet_validator_identity)
+            }
+        );
+    }
+    #[tokio::test]
+    async fn test_admin_config() {
+        let program_id = crate::id();
+        let config_address = find_config_address(&program_id).0;
+
+        let mainnet_validator_identity = Keypair::new();
+        let testnet_validator_identity = Keypair::new();
+        let participant = participant_address(&mainnet_validator_identity);
+        let second_admin = Keypair::new();
+
+        let (mut banks_client, payer, recent_blockhash) = ProgramTest::new(
+            "registry_program",
+            program_id,
+            processor!(process_instruction),
+        )
+        .start()
+        .await;
+
+        // Apply...
+        assert_matches!(
+            apply_participant(
+                &mut banks_client,
+                &payer,
+                recent_blockhash,
+                &mainnet_validator_identity,
+                &testnet_validator_identity,
+            )
+            .await,
+            Ok(())
+        );
+
+        // Initialize the config with a key other than the compiled-in admin, failure...
+        let mut transaction = Transaction::new_with_payer(
+            &[initialize_config(payer.pubkey(), second_admin.pubkey())],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &second_admin], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Initialize the config...
+        let mut transaction = Transaction::new_with_payer(
+            &[initialize_config(payer.pubkey(), test_admin::id())],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_eq!(
+            banks_client
+                .get_packed_account_data::<RegistryConfig>(config_address)
+                .await
+                .unwrap(),
+            RegistryConfig {
+                is_initialized: true,
+                threshold: 1,
+                admins: vec![test_admin::id()],
+            }
+        );
+
+        // Add a second admin and require both signatures...
+        let mut transaction = Transaction::new_with_payer(
+            &[
+                add_admin(second_admin.pubkey(), &[test_admin::id()]),
+                set_admin_threshold(2, &[test_admin::id()]),
+            ],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        // Approve with a single admin, failure...
+        let mut transaction = Transaction::new_with_payer(
+            &[approve(participant, &[test_admin::id()])],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Approve with both admins...
+        let mut transaction = Transaction::new_with_payer(
+            &[approve(
+                participant,
+                &[test_admin::id(), second_admin.pubkey()],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[&payer, &test_admin_keypair(), &second_admin],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_eq!(
+            get_participant(&mut banks_client, participant).await.state,
+            ParticipantState::Approved
+        );
+
+        // Remove an admin below the threshold, failure...
+        let mut transaction = Transaction::new_with_payer(
+            &[remove_admin(
+                test_admin::id(),
+                &[test_admin::id(), second_admin.pubkey()],
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[&payer, &test_admin_keypair(), &second_admin],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Rotate out the compiled-in admin...
+        let mut transaction = Transaction::new_with_payer(
+            &[
+                set_admin_threshold(1, &[test_admin::id(), second_admin.pubkey()]),
+                remove_admin(test_admin::id(), &[second_admin.pubkey()]),
+            ],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(
+            &[&payer, &test_admin_keypair(), &second_admin],
+            recent_blockhash,
+        );
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_eq!(
+            banks_client
+                .get_packed_account_data::<RegistryConfig>(config_address)
+                .await
+                .unwrap(),
+            RegistryConfig {
+                is_initialized: true,
+                threshold: 1,
+                admins: vec![second_admin.pubkey()],
+            }
+        );
+
+        // The compiled-in admin is no longer accepted...
+        let mut transaction = Transaction::new_with_payer(
+            &[reject(
+                participant,
+                &[test_admin::id()],
+                RejectionReason::default(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &test_admin_keypair()], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Err(_));
+
+        // Reject with the remaining admin...
+        let mut transaction = Transaction::new_with_payer(
+            &[reject(
+                participant,
+                &[second_admin.pubkey()],
+                RejectionReason::default(),
+            )],
+            Some(&payer.pubkey()),
+        );
+        transaction.sign(&[&payer, &second_admin], recent_blockhash);
+        assert_matches!(banks_client.process_transaction(transaction).await, Ok(()));
+
+        assert_eq!(
+            get_participant(&mut banks_client, participant).await.state,
+            ParticipantState::Rejected
+        );
     }
 }
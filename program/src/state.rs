@@ -1,6 +1,8 @@
 //! Program state
+use crate::error::RegistryError;
 use borsh::{BorshDeserialize, BorshSchema, BorshSerialize};
 use solana_program::{
+    clock::{Epoch, Slot},
     msg,
     program_error::ProgramError,
     program_pack::{Pack, Sealed},
@@ -20,6 +22,10 @@ pub enum ParticipantState {
 
     /// Participant is enrolled
     Approved,
+
+    /// Participant is temporarily suspended, and returns to `Approved` once `until_epoch` has
+    /// passed. Only supported by the `ParticipantV2` layout
+    Suspended { until_epoch: Epoch },
 }
 
 impl Default for ParticipantState {
@@ -28,6 +34,16 @@ impl Default for ParticipantState {
     }
 }
 
+impl ParticipantState {
+    /// The state in effect during `epoch`, with a lapsed suspension returned to `Approved`
+    pub fn at_epoch(&self, epoch: Epoch) -> Self {
+        match self {
+            Self::Suspended { until_epoch } if epoch > *until_epoch => Self::Approved,
+            state => state.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
 pub struct Participant {
     pub testnet_identity: Pubkey,
@@ -38,7 +54,7 @@ pub struct Participant {
 impl Sealed for Participant {}
 
 impl Pack for Participant {
-    const LEN: usize = 65; // see `test::get_packed_len()` for justification of "73"
+    const LEN: usize = 65; // see `test::get_packed_len()` for justification of "65"
 
     fn pack_into_slice(&self, dst: &mut [u8]) {
         let data = self.try_to_vec().unwrap();
@@ -57,15 +73,366 @@ impl Pack for Participant {
     }
 }
 
+/// Maximum length of the text of a `RejectionReason`
+pub const MAX_REJECTION_TEXT_LEN: usize = 64;
+
+/// Maximum length of an `InfoUrl`
+pub const MAX_INFO_URL_LEN: usize = 128;
+
+fn pack_text(text: &str, dst: &mut [u8]) -> Result<(), ProgramError> {
+    if text.len() > dst.len() {
+        msg!("Error: text is longer than {} bytes", dst.len());
+        return Err(ProgramError::InvalidArgument);
+    }
+    dst[..text.len()].copy_from_slice(text.as_bytes());
+    Ok(())
+}
+
+fn unpack_text(src: &[u8]) -> String {
+    let len = src.iter().position(|byte| *byte == 0).unwrap_or(src.len());
+    String::from_utf8_lossy(&src[..len]).into_owned()
+}
+
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub enum RejectionCode {
+    /// No reason was provided
+    Unspecified,
+
+    /// The validators do not meet the program requirements
+    Ineligible,
+
+    /// The testnet validator did not participate enough on testnet
+    InsufficientTestnetParticipation,
+
+    /// A validator is already registered by another participant
+    DuplicateRegistration,
+
+    /// See the rejection text
+    Other,
+}
+
+impl Default for RejectionCode {
+    fn default() -> Self {
+        Self::Unspecified
+    }
+}
+
+/// Why a participant was rejected, with a short text zero padded to `MAX_REJECTION_TEXT_LEN`
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub struct RejectionReason {
+    pub code: RejectionCode,
+    text: [u8; MAX_REJECTION_TEXT_LEN],
+}
+
+impl Default for RejectionReason {
+    fn default() -> Self {
+        Self {
+            code: RejectionCode::default(),
+            text: [0; MAX_REJECTION_TEXT_LEN],
+        }
+    }
+}
+
+impl RejectionReason {
+    pub fn new(code: RejectionCode, text: &str) -> Result<Self, ProgramError> {
+        let mut reason = Self {
+            code,
+            ..Self::default()
+        };
+        pack_text(text, &mut reason.text)?;
+        Ok(reason)
+    }
+
+    pub fn text(&self) -> String {
+        unpack_text(&self.text)
+    }
+}
+
+/// Validator info URL, zero padded to `MAX_INFO_URL_LEN`. Empty if not provided
+#[derive(Clone, Copy, Debug, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub struct InfoUrl([u8; MAX_INFO_URL_LEN]);
+
+impl Default for InfoUrl {
+    fn default() -> Self {
+        Self([0; MAX_INFO_URL_LEN])
+    }
+}
+
+impl InfoUrl {
+    pub fn new(url: &str) -> Result<Self, ProgramError> {
+        let mut info_url = Self::default();
+        pack_text(url, &mut info_url.0)?;
+        Ok(info_url)
+    }
+
+    pub fn url(&self) -> Option<String> {
+        Some(unpack_text(&self.0)).filter(|url| !url.is_empty())
+    }
+}
+
+/// Participant account layout with application metadata. Accounts in the original `Participant`
+/// layout are told apart by their size, see `unpack_participant()`
+#[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub struct ParticipantV2 {
+    pub testnet_identity: Pubkey,
+    pub mainnet_identity: Pubkey,
+    pub state: ParticipantState,
+
+    /// Slot and epoch of the application
+    pub application_slot: Slot,
+    pub application_epoch: Epoch,
+
+    /// Slot of the last change to `state`
+    pub state_change_slot: Slot,
+
+    /// Reason of the most recent rejection
+    pub rejection_reason: RejectionReason,
+
+    pub info_url: InfoUrl,
+}
+
+impl Sealed for ParticipantV2 {}
+
+impl Pack for ParticipantV2 {
+    const LEN: usize = 290; // see `test::get_packed_len()` for justification of "290"
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let data = self.try_to_vec().unwrap();
+        dst[..data.len()].copy_from_slice(&data);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let mut mut_src: &[u8] = src;
+        Self::deserialize(&mut mut_src).map_err(|err| {
+            msg!("Error: failed to deserialize participant account: {}", err);
+            ProgramError::InvalidAccountData
+        })
+    }
+}
+
+impl From<Participant> for ParticipantV2 {
+    fn from(participant: Participant) -> Self {
+        Self {
+            testnet_identity: participant.testnet_identity,
+            mainnet_identity: participant.mainnet_identity,
+            state: participant.state,
+            ..Self::default()
+        }
+    }
+}
+
+impl From<&ParticipantV2> for Participant {
+    fn from(participant: &ParticipantV2) -> Self {
+        Self {
+            testnet_identity: participant.testnet_identity,
+            mainnet_identity: participant.mainnet_identity,
+            state: participant.state.clone(),
+        }
+    }
+}
+
+/// Unpack a participant account in either the `Participant` or the `ParticipantV2` layout
+pub fn unpack_participant(src: &[u8]) -> Result<ParticipantV2, ProgramError> {
+    if src.len() == Participant::LEN {
+        Participant::unpack_from_slice(src).map(ParticipantV2::from)
+    } else {
+        ParticipantV2::unpack_from_slice(src)
+    }
+}
+
+/// Pack a participant into an account in either layout. The metadata of `ParticipantV2` is
+/// dropped from accounts in the `Participant` layout, which cannot hold a suspension
+pub fn pack_participant(participant: &ParticipantV2, dst: &mut [u8]) -> Result<(), ProgramError> {
+    if dst.len() == Participant::LEN {
+        if let ParticipantState::Suspended { .. } = participant.state {
+            msg!("Error: participant account has not been migrated and cannot be suspended");
+            return Err(RegistryError::ParticipantNotMigrated.into());
+        }
+        Participant::from(participant).pack_into_slice(dst)
+    } else {
+        participant.pack_into_slice(dst)
+    }
+    Ok(())
+}
+
+/// Index from a testnet validator identity to its participant, stored in the account at
+/// `crate::find_testnet_identity_index_address()`. Its existence prevents two participants from
+/// sharing a testnet validator identity
+#[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub struct TestnetIdentityIndex {
+    pub participant: Pubkey,
+}
+
+impl Sealed for TestnetIdentityIndex {}
+
+impl Pack for TestnetIdentityIndex {
+    const LEN: usize = 32; // see `test::get_packed_len()` for justification of "32"
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let data = self.try_to_vec().unwrap();
+        dst[..data.len()].copy_from_slice(&data);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let mut mut_src: &[u8] = src;
+        Self::deserialize(&mut mut_src).map_err(|err| {
+            msg!(
+                "Error: failed to deserialize testnet identity index account: {}",
+                err
+            );
+            ProgramError::InvalidAccountData
+        })
+    }
+}
+
+/// Maximum number of admins in the registry config
+pub const MAX_ADMINS: usize = 8;
+
+/// Registry configuration, stored in the account at `crate::find_config_address()`
+#[derive(Clone, Debug, Default, BorshSerialize, BorshDeserialize, BorshSchema, PartialEq)]
+pub struct RegistryConfig {
+    pub is_initialized: bool,
+
+    /// Number of distinct admin signatures required to authorize an admin instruction
+    pub threshold: u8,
+
+    /// At most `MAX_ADMINS` admins
+    pub admins: Vec<Pubkey>,
+}
+
+impl Sealed for RegistryConfig {}
+
+impl Pack for RegistryConfig {
+    const LEN: usize = 262; // see `test::get_packed_len()` for justification of "262"
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let data = self.try_to_vec().unwrap();
+        dst[..data.len()].copy_from_slice(&data);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let mut mut_src: &[u8] = src;
+        Self::deserialize(&mut mut_src).map_err(|err| {
+            msg!(
+                "Error: failed to deserialize registry config account: {}",
+                err
+            );
+            ProgramError::InvalidAccountData
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn get_packed_len() {
+        // The `Participant` layout predates `ParticipantState::Suspended`
         assert_eq!(
             Participant::get_packed_len(),
-            solana_program::borsh::get_packed_len::<Participant>()
+            Participant {
+                state: ParticipantState::Approved,
+                ..Participant::default()
+            }
+            .try_to_vec()
+            .unwrap()
+            .len()
+        );
+        assert_eq!(
+            ParticipantV2::get_packed_len(),
+            solana_program::borsh::get_packed_len::<ParticipantV2>()
+        );
+        assert_eq!(
+            TestnetIdentityIndex::get_packed_len(),
+            solana_program::borsh::get_packed_len::<TestnetIdentityIndex>()
+        );
+        assert_eq!(
+            RegistryConfig::get_packed_len(),
+            RegistryConfig {
+                is_initialized: true,
+                threshold: 1,
+                admins: vec![Pubkey::new_unique(); MAX_ADMINS],
+            }
+            .try_to_vec()
+            .unwrap()
+            .len()
+        );
+    }
+    #[test]
+    fn participant_layouts() {
+        let participant = ParticipantV2 {
+            testnet_identity: Pubkey::new_unique(),
+            mainnet_identity: Pubkey::new_unique(),
+            state: ParticipantState::Rejected,
+            application_slot: 1,
+            application_epoch: 2,
+            state_change_slot: 3,
+            rejection_reason: RejectionReason::new(RejectionCode::Other, "no contact").unwrap(),
+            info_url: InfoUrl::new("https://example.com").unwrap(),
+        };
+
+        let mut data = vec![0; ParticipantV2::get_packed_len()];
+        pack_participant(&participant, &mut data).unwrap();
+        assert_eq!(unpack_participant(&data), Ok(participant.clone()));
+
+        // The metadata does not fit in the original layout
+        let mut data = vec![0; Participant::get_packed_len()];
+        pack_participant(&participant, &mut data).unwrap();
+        assert_eq!(
+            unpack_participant(&data),
+            Ok(ParticipantV2 {
+                testnet_identity: participant.testnet_identity,
+                mainnet_identity: participant.mainnet_identity,
+                state: ParticipantState::Rejected,
+                ..ParticipantV2::default()
+            })
+        );
+    }
+
+    #[test]
+    fn suspension() {
+        let participant = ParticipantV2 {
+            state: ParticipantState::Suspended { until_epoch: 10 },
+            ..ParticipantV2::default()
+        };
+
+        let mut data = vec![0; ParticipantV2::get_packed_len()];
+        pack_participant(&participant, &mut data).unwrap();
+        assert_eq!(unpack_participant(&data), Ok(participant.clone()));
+
+        let mut data = vec![0; Participant::get_packed_len()];
+        assert_eq!(
+            pack_participant(&participant, &mut data),
+            Err(RegistryError::ParticipantNotMigrated.into())
+        );
+
+        assert_eq!(participant.state.at_epoch(9), participant.state);
+        assert_eq!(participant.state.at_epoch(10), participant.state);
+        assert_eq!(participant.state.at_epoch(11), ParticipantState::Approved);
+        assert_eq!(
+            ParticipantState::Pending.at_epoch(11),
+            ParticipantState::Pending
+        );
+    }
+
+    #[test]
+    fn text_fields() {
+        let reason = RejectionReason::new(RejectionCode::Other, "no contact").unwrap();
+        assert_eq!(reason.text(), "no contact");
+        assert_eq!(RejectionReason::default().text(), "");
+        assert!(RejectionReason::new(
+            RejectionCode::Other,
+            &"x".repeat(MAX_REJECTION_TEXT_LEN + 1)
+        )
+        .is_err());
+
+        assert_eq!(
+            InfoUrl::new("https://example.com").unwrap().url(),
+            Some("https://example.com".to_string())
         );
+        assert_eq!(InfoUrl::default().url(), None);
+        assert!(InfoUrl::new(&"x".repeat(MAX_INFO_URL_LEN)).is_ok());
+        assert!(InfoUrl::new(&"x".repeat(MAX_INFO_URL_LEN + 1)).is_err());
     }
 }
@@ -0,0 +1,1008 @@
+use {
+    assert_matches::*,
+    registry_program::{
+        error::RegistryError,
+        find_config_address, find_participant_address, find_testnet_identity_index_address, id,
+        instruction::*,
+        processor::process_instruction,
+        state::{
+            InfoUrl, Participant, ParticipantState, ParticipantV2, RegistryConfig, RejectionCode,
+            RejectionReason, TestnetIdentityIndex,
+        },
+    },
+    solana_program::{
+        instruction::{Instruction, InstructionError},
+        program_pack::Pack,
+        pubkey::Pubkey,
+        sysvar::rent::Rent,
+    },
+    solana_program_test::*,
+    solana_sdk::{
+        account::Account,
+        hash::Hash,
+        signature::{Keypair, Signer},
+        transaction::{Transaction, TransactionError},
+        transport::TransportError,
+    },
+};
+
+/// A registry with its config account initialized with a single admin and a threshold of 1,
+/// plus any additional accounts
+async fn setup(admin: &Keypair, accounts: Vec<(Pubkey, Account)>) -> (BanksClient, Keypair, Hash) {
+    let mut program_test =
+        ProgramTest::new("registry_program", id(), processor!(process_instruction));
+
+    let mut data = vec![0; RegistryConfig::get_packed_len()];
+    RegistryConfig {
+        is_initialized: true,
+        threshold: 1,
+        admins: vec![admin.pubkey()],
+    }
+    .pack_into_slice(&mut data);
+    program_test.add_account(
+        find_config_address(&id()).0,
+        Account {
+            lamports: Rent::default().minimum_balance(data.len()),
+            data,
+            owner: id(),
+            ..Account::default()
+        },
+    );
+    for (address, account) in accounts {
+        program_test.add_account(address, account);
+    }
+
+    program_test.start().await
+}
+
+/// Process `instructions` in a single transaction paid for by `payer`. Transactions must differ
+/// within a test, as a duplicate of an earlier transaction is rejected regardless of its outcome
+async fn process(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), TransportError> {
+    let recent_blockhash = banks_client.get_recent_blockhash().await.unwrap();
+    let mut transaction = Transaction::new_with_payer(instructions, Some(&payer.pubkey()));
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+    transaction.sign(&all_signers, recent_blockhash);
+    banks_client.process_transaction(transaction).await
+}
+
+fn instruction_error(error: InstructionError) -> TransactionError {
+    TransactionError::InstructionError(0, error)
+}
+
+fn registry_error(error: RegistryError) -> TransactionError {
+    instruction_error(InstructionError::Custom(error as u32))
+}
+
+async fn get_participant(
+    banks_client: &mut BanksClient,
+    mainnet_identity: &Keypair,
+) -> ParticipantV2 {
+    let address = find_participant_address(&id(), &mainnet_identity.pubkey()).0;
+    let account = banks_client.get_account(address).await.unwrap().unwrap();
+    ParticipantV2::unpack_from_slice(&account.data).unwrap()
+}
+
+async fn get_config(banks_client: &mut BanksClient) -> RegistryConfig {
+    banks_client
+        .get_packed_account_data::<RegistryConfig>(find_config_address(&id()).0)
+        .await
+        .unwrap()
+}
+
+async fn apply_participant(
+    banks_client: &mut BanksClient,
+    payer: &Keypair,
+    mainnet_identity: &Keypair,
+    testnet_identity: &Keypair,
+) -> Result<(), TransportError> {
+    process(
+        banks_client,
+        payer,
+        &[apply(
+            payer.pubkey(),
+            mainnet_identity.pubkey(),
+            testnet_identity.pubkey(),
+        )],
+        &[mainnet_identity, testnet_identity],
+    )
+    .await
+}
+
+fn participant_of(mainnet_identity: &Keypair, testnet_identity: &Keypair) -> Participant {
+    Participant {
+        testnet_identity: testnet_identity.pubkey(),
+        mainnet_identity: mainnet_identity.pubkey(),
+        state: ParticipantState::Pending,
+    }
+}
+
+#[tokio::test]
+async fn test_apply_and_withdraw() {
+    let admin = Keypair::new();
+    let mainnet_identity = Keypair::new();
+    let testnet_identity = Keypair::new();
+    let other_identity = Keypair::new();
+    let refundee = Pubkey::new_unique();
+    let (mut banks_client, payer, _) = setup(&admin, vec![]).await;
+
+    apply_participant(
+        &mut banks_client,
+        &payer,
+        &mainnet_identity,
+        &testnet_identity,
+    )
+    .await
+    .unwrap();
+    let participant = get_participant(&mut banks_client, &mainnet_identity).await;
+    assert_eq!(participant.state, ParticipantState::Pending);
+    assert_eq!(participant.mainnet_identity, mainnet_identity.pubkey());
+    assert_eq!(participant.testnet_identity, testnet_identity.pubkey());
+
+    // Apply twice, failure. The testnet identity funds the accounts to keep the transaction
+    // distinct from the first Apply...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[apply(
+                testnet_identity.pubkey(),
+                mainnet_identity.pubkey(),
+                testnet_identity.pubkey(),
+            )],
+            &[&mainnet_identity, &testnet_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == instruction_error(InstructionError::AccountAlreadyInitialized)
+    );
+
+    // Apply with an already registered testnet identity, failure...
+    assert_matches!(
+        apply_participant(&mut banks_client, &payer, &other_identity, &testnet_identity).await,
+        Err(TransportError::TransactionError(err))
+            if err == instruction_error(InstructionError::AccountAlreadyInitialized)
+    );
+
+    // Withdraw by an unrelated signer, failure...
+    let participant = participant_of(&mainnet_identity, &testnet_identity);
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[withdraw(&participant, other_identity.pubkey(), refundee)],
+            &[&other_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::UnauthorizedIdentity)
+    );
+
+    // Withdraw, refunding the participant and testnet identity index accounts...
+    let participant_address = find_participant_address(&id(), &mainnet_identity.pubkey()).0;
+    let index_address = find_testnet_identity_index_address(&id(), &testnet_identity.pubkey()).0;
+    let mut refund = 0;
+    for address in &[participant_address, index_address] {
+        refund += banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap()
+            .lamports;
+    }
+    assert!(refund > 0);
+
+    process(
+        &mut banks_client,
+        &payer,
+        &[withdraw(&participant, testnet_identity.pubkey(), refundee)],
+        &[&testnet_identity],
+    )
+    .await
+    .unwrap();
+    assert_eq!(banks_client.get_balance(refundee).await.unwrap(), refund);
+    assert_eq!(
+        banks_client.get_account(participant_address).await.unwrap(),
+        None
+    );
+    assert_eq!(banks_client.get_account(index_address).await.unwrap(), None);
+
+    // Withdraw again, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[withdraw(&participant, mainnet_identity.pubkey(), refundee)],
+            &[&mainnet_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::NotParticipantAccount)
+    );
+
+    // The identities are free to apply again...
+    let (other_mainnet_identity, other_testnet_identity) = (Keypair::new(), Keypair::new());
+    process(
+        &mut banks_client,
+        &payer,
+        &[
+            apply(
+                payer.pubkey(),
+                mainnet_identity.pubkey(),
+                other_testnet_identity.pubkey(),
+            ),
+            apply(
+                payer.pubkey(),
+                other_mainnet_identity.pubkey(),
+                testnet_identity.pubkey(),
+            ),
+        ],
+        &[
+            &mainnet_identity,
+            &testnet_identity,
+            &other_mainnet_identity,
+            &other_testnet_identity,
+        ],
+    )
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn test_participant_account_validation() {
+    let admin = Keypair::new();
+    let mainnet_identity = Keypair::new();
+    let testnet_identity = Keypair::new();
+
+    // A participant account that is not rent exempt
+    let underfunded_identity = Keypair::new();
+    let underfunded_participant = find_participant_address(&id(), &underfunded_identity.pubkey()).0;
+    let mut data = vec![0; ParticipantV2::get_packed_len()];
+    ParticipantV2 {
+        mainnet_identity: underfunded_identity.pubkey(),
+        state: ParticipantState::Pending,
+        ..ParticipantV2::default()
+    }
+    .pack_into_slice(&mut data);
+    let underfunded_account = Account {
+        lamports: 1,
+        data,
+        owner: id(),
+        ..Account::default()
+    };
+    let (mut banks_client, payer, _) =
+        setup(&admin, vec![(underfunded_participant, underfunded_account)]).await;
+
+    apply_participant(
+        &mut banks_client,
+        &payer,
+        &mainnet_identity,
+        &testnet_identity,
+    )
+    .await
+    .unwrap();
+    let participant_address = find_participant_address(&id(), &mainnet_identity.pubkey()).0;
+    let index_address = find_testnet_identity_index_address(&id(), &testnet_identity.pubkey()).0;
+    let participant = participant_of(&mainnet_identity, &testnet_identity);
+
+    // Withdraw to the participant account, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[withdraw(
+                &participant,
+                mainnet_identity.pubkey(),
+                participant_address
+            )],
+            &[&mainnet_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::InvalidRefundee)
+    );
+
+    // Withdraw to the testnet identity index account, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[withdraw(&participant, mainnet_identity.pubkey(), index_address)],
+            &[&mainnet_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::InvalidRefundee)
+    );
+
+    // Approve a read-only participant account, failure...
+    let mut instruction = approve(participant_address, &[admin.pubkey()]);
+    instruction.accounts[0].is_writable = false;
+    assert_matches!(
+        process(&mut banks_client, &payer, &[instruction], &[&admin]).await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::ParticipantNotWritable)
+    );
+
+    // Approve a participant account that is not rent exempt, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[approve(underfunded_participant, &[admin.pubkey()])],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::ParticipantNotRentExempt)
+    );
+}
+
+#[tokio::test]
+async fn test_admin_instructions() {
+    let admin = Keypair::new();
+    let mainnet_identity = Keypair::new();
+    let testnet_identity = Keypair::new();
+    let not_admin = Keypair::new();
+
+    // A zeroed participant account owned by the program
+    let uninitialized_participant = find_participant_address(&id(), &Pubkey::new_unique()).0;
+    let uninitialized_account = Account {
+        lamports: Rent::default().minimum_balance(ParticipantV2::get_packed_len()),
+        data: vec![0; ParticipantV2::get_packed_len()],
+        owner: id(),
+        ..Account::default()
+    };
+    let (mut banks_client, payer, _) = setup(
+        &admin,
+        vec![(uninitialized_participant, uninitialized_account)],
+    )
+    .await;
+
+    apply_participant(
+        &mut banks_client,
+        &payer,
+        &mainnet_identity,
+        &testnet_identity,
+    )
+    .await
+    .unwrap();
+    let participant_address = find_participant_address(&id(), &mainnet_identity.pubkey()).0;
+
+    // Approve by a non-admin, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[approve(participant_address, &[not_admin.pubkey()])],
+            &[&not_admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::NotAdmin)
+    );
+
+    // Approve...
+    process(
+        &mut banks_client,
+        &payer,
+        &[approve(participant_address, &[admin.pubkey()])],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_participant(&mut banks_client, &mainnet_identity)
+            .await
+            .state,
+        ParticipantState::Approved
+    );
+
+    // Suspend...
+    process(
+        &mut banks_client,
+        &payer,
+        &[suspend(participant_address, &[admin.pubkey()], 100)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_participant(&mut banks_client, &mainnet_identity)
+            .await
+            .state,
+        ParticipantState::Suspended { until_epoch: 100 }
+    );
+
+    // Reject...
+    let reason = RejectionReason::new(RejectionCode::Other, "unreachable operator").unwrap();
+    process(
+        &mut banks_client,
+        &payer,
+        &[reject(participant_address, &[admin.pubkey()], reason)],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    let participant = get_participant(&mut banks_client, &mainnet_identity).await;
+    assert_eq!(participant.state, ParticipantState::Rejected);
+    assert_eq!(participant.rejection_reason, reason);
+
+    // Suspend a rejected participant, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[suspend(participant_address, &[admin.pubkey()], 101)],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::InvalidStateTransition)
+    );
+
+    // Rewrite...
+    let mut participant = participant_of(&mainnet_identity, &testnet_identity);
+    participant.state = ParticipantState::Approved;
+    process(
+        &mut banks_client,
+        &payer,
+        &[rewrite(
+            participant_address,
+            &[admin.pubkey()],
+            participant.clone(),
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_participant(&mut banks_client, &mainnet_identity)
+            .await
+            .state,
+        ParticipantState::Approved
+    );
+
+    // Rewrite the validator identities, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[rewrite(
+                participant_address,
+                &[admin.pubkey()],
+                Participant {
+                    testnet_identity: Pubkey::new_unique(),
+                    ..participant.clone()
+                }
+            )],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::IdentityChangeNotAllowed)
+    );
+
+    // Rewrite to the uninitialized state, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[rewrite(
+                participant_address,
+                &[admin.pubkey()],
+                Participant {
+                    state: ParticipantState::Uninitialized,
+                    ..participant.clone()
+                }
+            )],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::InvalidStateTransition)
+    );
+
+    // Rewrite by a non-admin, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[rewrite(participant_address, &[not_admin.pubkey()], participant.clone())],
+            &[&not_admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::NotAdmin)
+    );
+
+    // Rewrite an uninitialized account, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[rewrite(
+                uninitialized_participant,
+                &[admin.pubkey()],
+                Participant::default()
+            )],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::UninitializedParticipant)
+    );
+
+    // Rewrite an account the program does not own, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[rewrite(
+                Pubkey::new_unique(),
+                &[admin.pubkey()],
+                Participant::default()
+            )],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::NotParticipantAccount)
+    );
+}
+
+#[tokio::test]
+async fn test_config_instructions() {
+    let admin = Keypair::new();
+    let second_admin = Keypair::new();
+    let mainnet_identity = Keypair::new();
+    let testnet_identity = Keypair::new();
+    let (mut banks_client, payer, _) = setup(&admin, vec![]).await;
+
+    // Initialize the config twice, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[initialize_config(payer.pubkey(), admin.pubkey())],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::ConfigAlreadyInitialized)
+    );
+
+    // Add an admin by a non-admin, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[add_admin(second_admin.pubkey(), &[second_admin.pubkey()])],
+            &[&second_admin],
+        )
+        .await,
+        Err(_)
+    );
+
+    // Add an admin and raise the threshold...
+    process(
+        &mut banks_client,
+        &payer,
+        &[
+            add_admin(second_admin.pubkey(), &[admin.pubkey()]),
+            set_admin_threshold(2, &[admin.pubkey()]),
+        ],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    let config = get_config(&mut banks_client).await;
+    assert_eq!(config.admins, vec![admin.pubkey(), second_admin.pubkey()]);
+    assert_eq!(config.threshold, 2);
+
+    apply_participant(
+        &mut banks_client,
+        &payer,
+        &mainnet_identity,
+        &testnet_identity,
+    )
+    .await
+    .unwrap();
+    let participant_address = find_participant_address(&id(), &mainnet_identity.pubkey()).0;
+
+    // Approve below the threshold, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[approve(participant_address, &[admin.pubkey()])],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::InsufficientAdminSignatures)
+    );
+
+    // Approve with both admins...
+    process(
+        &mut banks_client,
+        &payer,
+        &[approve(
+            participant_address,
+            &[admin.pubkey(), second_admin.pubkey()],
+        )],
+        &[&admin, &second_admin],
+    )
+    .await
+    .unwrap();
+
+    // Remove an admin that the threshold requires, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[remove_admin(
+                second_admin.pubkey(),
+                &[admin.pubkey(), second_admin.pubkey()],
+            )],
+            &[&admin, &second_admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::InvalidAdminThreshold)
+    );
+
+    // Lower the threshold and remove the admin...
+    process(
+        &mut banks_client,
+        &payer,
+        &[
+            set_admin_threshold(1, &[admin.pubkey(), second_admin.pubkey()]),
+            remove_admin(second_admin.pubkey(), &[admin.pubkey()]),
+        ],
+        &[&admin, &second_admin],
+    )
+    .await
+    .unwrap();
+    let config = get_config(&mut banks_client).await;
+    assert_eq!(config.admins, vec![admin.pubkey()]);
+    assert_eq!(config.threshold, 1);
+}
+
+#[tokio::test]
+async fn test_participant_instructions() {
+    let admin = Keypair::new();
+    let mainnet_identity = Keypair::new();
+    let testnet_identity = Keypair::new();
+    let new_mainnet_identity = Keypair::new();
+    let new_testnet_identity = Keypair::new();
+    let other_identity = Keypair::new();
+    let (mut banks_client, payer, _) = setup(&admin, vec![]).await;
+
+    apply_participant(
+        &mut banks_client,
+        &payer,
+        &mainnet_identity,
+        &testnet_identity,
+    )
+    .await
+    .unwrap();
+    let participant_address = find_participant_address(&id(), &mainnet_identity.pubkey()).0;
+
+    // Set the info URL by an unrelated signer, failure...
+    let info_url = InfoUrl::new("https://example.com").unwrap();
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[set_info_url(
+                participant_address,
+                other_identity.pubkey(),
+                info_url
+            )],
+            &[&other_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::UnauthorizedIdentity)
+    );
+
+    // Set the info URL...
+    process(
+        &mut banks_client,
+        &payer,
+        &[set_info_url(
+            participant_address,
+            testnet_identity.pubkey(),
+            info_url,
+        )],
+        &[&testnet_identity],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        get_participant(&mut banks_client, &mainnet_identity)
+            .await
+            .info_url,
+        info_url
+    );
+
+    // Update the identity by an unrelated signer, failure...
+    let participant = participant_of(&mainnet_identity, &testnet_identity);
+    let mut instruction = update_identity(
+        payer.pubkey(),
+        &participant,
+        Network::Testnet,
+        new_testnet_identity.pubkey(),
+        &[],
+    );
+    instruction.accounts[1].pubkey = other_identity.pubkey();
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[instruction],
+            &[&other_identity, &new_testnet_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::UnauthorizedIdentity)
+    );
+
+    // Approve...
+    process(
+        &mut banks_client,
+        &payer,
+        &[approve(participant_address, &[admin.pubkey()])],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+
+    // Update the testnet identity, with a non-admin signing to skip the review, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[update_identity(
+                payer.pubkey(),
+                &participant,
+                Network::Testnet,
+                new_testnet_identity.pubkey(),
+                &[other_identity.pubkey()],
+            )],
+            &[&testnet_identity, &new_testnet_identity, &other_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::NotAdmin)
+    );
+
+    // Update the testnet identity, with an admin signing to skip the review...
+    process(
+        &mut banks_client,
+        &payer,
+        &[update_identity(
+            payer.pubkey(),
+            &participant,
+            Network::Testnet,
+            new_testnet_identity.pubkey(),
+            &[admin.pubkey()],
+        )],
+        &[&testnet_identity, &new_testnet_identity, &admin],
+    )
+    .await
+    .unwrap();
+    let updated_participant = get_participant(&mut banks_client, &mainnet_identity).await;
+    assert_eq!(
+        updated_participant.testnet_identity,
+        new_testnet_identity.pubkey()
+    );
+    assert_eq!(updated_participant.state, ParticipantState::Approved);
+    assert_eq!(
+        banks_client
+            .get_packed_account_data::<TestnetIdentityIndex>(
+                find_testnet_identity_index_address(&id(), &new_testnet_identity.pubkey()).0
+            )
+            .await
+            .unwrap(),
+        TestnetIdentityIndex {
+            participant: participant_address
+        }
+    );
+
+    // Update the mainnet identity, returning the approved participant to review...
+    let participant = participant_of(&mainnet_identity, &new_testnet_identity);
+    process(
+        &mut banks_client,
+        &payer,
+        &[update_identity(
+            payer.pubkey(),
+            &participant,
+            Network::Mainnet,
+            new_mainnet_identity.pubkey(),
+            &[],
+        )],
+        &[&mainnet_identity, &new_mainnet_identity],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        banks_client.get_account(participant_address).await.unwrap(),
+        None
+    );
+    let participant = get_participant(&mut banks_client, &new_mainnet_identity).await;
+    assert_eq!(participant.state, ParticipantState::Pending);
+    assert_eq!(participant.info_url, info_url);
+}
+
+#[tokio::test]
+async fn test_import_and_migrate() {
+    let admin = Keypair::new();
+    let not_admin = Keypair::new();
+    let imported = participant_of(&Keypair::new(), &Keypair::new());
+    let legacy = Participant {
+        state: ParticipantState::Approved,
+        ..participant_of(&Keypair::new(), &Keypair::new())
+    };
+
+    let legacy_address = Pubkey::new_unique();
+    let mut data = vec![0; Participant::get_packed_len()];
+    legacy.pack_into_slice(&mut data);
+    let legacy_account = Account {
+        lamports: Rent::default().minimum_balance(data.len()),
+        data,
+        owner: id(),
+        ..Account::default()
+    };
+    let (mut banks_client, payer, _) = setup(&admin, vec![(legacy_address, legacy_account)]).await;
+
+    // Import by a non-admin, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[import(
+                payer.pubkey(),
+                &[not_admin.pubkey()],
+                imported.clone()
+            )],
+            &[&not_admin],
+        )
+        .await,
+        Err(_)
+    );
+
+    // Import in the uninitialized state, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[import(
+                payer.pubkey(),
+                &[admin.pubkey()],
+                Participant {
+                    state: ParticipantState::Uninitialized,
+                    ..imported.clone()
+                }
+            )],
+            &[&admin],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::InvalidStateTransition)
+    );
+
+    // Import...
+    process(
+        &mut banks_client,
+        &payer,
+        &[import(payer.pubkey(), &[admin.pubkey()], imported.clone())],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    let account = banks_client
+        .get_account(find_participant_address(&id(), &imported.mainnet_identity).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        Participant::from(&ParticipantV2::unpack_from_slice(&account.data).unwrap()),
+        imported
+    );
+
+    // Migrate...
+    process(
+        &mut banks_client,
+        &payer,
+        &[migrate(
+            payer.pubkey(),
+            &[admin.pubkey()],
+            legacy_address,
+            &legacy,
+        )],
+        &[&admin],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        banks_client.get_account(legacy_address).await.unwrap(),
+        None
+    );
+    let account = banks_client
+        .get_account(find_participant_address(&id(), &legacy.mainnet_identity).0)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.data.len(), ParticipantV2::get_packed_len());
+    assert_eq!(
+        Participant::from(&ParticipantV2::unpack_from_slice(&account.data).unwrap()),
+        legacy
+    );
+}
+
+#[tokio::test]
+async fn test_withdraw_legacy() {
+    let admin = Keypair::new();
+    let mainnet_identity = Keypair::new();
+    let testnet_identity = Keypair::new();
+    let other_identity = Keypair::new();
+    let refundee = Pubkey::new_unique();
+    let legacy = participant_of(&mainnet_identity, &testnet_identity);
+
+    let legacy_address = Pubkey::new_unique();
+    let mut data = vec![0; Participant::get_packed_len()];
+    legacy.pack_into_slice(&mut data);
+    let rent = Rent::default().minimum_balance(data.len());
+    let legacy_account = Account {
+        lamports: rent,
+        data,
+        owner: id(),
+        ..Account::default()
+    };
+    let (mut banks_client, payer, _) = setup(&admin, vec![(legacy_address, legacy_account)]).await;
+
+    // Withdraw by an unrelated identity, failure...
+    assert_matches!(
+        process(
+            &mut banks_client,
+            &payer,
+            &[withdraw_account(
+                legacy_address,
+                &legacy,
+                other_identity.pubkey(),
+                refundee
+            )],
+            &[&other_identity],
+        )
+        .await,
+        Err(TransportError::TransactionError(err))
+            if err == registry_error(RegistryError::UnauthorizedIdentity)
+    );
+
+    // Withdraw without migrating first...
+    process(
+        &mut banks_client,
+        &payer,
+        &[withdraw_account(
+            legacy_address,
+            &legacy,
+            testnet_identity.pubkey(),
+            refundee,
+        )],
+        &[&testnet_identity],
+    )
+    .await
+    .unwrap();
+    assert_eq!(
+        banks_client.get_account(legacy_address).await.unwrap(),
+        None
+    );
+    assert_eq!(banks_client.get_balance(refundee).await.unwrap(), rent);
+}